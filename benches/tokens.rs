@@ -0,0 +1,59 @@
+//! Times tokenizing and parsing command lines with hundreds to thousands
+//! of flags. `cargo bench` prints the time per input and per flag, which
+//! stays flat as the input grows since the words are read in one pass,
+//! next to the tokenizer that pass replaced, which grows quadratically.
+
+use args::{Accumulation, ParserBuilder, TokensIterator};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: u32 = 20;
+
+/// The mean time of `RUNS` calls of `f`.
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..RUNS {
+        f();
+    }
+    start.elapsed() / RUNS
+}
+
+/// The flags of `input` counted the way the tokenizer read them before
+/// lexing in one pass: splitting the input at every `-` again, from the
+/// start, for each flag.
+fn resplit_count(input: &str) -> usize {
+    let (mut cursor, mut count) = (0, 0);
+    while let Some(segment) = input.split('-').nth(cursor) {
+        cursor += 1;
+        if !segment.is_empty() {
+            let words: Vec<String> =
+                segment.split(' ').filter(|word| !word.is_empty()).map(String::from).collect();
+            black_box(words);
+            count += 1;
+        }
+    }
+    count
+}
+
+fn main() {
+    let parser = ParserBuilder::new("l,p#,d[*]")
+        .duplicates(Accumulation::Replace)
+        .build()
+        .unwrap();
+    println!(
+        "{:>6} {:>12} {:>12} {:>12} {:>12}",
+        "flags", "tokens", "resplit", "parse", "per flag"
+    );
+    for flags in [100, 200, 400, 800, 1600, 3200] {
+        let words: Vec<String> = (0..flags / 2).map(|i| format!("-d v{} -p {}", i, i)).collect();
+        let input = words.join(" ");
+        let tokens = time(|| assert_eq!(TokensIterator::new(&input).count(), flags));
+        let resplit = time(|| assert_eq!(resplit_count(&input), flags));
+        let parse = time(|| assert!(parser.parse(&input).is_ok()));
+        let per_flag = parse / flags as u32;
+        println!(
+            "{:>6} {:>12?} {:>12?} {:>12?} {:>12?}",
+            flags, tokens, resplit, parse, per_flag
+        );
+    }
+}
@@ -0,0 +1,43 @@
+/* C interface to the args parser, built with `--features ffi`; see
+ * src/ffi.rs for the details of each function. */
+
+#ifndef ARGS_H
+#define ARGS_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+#define ARGS_OK 0
+#define ARGS_ERR_SCHEMA 1
+#define ARGS_ERR_UNKNOWN_ARG 2
+#define ARGS_ERR_INVALID_VALUE 3
+#define ARGS_ERR_MISSING 4
+#define ARGS_ERR_CONFLICT 5
+#define ARGS_ERR_WRONG_TYPE 6
+#define ARGS_ERR_HELP 7
+#define ARGS_ERR_INPUT 8
+#define ARGS_ERR_OTHER 9
+
+typedef struct ArgsResult ArgsResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+int args_parse(const char *schema, const char *input, ArgsResult **out);
+
+const char *args_error_message(const ArgsResult *result);
+
+int args_get_bool(const ArgsResult *result, const char *name, bool *out);
+
+int args_get_number(const ArgsResult *result, const char *name, int64_t *out);
+
+int args_get_str(const ArgsResult *result, const char *name, const char **out);
+
+void args_free(ArgsResult *result);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* ARGS_H */
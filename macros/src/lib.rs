@@ -0,0 +1,548 @@
+//! Compile-time companions for the `args` crate, enabled through its
+//! `macros` feature.
+extern crate proc_macro;
+
+use proc_macro::{Delimiter, Literal, Span, TokenStream, TokenTree};
+
+/// Checks a literal schema definition at compile time and expands to the
+/// same `&'static str`, so `schema_str!("d*,p!")` is a build error instead
+/// of a runtime `UnsupportedArgType`.
+#[proc_macro]
+pub fn schema_str(input: TokenStream) -> TokenStream {
+    let mut tokens = input.into_iter();
+    let literal = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Literal(literal)), None) => literal,
+        (Some(other), _) => return compile_error("expected a string literal", other.span()),
+        (None, _) => return compile_error("expected a string literal", Span::call_site()),
+    };
+    let definition = match unquote(&literal.to_string()) {
+        Some(definition) => definition,
+        None => return compile_error("expected a string literal", literal.span()),
+    };
+    match validate(&definition) {
+        Ok(()) => {
+            let mut expanded = Literal::string(&definition);
+            expanded.set_span(literal.span());
+            TokenTree::Literal(expanded).into()
+        }
+        Err(message) => compile_error(&message, literal.span()),
+    }
+}
+
+/// Implements `FromArgs` and `ArgsSchema` for a struct with named fields,
+/// declaring one argument per field. A field `log_file` is read from
+/// `--log-file`, and its type decides the argument's: `bool` is a switch,
+/// integers are numbers, `Vec<_>` a list and anything else a string parsed
+/// with `FromStr`. Fields that aren't `Option`s, `bool`s or lists are
+/// required unless they have a default. Doc comments become descriptions,
+/// and `#[arg(name = "p", default = "80", description = "...")]`
+/// overrides any of them.
+#[proc_macro_derive(FromArgs, attributes(arg))]
+pub fn derive_from_args(input: TokenStream) -> TokenStream {
+    match derive(input) {
+        Ok(tokens) => tokens,
+        Err((message, span)) => compile_error(&message, span),
+    }
+}
+
+type DeriveResult<T> = Result<T, (String, Span)>;
+
+/// A field of a struct deriving `FromArgs`.
+struct Field {
+    ident: String,
+    name: String,
+    ty: String,
+    default: Option<String>,
+    description: Option<String>,
+    span: Span,
+}
+
+const INTEGERS: &[&str] = &[
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+];
+
+fn derive(input: TokenStream) -> DeriveResult<TokenStream> {
+    let mut tokens = input.into_iter().peekable();
+    let mut name = None;
+    let mut body = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "struct" => match tokens.next() {
+                Some(TokenTree::Ident(ident)) => name = Some(ident),
+                _ => return Err(("expected a struct name".to_string(), ident.span())),
+            },
+            TokenTree::Ident(ident) if ["enum", "union"].contains(&ident.to_string().as_str()) => {
+                return Err(("FromArgs can only be derived for structs".to_string(), ident.span()))
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '<' && name.is_some() => {
+                return Err(("generic structs are not supported".to_string(), punct.span()))
+            }
+            TokenTree::Group(group)
+                if name.is_some() && group.delimiter() == Delimiter::Brace =>
+            {
+                body = Some(group);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let name = name.ok_or_else(|| ("expected a struct".to_string(), Span::call_site()))?;
+    let body = body.ok_or_else(|| ("expected named fields".to_string(), name.span()))?;
+    let fields = split_fields(body.stream())
+        .into_iter()
+        .map(parse_field)
+        .collect::<DeriveResult<Vec<_>>>()?;
+    if fields.is_empty() {
+        return Err(("struct has no fields to derive arguments from".to_string(), name.span()));
+    }
+    for (i, field) in fields.iter().enumerate() {
+        if fields[..i].iter().any(|other| other.name == field.name) {
+            return Err((format!("argument '{}' is declared twice", field.name), field.span));
+        }
+    }
+    let mut builder = String::from("::args::Schema::builder()");
+    let mut reads = String::new();
+    for field in &fields {
+        let (declare, read) = field_kind(field)?;
+        builder.push_str(&format!(".{}({:?})", declare, field.name));
+        match &field.default {
+            Some(default) => builder.push_str(&format!(".default({:?})", default)),
+            None if read == "get_as" => builder.push_str(".required()"),
+            None => {}
+        }
+        if let Some(description) = &field.description {
+            builder.push_str(&format!(".description({:?})", description));
+        }
+        reads.push_str(&format!("{}: args.{}({:?})?,", field.ident, read, field.name));
+    }
+    let code = format!(
+        "impl ::args::FromArgs for {name} {{\
+             fn from_args(args: &::args::ParsedArgs) \
+                 -> ::std::result::Result<Self, ::args::ParseErr> {{\
+                 ::std::result::Result::Ok({name} {{ {reads} }})\
+             }}\
+         }}\
+         impl ::args::ArgsSchema for {name} {{\
+             fn schema() -> ::args::Schema {{\
+                 {builder}.build().expect(\"schema derived from {name}\")\
+             }}\
+         }}",
+        name = name,
+        reads = reads,
+        builder = builder
+    );
+    Ok(code.parse().unwrap())
+}
+
+/// Implements `ArgEnum` and `FromStr` for an enum of unit variants, one
+/// choice per variant. A variant `JsonLines` is written `json-lines`, and
+/// `#[arg(name = "jsonl")]` overrides it. `FromStr` matches regardless of
+/// case and fails with the list of choices.
+#[proc_macro_derive(ArgEnum, attributes(arg))]
+pub fn derive_arg_enum(input: TokenStream) -> TokenStream {
+    match derive_enum(input) {
+        Ok(tokens) => tokens,
+        Err((message, span)) => compile_error(&message, span),
+    }
+}
+
+fn derive_enum(input: TokenStream) -> DeriveResult<TokenStream> {
+    let mut tokens = input.into_iter();
+    let mut name = None;
+    let mut body = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "enum" => match tokens.next() {
+                Some(TokenTree::Ident(ident)) => name = Some(ident),
+                _ => return Err(("expected an enum name".to_string(), ident.span())),
+            },
+            TokenTree::Ident(ident) if matches!(ident.to_string().as_str(), "struct" | "union") => {
+                return Err(("ArgEnum can only be derived for enums".to_string(), ident.span()))
+            }
+            TokenTree::Punct(punct) if punct.as_char() == '<' && name.is_some() => {
+                return Err(("generic enums are not supported".to_string(), punct.span()))
+            }
+            TokenTree::Group(group)
+                if name.is_some() && group.delimiter() == Delimiter::Brace =>
+            {
+                body = Some(group);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let name = name.ok_or_else(|| ("expected an enum".to_string(), Span::call_site()))?;
+    let body = body.ok_or_else(|| ("expected variants".to_string(), name.span()))?;
+    let variants = split_fields(body.stream())
+        .into_iter()
+        .map(parse_variant)
+        .collect::<DeriveResult<Vec<_>>>()?;
+    if variants.is_empty() {
+        return Err(("enum has no variants to choose from".to_string(), name.span()));
+    }
+    let mut names = String::new();
+    let mut arms = String::new();
+    for (i, (ident, value, span)) in variants.iter().enumerate() {
+        let folded = value.to_lowercase();
+        if variants[..i].iter().any(|(_, other, _)| other.to_lowercase() == folded) {
+            return Err((format!("choice '{}' is declared twice", value), *span));
+        }
+        names.push_str(&format!("{:?},", value));
+        arms.push_str(&format!(
+            "{:?} => ::std::result::Result::Ok({}::{}),",
+            folded, name, ident
+        ));
+    }
+    let code = format!(
+        "impl ::args::ArgEnum for {name} {{\
+             const VARIANTS: &'static [&'static str] = &[{names}];\
+         }}\
+         impl ::std::str::FromStr for {name} {{\
+             type Err = ::std::string::String;\
+             fn from_str(value: &str) -> ::std::result::Result<Self, Self::Err> {{\
+                 match value.to_lowercase().as_str() {{\
+                     {arms}\
+                     _ => ::std::result::Result::Err(::std::format!(\
+                         \"expected one of: {{}}\",\
+                         <Self as ::args::ArgEnum>::VARIANTS.join(\", \")\
+                     )),\
+                 }}\
+             }}\
+         }}",
+        name = name,
+        names = names,
+        arms = arms
+    );
+    Ok(code.parse().unwrap())
+}
+
+/// A unit variant of an enum deriving `ArgEnum`: its identifier, the choice
+/// it is written as, and where it is.
+fn parse_variant(tokens: Vec<TokenTree>) -> DeriveResult<(String, String, Span)> {
+    let mut tokens = tokens.into_iter();
+    let mut attrs = Field {
+        ident: String::new(),
+        name: String::new(),
+        ty: String::new(),
+        default: None,
+        description: None,
+        span: Span::call_site(),
+    };
+    let mut name = None;
+    let ident = loop {
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => match tokens.next() {
+                Some(TokenTree::Group(attr)) => {
+                    parse_attribute(attr.stream(), &mut name, &mut attrs, &mut vec![])?
+                }
+                _ => return Err(("expected an attribute".to_string(), punct.span())),
+            },
+            Some(TokenTree::Ident(ident)) => break ident,
+            Some(other) => return Err(("expected a variant".to_string(), other.span())),
+            None => return Err(("expected a variant".to_string(), Span::call_site())),
+        }
+    };
+    if attrs.default.is_some() || attrs.description.is_some() {
+        return Err(("only `name` applies to a variant".to_string(), ident.span()));
+    }
+    if let Some(TokenTree::Group(group)) = tokens.next() {
+        return Err(("ArgEnum variants can't have fields".to_string(), group.span()));
+    }
+    let choice = name.unwrap_or_else(|| choice_name(ident.to_string().trim_start_matches("r#")));
+    Ok((ident.to_string(), choice, ident.span()))
+}
+
+/// How the variant `ident` is written on the command line: lowercase, with
+/// a dash where a new word starts, `json-lines` for `JsonLines`.
+fn choice_name(ident: &str) -> String {
+    let mut choice = String::new();
+    let mut prev: Option<char> = None;
+    for c in ident.chars() {
+        if c.is_uppercase() && prev.is_some_and(|p| p.is_lowercase() || p.is_ascii_digit()) {
+            choice.push('-');
+        }
+        choice.extend(c.to_lowercase());
+        prev = Some(c);
+    }
+    choice
+}
+
+/// The tokens of each field, split at the commas between them. Commas
+/// within `<...>` belong to a type.
+fn split_fields(stream: TokenStream) -> Vec<Vec<TokenTree>> {
+    let mut fields = vec![vec![]];
+    let mut depth = 0;
+    for token in stream {
+        match &token {
+            TokenTree::Punct(punct) if punct.as_char() == '<' => depth += 1,
+            TokenTree::Punct(punct) if punct.as_char() == '>' => depth -= 1,
+            TokenTree::Punct(punct) if punct.as_char() == ',' && depth == 0 => {
+                fields.push(vec![]);
+                continue;
+            }
+            _ => {}
+        }
+        fields.last_mut().unwrap().push(token);
+    }
+    fields.retain(|field| !field.is_empty());
+    fields
+}
+
+fn parse_field(tokens: Vec<TokenTree>) -> DeriveResult<Field> {
+    let mut tokens = tokens.into_iter().peekable();
+    let mut field = Field {
+        ident: String::new(),
+        name: String::new(),
+        ty: String::new(),
+        default: None,
+        description: None,
+        span: Span::call_site(),
+    };
+    let mut name = None;
+    let mut docs: Vec<String> = vec![];
+    loop {
+        match tokens.next() {
+            Some(TokenTree::Punct(punct)) if punct.as_char() == '#' => match tokens.next() {
+                Some(TokenTree::Group(attr)) => {
+                    parse_attribute(attr.stream(), &mut name, &mut field, &mut docs)?
+                }
+                _ => return Err(("expected an attribute".to_string(), punct.span())),
+            },
+            Some(TokenTree::Ident(ident)) if ident.to_string() == "pub" => {
+                if let Some(TokenTree::Group(_)) = tokens.peek() {
+                    tokens.next();
+                }
+            }
+            Some(TokenTree::Ident(ident)) => {
+                field.ident = ident.to_string();
+                field.span = ident.span();
+                break;
+            }
+            Some(other) => return Err(("expected a named field".to_string(), other.span())),
+            None => return Err(("expected a named field".to_string(), Span::call_site())),
+        }
+    }
+    match tokens.next() {
+        Some(TokenTree::Punct(punct)) if punct.as_char() == ':' => {}
+        _ => return Err(("expected a named field".to_string(), field.span)),
+    }
+    field.ty = tokens.map(|token| token.to_string()).collect();
+    let ident = field.ident.trim_start_matches("r#");
+    field.name = name.unwrap_or_else(|| ident.replace('_', "-"));
+    if field.description.is_none() && !docs.is_empty() {
+        field.description = Some(docs.join(" "));
+    }
+    Ok(field)
+}
+
+/// Reads `arg(key = "value", ...)` into `field`, and doc comments into
+/// `docs`. Other attributes are left alone.
+fn parse_attribute(
+    stream: TokenStream,
+    name: &mut Option<String>,
+    field: &mut Field,
+    docs: &mut Vec<String>,
+) -> DeriveResult<()> {
+    let mut tokens = stream.into_iter();
+    match tokens.next() {
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "doc" => {
+            if let (Some(_), Some(TokenTree::Literal(doc))) = (tokens.next(), tokens.next()) {
+                let doc = unquote(&doc.to_string()).unwrap_or_default();
+                if !doc.trim().is_empty() {
+                    docs.push(doc.trim().to_string());
+                }
+            }
+            Ok(())
+        }
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "arg" => {
+            let group = match tokens.next() {
+                Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis => {
+                    group
+                }
+                _ => return Err(("expected #[arg(...)]".to_string(), ident.span())),
+            };
+            let entries: Vec<TokenTree> = group.stream().into_iter().collect();
+            let comma = |token: &TokenTree| matches!(token, TokenTree::Punct(p) if p.as_char() == ',');
+            for entry in entries.split(comma) {
+                let (key, value) = match entry {
+                    [] => continue,
+                    [TokenTree::Ident(key), TokenTree::Punct(eq), TokenTree::Literal(value)]
+                        if eq.as_char() == '=' =>
+                    {
+                        (key, value)
+                    }
+                    _ => {
+                        let message = "expected key = \"value\"".to_string();
+                        return Err((message, entry[0].span()));
+                    }
+                };
+                let value = unquote(&value.to_string())
+                    .ok_or_else(|| ("expected a string literal".to_string(), value.span()))?;
+                match key.to_string().as_str() {
+                    "name" => *name = Some(value),
+                    "default" => field.default = Some(value),
+                    "description" => field.description = Some(value),
+                    other => {
+                        return Err((format!("unknown key '{}' in #[arg]", other), key.span()))
+                    }
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The builder method declaring `field` and the `ParsedArgs` accessor that
+/// reads it back.
+fn field_kind(field: &Field) -> DeriveResult<(&'static str, &'static str)> {
+    let unwrap = |ty: &str, wrapper: &str| {
+        let inner = ty.strip_prefix(wrapper)?.strip_prefix('<')?.strip_suffix('>')?;
+        let inner = inner.to_string();
+        Some(inner)
+    };
+    let ty = field.ty.trim_start_matches("::std::option::").trim_start_matches("std::option::");
+    let number = |ty: &str| INTEGERS.contains(&ty);
+    let kind = if ty == "bool" {
+        Some(("bool", "try_get_bool"))
+    } else if let Some(inner) = unwrap(ty, "Option") {
+        match inner.as_str() {
+            inner if number(inner) => Some(("number", "get_opt_as")),
+            "bool" => None,
+            inner if inner.starts_with("Vec<") => None,
+            _ => Some(("string", "get_opt_as")),
+        }
+    } else if let Some(inner) = unwrap(ty, "Vec") {
+        match inner.as_str() {
+            inner if number(inner) => Some(("number_list", "get_list_as")),
+            "bool" => None,
+            _ => Some(("string_list", "get_list_as")),
+        }
+    } else if number(ty) {
+        Some(("number", "get_as"))
+    } else {
+        Some(("string", "get_as"))
+    };
+    kind.ok_or_else(|| (format!("unsupported field type '{}'", field.ty), field.span))
+}
+
+fn compile_error(message: &str, span: Span) -> TokenStream {
+    let tokens: TokenStream = format!("compile_error!({:?})", message).parse().unwrap();
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+/// The value of a string literal as written in source, `"..."` or `r#"..."#`.
+fn unquote(source: &str) -> Option<String> {
+    if let Some(raw) = source.strip_prefix('r') {
+        let hashes = raw.len() - raw.trim_start_matches('#').len();
+        let body = raw.get(hashes..raw.len().checked_sub(hashes)?)?;
+        return Some(body.strip_prefix('"')?.strip_suffix('"')?.to_string());
+    }
+    let body = source.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'n' => value.push('\n'),
+            'r' => value.push('\r'),
+            't' => value.push('\t'),
+            '0' => value.push('\0'),
+            '\n' => {
+                let rest = chars.as_str().trim_start();
+                chars = rest.chars();
+            }
+            c @ '\\' | c @ '"' | c @ '\'' => value.push(c),
+            _ => return None,
+        }
+    }
+    Some(value)
+}
+
+/// Mirrors `Schema::new`: entries separated by commas or whitespace, `#`
+/// comments at the start of a word, one-character names followed by a type
+/// marker.
+fn validate(definition: &str) -> Result<(), String> {
+    let mut entries = 0;
+    for line in definition.lines() {
+        let mut prev = None;
+        let mut end = line.len();
+        for (i, c) in line.char_indices() {
+            if c == '#' && prev.is_none_or(|p: char| p == ',' || p.is_whitespace()) {
+                end = i;
+                break;
+            }
+            prev = Some(c);
+        }
+        let tokens = line[..end].split(|c: char| c == ',' || c.is_whitespace());
+        for token in tokens.filter(|token| !token.is_empty()) {
+            let positional = token.strip_suffix("...").unwrap_or(token);
+            if let Some(name) = positional.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+                if name.is_empty() {
+                    return Err("positional argument without a name".to_string());
+                }
+                entries += 1;
+                continue;
+            }
+            let name_len = token.chars().next().map_or(0, char::len_utf8);
+            match &token[name_len..] {
+                "" | "*" | "*?" | "#" | "[*]" | "[#]" => entries += 1,
+                marker => {
+                    return Err(format!(
+                        "unsupported argument type '{}' in '{}'",
+                        marker, token
+                    ))
+                }
+            }
+        }
+    }
+    if entries == 0 {
+        return Err("schema defines no arguments".to_string());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_valid_schemas() {
+        assert_eq!(validate("l,p#,d*,s[*],n[#]"), Ok(()));
+        assert_eq!(validate("l\np#  # port\n"), Ok(()));
+        assert_eq!(validate("c*?,<input>,<files>..."), Ok(()));
+    }
+
+    #[test]
+    fn rejects_invalid_schemas() {
+        assert_eq!(
+            validate("d*,p!"),
+            Err("unsupported argument type '!' in 'p!'".to_string())
+        );
+        assert_eq!(validate(" # nothing\n"), Err("schema defines no arguments".to_string()));
+    }
+
+    #[test]
+    fn names_choices_after_variants() {
+        assert_eq!(choice_name("Json"), "json");
+        assert_eq!(choice_name("JsonLines"), "json-lines");
+        assert_eq!(choice_name("TOML"), "toml");
+        assert_eq!(choice_name("Utf8Bom"), "utf8-bom");
+    }
+
+    #[test]
+    fn unquotes_literals() {
+        assert_eq!(unquote(r#""d*,\np#""#).as_deref(), Some("d*,\np#"));
+        assert_eq!(unquote(r###"r#"d*,"p#"#"###).as_deref(), Some("d*,\"p#"));
+        assert_eq!(unquote("42"), None);
+    }
+}
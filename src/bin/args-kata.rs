@@ -0,0 +1,90 @@
+//! `args-kata`: checks schema definitions and tries them out from the
+//! shell, without writing a program around them.
+
+use args::{Schema, Shell};
+use std::process;
+
+const USAGE: &str = "usage: args-kata check <schema>
+       args-kata parse <schema> [word...]
+       args-kata help <schema> [program]
+       args-kata completions <schema> <bash|zsh|fish> [program]";
+
+fn main() {
+    let words: Vec<String> = std::env::args().skip(1).collect();
+    match run(&words) {
+        Ok(output) => println!("{}", output),
+        Err(message) => {
+            eprintln!("{}", message);
+            process::exit(2);
+        }
+    }
+}
+
+/// What the subcommand in `words` prints, or why it failed.
+fn run(words: &[String]) -> Result<String, String> {
+    let (command, definition, rest) = match words {
+        [command, definition, rest @ ..] => (command.as_str(), definition, rest),
+        _ => return Err(USAGE.to_string()),
+    };
+    let schema = Schema::new(definition).map_err(|err| format!("invalid schema: {}", err))?;
+    let program = |index: usize| rest.get(index).map_or("app", String::as_str);
+    match command {
+        "check" if rest.is_empty() => Ok(schema.usage("app")),
+        "parse" => schema
+            .parse_from(rest)
+            .map(|parsed| parsed.to_json())
+            .map_err(|err| format!("error: {}", err)),
+        "help" if rest.len() <= 1 => Ok(schema.help(program(0))),
+        "completions" if !rest.is_empty() && rest.len() <= 2 => {
+            let shell = match rest[0].as_str() {
+                "bash" => Shell::Bash,
+                "zsh" => Shell::Zsh,
+                "fish" => Shell::Fish,
+                other => return Err(format!("unknown shell: {}", other)),
+            };
+            Ok(schema.completion_script(shell, program(1)))
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_with(words: &[&str]) -> Result<String, String> {
+        run(&words.iter().map(ToString::to_string).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(run_with(&[]), Err(USAGE.to_string()));
+        assert_eq!(run_with(&["check"]), Err(USAGE.to_string()));
+        assert_eq!(run_with(&["check", "l", "extra"]), Err(USAGE.to_string()));
+        assert_eq!(run_with(&["frobnicate", "l"]), Err(USAGE.to_string()));
+        assert_eq!(run_with(&["completions", "l"]), Err(USAGE.to_string()));
+    }
+
+    #[test]
+    fn checks_schemas() {
+        assert_eq!(run_with(&["check", "l,p#"]).unwrap(), "usage: app [-l] [-p <number>]");
+        let unsupported = "invalid schema: unsupported argument type '?'";
+        assert_eq!(run_with(&["check", "l,p?"]), Err(unsupported.to_string()));
+        let duplicate = run_with(&["check", "l,l"]).unwrap_err();
+        assert_eq!(duplicate, "invalid schema: -l is declared twice");
+    }
+
+    #[test]
+    fn parses_to_json() {
+        assert_eq!(run_with(&["parse", "l,p#", "-p", "8080"]).unwrap(), r#"{"l":false,"p":8080}"#);
+        let err = run_with(&["parse", "p#", "-p", "x"]).unwrap_err();
+        assert_eq!(err, "error: 'x' is not a number");
+    }
+
+    #[test]
+    fn prints_help_and_completions() {
+        assert!(run_with(&["help", "l", "tool"]).unwrap().contains("tool"));
+        assert!(run_with(&["completions", "l", "bash", "tool"]).unwrap().contains("tool"));
+        assert_eq!(run_with(&["completions", "l", "csh"]), Err("unknown shell: csh".to_string()));
+    }
+}
@@ -0,0 +1,273 @@
+//! Subcommands: flags of the program itself, then a command name choosing
+//! the schema the rest of the input is parsed with, as in `app -v serve -p
+//! 8080` or `app migrate -d ./db`. Commands nest, as in `app remote add -v
+//! origin`, and global arguments are accepted at every level below the one
+//! declaring them.
+
+use crate::{split_words, ParseErr, ParsedArgs, Schema, Source};
+
+/// A top-level schema for the flags before the command, and one schema per
+/// command for the input after it:
+///
+/// ```
+/// use args::{Commands, Schema};
+///
+/// let app = Commands::new(Schema::new("v").unwrap())
+///     .global("v")
+///     .command("serve", Schema::new("p#").unwrap())
+///     .group(
+///         "remote",
+///         Commands::default().command("add", Schema::new("<name>").unwrap()),
+///     );
+/// let parsed = app.parse("serve -p 8080").unwrap();
+/// assert_eq!(parsed.name(), "serve");
+/// assert_eq!(parsed.args().get_number("p"), Some(8080));
+/// let parsed = app.parse("remote add -v origin").unwrap();
+/// assert_eq!(parsed.path(), vec!["remote", "add"]);
+/// assert_eq!(parsed.args().get_str("name"), Some("origin".to_string()));
+/// assert_eq!(parsed.global().get_bool("v"), Some(true));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Commands {
+    schema: Schema,
+    globals: Vec<String>,
+    /// The global arguments of levels above declared in `schema`.
+    inherited: Vec<String>,
+    commands: Vec<(String, Commands)>,
+}
+
+/// The result of [`Commands::parse`]: what each level of the commands
+/// given parsed, from the top-level flags down to the innermost command.
+#[derive(Debug)]
+pub struct ParsedCommand {
+    /// The top-level arguments, then each command with its arguments.
+    levels: Vec<(String, ParsedArgs)>,
+}
+
+impl Commands {
+    pub fn new(schema: Schema) -> Self {
+        Self { schema, globals: vec![], inherited: vec![], commands: vec![] }
+    }
+
+    /// Adds the command `name`, replacing one declared before under the
+    /// same name.
+    pub fn command(self, name: &str, schema: Schema) -> Self {
+        self.group(name, Commands::new(schema))
+    }
+
+    /// Adds the command `name` with commands of its own, as `remote` is for
+    /// `remote add` and `remote remove`.
+    pub fn group(mut self, name: &str, mut commands: Commands) -> Self {
+        for arg in &self.globals {
+            commands.inherit(&self.schema, arg);
+        }
+        self.commands.retain(|(command, _)| command != name);
+        self.commands.push((name.to_string(), commands));
+        self
+    }
+
+    /// Accepts the non-positional `arg` of this level's schema in every
+    /// command below it too, and gives it the same value at each of those
+    /// levels in the result: the one from the innermost level that was
+    /// given it, and otherwise this level's. A command declaring an
+    /// argument of the same name keeps its own.
+    pub fn global(mut self, arg: &str) -> Self {
+        if self.schema.flag_spec(arg).is_none() || self.globals.iter().any(|name| name == arg) {
+            return self;
+        }
+        for (_, commands) in &mut self.commands {
+            commands.inherit(&self.schema, arg);
+        }
+        self.globals.push(arg.to_string());
+        self
+    }
+
+    /// Declares the argument `arg` of `from` at this level and every level
+    /// below, unless one declares its own.
+    fn inherit(&mut self, from: &Schema, arg: &str) {
+        if self.schema.spec(arg).is_some() {
+            return;
+        }
+        let spec = from.spec(arg).cloned().into_iter().collect();
+        if self.schema.include(&Schema::from_specs(spec)).is_err() {
+            return;
+        }
+        self.inherited.push(arg.to_string());
+        for (_, commands) in &mut self.commands {
+            commands.inherit(from, arg);
+        }
+    }
+
+    /// The declared command names, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Parses the flags of the top-level schema at the start of `input`,
+    /// see [`Schema::parse_partial`], then the command name after them and
+    /// the rest of the input with that command, down to a command without
+    /// commands of its own, whose schema parses all that's left. Fails with
+    /// [`ParseErr::MissingCommand`] if nothing follows the flags of a level
+    /// that has commands and [`ParseErr::UnknownCommand`] for a name that
+    /// isn't declared.
+    pub fn parse(&self, input: &str) -> Result<ParsedCommand, ParseErr> {
+        let mut levels = vec![];
+        let mut inherited = vec![];
+        let mut globals = vec![];
+        let (mut commands, mut name, mut input) = (self, String::new(), input.to_string());
+        while !commands.commands.is_empty() {
+            let (parsed, rest) = commands.schema.parse_partial(&input)?;
+            globals.extend(commands.globals.iter().map(|arg| (arg, levels.len())));
+            levels.push((name, parsed));
+            inherited.push(&commands.inherited);
+            let word = split_words(&rest).next().ok_or(ParseErr::MissingCommand)?;
+            let found = commands.commands.iter().find(|(name, _)| *name == word.text);
+            let (found, next) =
+                found.ok_or_else(|| ParseErr::UnknownCommand(word.text.to_string()))?;
+            input = rest[word.span.end..].to_string();
+            commands = next;
+            name = found.clone();
+        }
+        levels.push((name, commands.schema.parse(&input)?));
+        inherited.push(&commands.inherited);
+        for (arg, depth) in globals {
+            let below = inherited[depth + 1..].iter().take_while(|names| names.contains(arg));
+            let sharing = depth..depth + 1 + below.count();
+            let given = sharing
+                .clone()
+                .rev()
+                .find(|&level| levels[level].1.source(arg) == Some(Source::CommandLine))
+                .unwrap_or(depth);
+            let value = levels[given].1.clone();
+            for (_, parsed) in &mut levels[sharing] {
+                parsed.adopt(arg, &value);
+            }
+        }
+        Ok(ParsedCommand { levels })
+    }
+
+    /// Like [`Commands::parse`] for words that have already been split, see
+    /// [`Schema::parse_from`].
+    pub fn parse_from<I, S>(&self, args: I) -> Result<ParsedCommand, ParseErr>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.parse(&crate::join_words(args))
+    }
+
+    /// The synopsis of the top-level flags followed by one line per command,
+    /// e.g. `usage: app [-v] <command>` and `usage: app serve [-p <number>]`.
+    pub fn usage(&self, program: &str) -> String {
+        if self.commands.is_empty() {
+            return self.schema.usage(program);
+        }
+        let mut lines = vec![format!("{} <command>", self.schema.usage(program))];
+        for (name, commands) in &self.commands {
+            lines.push(commands.usage(&format!("{} {}", program, name)));
+        }
+        lines.join("\n")
+    }
+}
+
+/// A level without flags of its own, e.g. for commands that only group
+/// others.
+impl Default for Commands {
+    fn default() -> Self {
+        Self::new(Schema::from_specs(vec![]))
+    }
+}
+
+impl ParsedCommand {
+    /// The flags given before the first command.
+    pub fn global(&self) -> &ParsedArgs {
+        &self.levels[0].1
+    }
+
+    /// The innermost command given, as declared, e.g. `add` for `remote
+    /// add`.
+    pub fn name(&self) -> &str {
+        &self.levels[self.levels.len() - 1].0
+    }
+
+    /// What the innermost command's schema parsed.
+    pub fn args(&self) -> &ParsedArgs {
+        &self.levels[self.levels.len() - 1].1
+    }
+
+    /// Every command given, from the outermost in.
+    pub fn path(&self) -> Vec<&str> {
+        self.levels[1..].iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// What the command `name` along the path parsed, e.g. the flags of
+    /// `remote` given before `add`.
+    pub fn args_of(&self, name: &str) -> Option<&ParsedArgs> {
+        self.levels[1..].iter().find(|(command, _)| command == name).map(|(_, args)| args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app() -> Commands {
+        Commands::new(Schema::new("v,c*").unwrap())
+            .command("serve", Schema::new("p#,<root>").unwrap())
+            .command("migrate", Schema::new("d*,n").unwrap())
+    }
+
+    #[test]
+    fn parses_the_command_with_its_schema() {
+        let parsed = app().parse("-c app.toml migrate -d './my db' -n").unwrap();
+        assert_eq!(parsed.name(), "migrate");
+        assert_eq!(parsed.global().get_str("c"), Some("app.toml".to_string()));
+        assert_eq!(parsed.args().get_str("d"), Some("./my db".to_string()));
+        assert_eq!(parsed.args().get_bool("n"), Some(true));
+        let parsed = app().parse_from(["serve", "-p", "80", "/srv"]).unwrap();
+        assert_eq!(parsed.args().get_str("root"), Some("/srv".to_string()));
+        assert_eq!(parsed.global().get_bool("v"), Some(false));
+    }
+
+    #[test]
+    fn fails_on_missing_or_unknown_commands() {
+        assert_eq!(app().parse("-v").unwrap_err(), ParseErr::MissingCommand);
+        let err = app().parse("-v build").unwrap_err();
+        assert_eq!(err, ParseErr::UnknownCommand("build".to_string()));
+        let err = app().parse("serve -d x").unwrap_err();
+        assert_eq!(err, ParseErr::UnknownArg {
+            arg: "d".into(),
+            typed: "-d".into(),
+            suggestion: Some("-p".into()),
+        });
+        assert_eq!(app().names().collect::<Vec<_>>(), vec!["serve", "migrate"]);
+        assert_eq!(
+            app().usage("app"),
+            "usage: app [-v] [-c <string>] <command>\n\
+             usage: app serve [-p <number>] <root>\n\
+             usage: app migrate [-d <string>] [-n]"
+        );
+    }
+
+    #[test]
+    fn nested_commands_share_global_args() {
+        let remote = Commands::new(Schema::new("f").unwrap())
+            .command("add", Schema::new("<name>,c#").unwrap())
+            .command("remove", Schema::new("<name>").unwrap());
+        let app = app().group("remote", remote).global("v").global("c");
+        let parsed = app.parse("-c a.toml remote -f add origin -v").unwrap();
+        assert_eq!(parsed.path(), vec!["remote", "add"]);
+        assert_eq!(parsed.name(), "add");
+        assert_eq!(parsed.args().get_str("name"), Some("origin".to_string()));
+        assert_eq!(parsed.args_of("remote").unwrap().get_bool("f"), Some(true));
+        for args in [parsed.global(), parsed.args_of("remote").unwrap()] {
+            assert_eq!(args.get_bool("v"), Some(true));
+            assert_eq!(args.get_str("c"), Some("a.toml".to_string()));
+        }
+        assert_eq!(parsed.args().get_number("c"), None);
+        let parsed = app.parse("remote remove -v origin").unwrap();
+        assert_eq!(parsed.global().source("v"), Some(Source::CommandLine));
+        assert_eq!(parsed.global().occurrences_of("v"), 1);
+        assert_eq!(app.parse("remote").unwrap_err(), ParseErr::MissingCommand);
+    }
+}
@@ -0,0 +1,343 @@
+//! Tab completion, from scripts generated for the shells or at runtime.
+
+use crate::schema::{flag, ArgKind};
+use crate::Schema;
+
+/// A shell to generate a completion script for.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// What an argument's value names, for completion to offer matching paths.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ValueHint {
+    File,
+    Dir,
+}
+
+/// A flag as completion sees it.
+struct Entry {
+    names: Vec<String>,
+    description: String,
+    takes_value: bool,
+    repeatable: bool,
+    choices: Vec<String>,
+    hint: Option<ValueHint>,
+}
+
+impl Entry {
+    fn switch(names: &[&str], description: &str) -> Self {
+        Entry {
+            names: names.iter().map(ToString::to_string).collect(),
+            description: description.to_string(),
+            takes_value: false,
+            repeatable: false,
+            choices: vec![],
+            hint: None,
+        }
+    }
+
+    fn flags(&self) -> Vec<String> {
+        self.names.iter().map(|name| flag(name)).collect()
+    }
+}
+
+/// A word that may come next at the cursor, found by [`complete`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct Completion {
+    /// The whole word, to replace the one being typed.
+    pub text: String,
+    pub description: Option<String>,
+}
+
+/// What may be typed at byte offset `cursor` of a partial `input`, for
+/// completion at runtime, such as a hidden `myapp __complete` command or
+/// an interactive shell: the allowed values of the flag before the word
+/// being typed, or otherwise the flags matching it, leaving out those that
+/// can't be given again. Values without choices get no suggestions, as
+/// the shell is better placed to complete paths.
+pub fn complete(schema: &Schema, input: &str, cursor: usize) -> Vec<Completion> {
+    let mut cursor = cursor.min(input.len());
+    while !input.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    let line = &input[..cursor];
+    let start = line.rfind(char::is_whitespace).map_or(0, |at| at + 1);
+    let (before, current) = line.split_at(start);
+    let words: Vec<&str> = before.split_whitespace().collect();
+    let name_of = |word: &str| schema.canonical_name(word.trim_start_matches('-').to_string());
+    let last_flag = words.iter().rposition(|word| word.starts_with('-') && *word != "--");
+    let spec = last_flag.and_then(|at| {
+        let spec = schema.flag_spec(&name_of(words[at]))?;
+        let is_list = matches!(spec.kind, ArgKind::StrArray | ArgKind::NumberArray);
+        let taking = spec.kind != ArgKind::Bool && (is_list || at + 1 == words.len());
+        Some(spec).filter(|_| taking && !words[at].contains('='))
+    });
+    let choices = |prefix: &str, typed: &str, choices: &[String]| -> Vec<Completion> {
+        let matching = choices.iter().filter(|choice| choice.starts_with(typed));
+        matching
+            .map(|choice| Completion { text: format!("{}{}", prefix, choice), description: None })
+            .collect()
+    };
+    if words.contains(&"--") {
+        return vec![];
+    }
+    if let Some((name, value)) = current.split_once('=').filter(|_| current.starts_with('-')) {
+        return match schema.flag_spec(&name_of(name)) {
+            Some(spec) => choices(&format!("{}=", name), value, &spec.choices),
+            None => vec![],
+        };
+    }
+    if let Some(spec) = spec.filter(|_| !current.starts_with('-')) {
+        return choices("", current, &spec.choices);
+    }
+    let given: Vec<String> = words.iter().map(|word| name_of(word)).collect();
+    let mut completions = vec![];
+    for entry in schema.completion_entries() {
+        if !entry.repeatable && entry.names.iter().any(|name| given.contains(name)) {
+            continue;
+        }
+        for text in entry.flags().into_iter().filter(|text| text.starts_with(current)) {
+            let description = Some(entry.description.clone()).filter(|text| !text.is_empty());
+            completions.push(Completion { text, description });
+        }
+    }
+    completions
+}
+
+/// `text` inside single quotes, for any of the shells.
+fn quoted(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+impl Schema {
+    /// A completion script for `program` in `shell`, offering the visible
+    /// flags, and for their values the allowed choices or the paths their
+    /// [`ValueHint`] asks for. Source it from the shell's startup file, or
+    /// install it where the shell looks for completions.
+    pub fn completion_script(&self, shell: Shell, program: &str) -> String {
+        let entries = self.completion_entries();
+        match shell {
+            Shell::Bash => bash(program, &entries),
+            Shell::Zsh => zsh(program, &entries),
+            Shell::Fish => fish(program, &entries),
+        }
+    }
+
+    fn completion_entries(&self) -> Vec<Entry> {
+        let visible = |name: &String| !self.hidden.contains(name);
+        let mut entries: Vec<Entry> = self
+            .specs
+            .iter()
+            .filter(|spec| !spec.positional && visible(&spec.name))
+            .map(|spec| Entry {
+                names: vec![spec.name.clone()],
+                description: spec.description.clone().unwrap_or_default(),
+                takes_value: spec.kind != ArgKind::Bool,
+                repeatable: spec.max_occurrences != Some(1),
+                choices: spec.choices.clone(),
+                hint: spec.hint,
+            })
+            .collect();
+        for (switch, arg) in self.inversions.iter().filter(|(switch, _)| visible(switch)) {
+            entries.push(Entry::switch(&[switch], &format!("turns {} off", flag(arg))));
+        }
+        if self.help_program.is_some() {
+            entries.push(Entry::switch(&["h", "help"], "shows this help"));
+        }
+        if self.version.is_some() {
+            entries.push(Entry::switch(&["V", "version"], "shows the version"));
+        }
+        entries
+    }
+}
+
+fn bash(program: &str, entries: &[Entry]) -> String {
+    let function = format!("_{}", program.replace(|c: char| !c.is_alphanumeric(), "_"));
+    let mut script = format!(
+        "{}() {{\n    local cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    \
+         local prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n    case \"$prev\" in\n",
+        function
+    );
+    for entry in entries.iter().filter(|entry| entry.takes_value) {
+        let reply = match (entry.hint, entry.choices.is_empty()) {
+            (_, false) => format!(
+                "COMPREPLY=($(compgen -W {} -- \"$cur\"))",
+                quoted(&entry.choices.join(" "))
+            ),
+            (Some(ValueHint::File), true) => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+            (Some(ValueHint::Dir), true) => "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string(),
+            (None, true) => "COMPREPLY=()".to_string(),
+        };
+        script.push_str(&format!(
+            "        {})\n            {}\n            return\n            ;;\n",
+            entry.flags().join("|"),
+            reply
+        ));
+    }
+    let flags: Vec<String> = entries.iter().flat_map(Entry::flags).collect();
+    script.push_str(&format!(
+        "    esac\n    COMPREPLY=($(compgen -W {} -- \"$cur\"))\n}}\ncomplete -F {} {}\n",
+        quoted(&flags.join(" ")),
+        function,
+        program
+    ));
+    script
+}
+
+fn zsh(program: &str, entries: &[Entry]) -> String {
+    let mut script = format!("#compdef {}\n\n_arguments", program);
+    for entry in entries {
+        let description = entry.description.replace('[', "\\[").replace(']', "\\]");
+        let action = match (entry.hint, entry.choices.is_empty()) {
+            _ if !entry.takes_value => String::new(),
+            (_, false) => format!(":value:({})", entry.choices.join(" ")),
+            (Some(ValueHint::File), true) => ":file:_files".to_string(),
+            (Some(ValueHint::Dir), true) => ":directory:_files -/".to_string(),
+            (None, true) => ":value:".to_string(),
+        };
+        let repeat = if entry.repeatable { "*" } else { "" };
+        let names = entry.flags();
+        let flags = if names.len() > 1 {
+            format!("({}){{{}}}", names.join(" "), names.join(","))
+        } else {
+            names.join("")
+        };
+        let spec = format!("{}{}[{}]{}", repeat, flags, description, action);
+        script.push_str(&format!(" \\\n    {}", quoted(&spec)));
+    }
+    script.push('\n');
+    script
+}
+
+fn fish(program: &str, entries: &[Entry]) -> String {
+    let mut script = String::new();
+    for entry in entries {
+        let mut line = format!("complete -c {}", program);
+        for name in &entry.names {
+            let option = if name.chars().count() > 1 { "-l" } else { "-s" };
+            line.push_str(&format!(" {} {}", option, name));
+        }
+        match (entry.hint, entry.choices.is_empty()) {
+            _ if !entry.takes_value => {}
+            (_, false) => line.push_str(&format!(" -x -a {}", quoted(&entry.choices.join(" ")))),
+            (Some(ValueHint::File), true) => line.push_str(" -r -F"),
+            (Some(ValueHint::Dir), true) => line.push_str(" -x -a '(__fish_complete_directories)'"),
+            (None, true) => line.push_str(" -x"),
+        }
+        if !entry.description.is_empty() {
+            line.push_str(&format!(" -d {}", quoted(&entry.description)));
+        }
+        script.push_str(&line);
+        script.push('\n');
+    }
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::builder()
+            .bool("l")
+            .description("verbose logging")
+            .string("color")
+            .choices(&["auto", "never"])
+            .string("f")
+            .hint(ValueHint::File)
+            .string_list("d")
+            .hint(ValueHint::Dir)
+            .number("p")
+            .positional("input")
+            .bool("x")
+            .build()
+            .unwrap()
+            .hidden("x")
+            .help_flags("my-app")
+    }
+
+    fn texts(completions: Vec<Completion>) -> Vec<String> {
+        completions.into_iter().map(|completion| completion.text).collect()
+    }
+
+    #[test]
+    fn completes_flags_and_values() {
+        let schema = schema();
+        assert_eq!(texts(complete(&schema, "-l --c", 6)), vec!["--color"]);
+        assert_eq!(texts(complete(&schema, "-l --color a", 12)), vec!["auto"]);
+        assert_eq!(texts(complete(&schema, "--color=n", 9)), vec!["--color=never"]);
+        assert_eq!(texts(complete(&schema, "--color ", 8)), vec!["auto", "never"]);
+        assert!(complete(&schema, "-f ", 3).is_empty());
+        assert!(complete(&schema, "-l -- -", 7).is_empty());
+        assert_eq!(
+            texts(complete(&schema, "-l -d a -", 9)),
+            vec!["--color", "-f", "-d", "-p", "-h", "--help"]
+        );
+        let flags = complete(&schema, "-lx", 2);
+        assert_eq!(
+            flags,
+            vec![Completion {
+                text: "-l".to_string(),
+                description: Some("verbose logging".to_string())
+            }]
+        );
+    }
+
+    #[test]
+    fn bash_script() {
+        assert_eq!(
+            schema().completion_script(Shell::Bash, "my-app"),
+            "_my_app() {\n    \
+                 local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n    \
+                 local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n    \
+                 case \"$prev\" in\n        \
+                     --color)\n            \
+                         COMPREPLY=($(compgen -W 'auto never' -- \"$cur\"))\n            \
+                         return\n            ;;\n        \
+                     -f)\n            \
+                         COMPREPLY=($(compgen -f -- \"$cur\"))\n            \
+                         return\n            ;;\n        \
+                     -d)\n            \
+                         COMPREPLY=($(compgen -d -- \"$cur\"))\n            \
+                         return\n            ;;\n        \
+                     -p)\n            \
+                         COMPREPLY=()\n            \
+                         return\n            ;;\n    \
+                 esac\n    \
+                 COMPREPLY=($(compgen -W '-l --color -f -d -p -h --help' -- \"$cur\"))\n\
+             }\n\
+             complete -F _my_app my-app\n"
+        );
+    }
+
+    #[test]
+    fn zsh_script() {
+        assert_eq!(
+            schema().completion_script(Shell::Zsh, "my-app"),
+            "#compdef my-app\n\n_arguments \\\n    \
+                 '-l[verbose logging]' \\\n    \
+                 '--color[]:value:(auto never)' \\\n    \
+                 '-f[]:file:_files' \\\n    \
+                 '*-d[]:directory:_files -/' \\\n    \
+                 '-p[]:value:' \\\n    \
+                 '(-h --help){-h,--help}[shows this help]'\n"
+        );
+    }
+
+    #[test]
+    fn fish_script() {
+        assert_eq!(
+            schema().completion_script(Shell::Fish, "my-app"),
+            "complete -c my-app -s l -d 'verbose logging'\n\
+             complete -c my-app -l color -x -a 'auto never'\n\
+             complete -c my-app -s f -r -F\n\
+             complete -c my-app -s d -x -a '(__fish_complete_directories)'\n\
+             complete -c my-app -s p -x\n\
+             complete -c my-app -s h -l help -d 'shows this help'\n"
+        );
+    }
+}
@@ -0,0 +1,152 @@
+//! Expansion of `~`, `$VAR` and `${VAR}` inside string values.
+
+use crate::schema::ArgKind;
+use crate::{ParseErr, Schema};
+
+impl Schema {
+    /// Expands `$HOME` and `${HOME}` inside the values of string arguments,
+    /// for input that didn't go through a shell. Values are expanded once
+    /// their quotes are resolved, so quoting doesn't keep a `$` literal:
+    /// `$$` is the only escape, and a `$` not followed by a name is kept.
+    /// A variable that isn't set fails with [`ParseErr::UnsetVariable`].
+    pub fn expand_env(mut self) -> Self {
+        self.expand_env = true;
+        self
+    }
+
+    /// Expands a leading `~` in the values of string arguments, such as
+    /// paths, to the user's home directory, and `~user` to that user's.
+    /// Values naming a user who isn't known, or given while the home
+    /// directory isn't, are kept as they are. A quoted `~` is expanded too.
+    pub fn expand_tilde(mut self) -> Self {
+        self.expand_tilde = true;
+        self
+    }
+
+    /// `values` of `name` with home directories and variables expanded, as
+    /// far as enabled, if `name` takes strings.
+    pub(crate) fn expand_values(
+        &self,
+        name: &str,
+        values: Vec<String>,
+    ) -> Result<Vec<String>, ParseErr> {
+        let strings = self.spec(name).is_some_and(|spec| {
+            matches!(spec.kind, ArgKind::Str | ArgKind::OptStr | ArgKind::StrArray)
+        });
+        if !strings {
+            return Ok(values);
+        }
+        let tilde = |value: String| if self.expand_tilde { expand_tilde(value) } else { value };
+        let values = values.into_iter().map(tilde);
+        if !self.expand_env {
+            return Ok(values.collect());
+        }
+        values.map(|value| expand(&value)).collect()
+    }
+}
+
+fn expand_tilde(value: String) -> String {
+    let rest = match value.strip_prefix('~') {
+        Some(rest) => rest,
+        None => return value,
+    };
+    let (user, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let home = if user.is_empty() {
+        std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok()
+    } else {
+        home_of(user)
+    };
+    match home {
+        Some(home) => format!("{}{}", home, path),
+        None => value,
+    }
+}
+
+/// The home directory of `user` in `/etc/passwd`, if any.
+fn home_of(user: &str) -> Option<String> {
+    let passwd = std::fs::read_to_string("/etc/passwd").ok()?;
+    let entry = passwd.lines().find(|line| line.split(':').next() == Some(user))?;
+    entry.split(':').nth(5).map(ToString::to_string)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn expand(value: &str) -> Result<String, ParseErr> {
+    let mut out = String::new();
+    let mut rest = value;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        let (name, next) = if let Some(after) = after.strip_prefix('$') {
+            out.push('$');
+            rest = after;
+            continue;
+        } else if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end + 1..]),
+                None => ("", after),
+            }
+        } else {
+            let end = after.find(|c| !is_name_char(c)).unwrap_or(after.len());
+            (&after[..end], &after[end..])
+        };
+        if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+            out.push('$');
+            rest = after;
+            continue;
+        }
+        let value = std::env::var(name).map_err(|_| ParseErr::UnsetVariable(name.to_string()))?;
+        out.push_str(&value);
+        rest = next;
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_variables_in_string_values() {
+        std::env::set_var("ARGS_TEST_EXPAND_DIR", "/home/me");
+        let schema = Schema::new("d*,s[*],p#").unwrap().expand_env();
+        let input = "-d $ARGS_TEST_EXPAND_DIR/logs -s ${ARGS_TEST_EXPAND_DIR}x $$1 $";
+        let args = schema.parse(input).unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/home/me/logs");
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["/home/mex", "$1", "$"]);
+        let args = schema.parse("-d '$ARGS_TEST_EXPAND_DIR' -s '$$ARGS_TEST_EXPAND_DIR'").unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/home/me");
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["$ARGS_TEST_EXPAND_DIR"]);
+    }
+
+    #[test]
+    fn expands_home_directories() {
+        let home = std::env::var("HOME").unwrap();
+        let root = home_of("root").unwrap_or("~root".to_string());
+        let schema = Schema::new("d*,s[*]").unwrap().expand_tilde();
+        let args = schema.parse("-d ~/logs -s ~ ~root/x a~b ~args-test-nobody/x").unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), format!("{}/logs", home));
+        let quoted = schema.parse("-d '~/logs'").unwrap();
+        assert_eq!(quoted.get("d").unwrap().get().unwrap(), format!("{}/logs", home));
+        assert_eq!(
+            args.get("s").unwrap().as_str_array(),
+            vec![home, format!("{}/x", root), "a~b".to_string(), "~args-test-nobody/x".to_string()]
+        );
+        let plain = Schema::new("d*").unwrap().parse("-d ~/logs").unwrap();
+        assert_eq!(plain.get("d").unwrap().get().unwrap(), "~/logs");
+    }
+
+    #[test]
+    fn unset_variables_fail() {
+        let schema = Schema::new("d*").unwrap().expand_env();
+        assert_eq!(
+            schema.parse("-d ${ARGS_TEST_EXPAND_UNSET}").unwrap_err(),
+            ParseErr::UnsetVariable("ARGS_TEST_EXPAND_UNSET".to_string())
+        );
+        let plain = Schema::new("d*").unwrap().parse("-d $ARGS_TEST_EXPAND_UNSET").unwrap();
+        assert_eq!(plain.get("d").unwrap().get().unwrap(), "$ARGS_TEST_EXPAND_UNSET");
+    }
+}
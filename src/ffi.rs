@@ -0,0 +1,294 @@
+//! A C interface to [`parse`](crate::parse), declared in `include/args.h`.
+//!
+//! Build the crate with `--features ffi` as a `staticlib` or `cdylib`
+//! (e.g. `cargo rustc --release --features ffi --crate-type staticlib`)
+//! and link it into the C or C++ program. Every function returns one of the
+//! `ARGS_*` codes, and results are freed with [`args_free`].
+
+use crate::{ParseErr, ParsedArgs};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+pub const ARGS_OK: c_int = 0;
+/// The schema definition is malformed.
+pub const ARGS_ERR_SCHEMA: c_int = 1;
+/// A flag or argument name isn't in the schema.
+pub const ARGS_ERR_UNKNOWN_ARG: c_int = 2;
+/// A value couldn't be read, or was rejected by a validator.
+pub const ARGS_ERR_INVALID_VALUE: c_int = 3;
+/// A required argument or a value wasn't given.
+pub const ARGS_ERR_MISSING: c_int = 4;
+/// Arguments were given together that can't be, or too often.
+pub const ARGS_ERR_CONFLICT: c_int = 5;
+/// The argument asked for has a different type.
+pub const ARGS_ERR_WRONG_TYPE: c_int = 6;
+/// The help or version flag was given; the message is the text to print.
+pub const ARGS_ERR_HELP: c_int = 7;
+/// A pointer passed in was null, or a string wasn't UTF-8.
+pub const ARGS_ERR_INPUT: c_int = 8;
+pub const ARGS_ERR_OTHER: c_int = 9;
+
+/// The outcome of [`args_parse`]: the parsed arguments, or the error that
+/// stopped parsing.
+pub struct ArgsResult {
+    parsed: Result<ParsedArgs, (c_int, CString)>,
+    /// Strings handed out by [`args_get_str`], kept until [`args_free`].
+    strings: RefCell<Vec<CString>>,
+}
+
+/// The `ARGS_*` code of `err`.
+fn code(err: &ParseErr) -> c_int {
+    match err {
+        ParseErr::InvalidSchema
+        | ParseErr::UnsupportedArgType(_)
+        | ParseErr::InvalidSchemaFile { .. }
+        | ParseErr::InvalidWireFormat
+        | ParseErr::DuplicateArg(_)
+        | ParseErr::DuplicateSchema(_)
+        | ParseErr::UnknownSchema(_)
+        | ParseErr::DuplicateMarker(_) => ARGS_ERR_SCHEMA,
+        ParseErr::UnknownArg { .. }
+        | ParseErr::UnknownCommand(_)
+        | ParseErr::AmbiguousArg { .. } => ARGS_ERR_UNKNOWN_ARG,
+        ParseErr::NumberFormatErr { .. }
+        | ParseErr::ValidationFailed { .. }
+        | ParseErr::UnexpectedValue(_)
+        | ParseErr::InvalidValue { .. }
+        | ParseErr::PathNotFound { .. }
+        | ParseErr::NotAFile { .. }
+        | ParseErr::NotADirectory { .. }
+        | ParseErr::ParentNotFound { .. }
+        | ParseErr::CreateDirFailed { .. }
+        | ParseErr::NoMatches { .. }
+        | ParseErr::UnknownPreset(_)
+        | ParseErr::InvalidTemplate(_)
+        | ParseErr::UnsetVariable(_)
+        | ParseErr::UnterminatedQuote(_) => ARGS_ERR_INVALID_VALUE,
+        ParseErr::MissingRequiredArg { .. }
+        | ParseErr::MissingValue(_)
+        | ParseErr::MissingArg(_)
+        | ParseErr::MissingCommand
+        | ParseErr::MissingOneOf(_)
+        | ParseErr::RequiredIf { .. } => ARGS_ERR_MISSING,
+        ParseErr::ConflictingArgs { .. } | ParseErr::TooManyOccurrences { .. } => ARGS_ERR_CONFLICT,
+        ParseErr::WrongType { .. } => ARGS_ERR_WRONG_TYPE,
+        ParseErr::HelpRequested(_) | ParseErr::VersionRequested(_) => ARGS_ERR_HELP,
+        _ => ARGS_ERR_OTHER,
+    }
+}
+
+/// The error as a code and message, without the interior NULs C can't
+/// represent.
+fn error(err: &ParseErr) -> (c_int, CString) {
+    let message = match err {
+        ParseErr::HelpRequested(text) | ParseErr::VersionRequested(text) => text.clone(),
+        _ => err.to_string(),
+    };
+    (code(err), CString::new(message.replace('\0', "")).unwrap_or_default())
+}
+
+/// The string `s` points to, or `None` if it's null or not UTF-8.
+unsafe fn read<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        return None;
+    }
+    CStr::from_ptr(s).to_str().ok()
+}
+
+/// The parsed arguments of `result`, the name `name` points to, and where
+/// to write the value, or the code to return instead.
+unsafe fn lookup<'a, T>(
+    result: *const ArgsResult,
+    name: *const c_char,
+    out: *mut T,
+) -> Result<(&'a ParsedArgs, &'a str), c_int> {
+    if result.is_null() || out.is_null() {
+        return Err(ARGS_ERR_INPUT);
+    }
+    let parsed = match &(*result).parsed {
+        Ok(parsed) => parsed,
+        Err((code, _)) => return Err(*code),
+    };
+    read(name).map(|name| (parsed, name)).ok_or(ARGS_ERR_INPUT)
+}
+
+/// Parses `input` against the schema definition `schema`, as
+/// [`parse`](crate::parse) does. Writes the result, which must be freed
+/// with [`args_free`], to `*out` even when parsing fails, so that
+/// [`args_error_message`] can describe the failure.
+///
+/// # Safety
+///
+/// `schema` and `input` must be null or point to NUL-terminated strings,
+/// and `out` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn args_parse(
+    schema: *const c_char,
+    input: *const c_char,
+    out: *mut *mut ArgsResult,
+) -> c_int {
+    if out.is_null() {
+        return ARGS_ERR_INPUT;
+    }
+    let parsed = match (read(schema), read(input)) {
+        (Some(schema), Some(input)) => crate::parse(schema, input).map_err(|err| error(&err)),
+        _ => Err((ARGS_ERR_INPUT, CString::new("schema and input must be UTF-8").unwrap())),
+    };
+    let code = parsed.as_ref().err().map_or(ARGS_OK, |(code, _)| *code);
+    *out = Box::into_raw(Box::new(ArgsResult { parsed, strings: RefCell::default() }));
+    code
+}
+
+/// The message of the error `result` failed with, or null if it parsed.
+/// The string is owned by `result`.
+///
+/// # Safety
+///
+/// `result` must be null or come from [`args_parse`] and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn args_error_message(result: *const ArgsResult) -> *const c_char {
+    if result.is_null() {
+        return ptr::null();
+    }
+    match &(*result).parsed {
+        Ok(_) => ptr::null(),
+        Err((_, message)) => message.as_ptr(),
+    }
+}
+
+/// Writes the value of the bool argument `name` to `*out`.
+///
+/// # Safety
+///
+/// `result` must be null or come from [`args_parse`] and not be freed yet,
+/// `name` must be null or point to a NUL-terminated string, and `out` must
+/// be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn args_get_bool(
+    result: *const ArgsResult,
+    name: *const c_char,
+    out: *mut bool,
+) -> c_int {
+    match lookup(result, name, out) {
+        Ok((parsed, name)) => match parsed.try_get_bool(name) {
+            Ok(value) => {
+                *out = value;
+                ARGS_OK
+            }
+            Err(err) => code(&err),
+        },
+        Err(code) => code,
+    }
+}
+
+/// Writes the value of the number argument `name` to `*out`.
+///
+/// # Safety
+///
+/// As for [`args_get_bool`].
+#[no_mangle]
+pub unsafe extern "C" fn args_get_number(
+    result: *const ArgsResult,
+    name: *const c_char,
+    out: *mut i64,
+) -> c_int {
+    match lookup(result, name, out) {
+        Ok((parsed, name)) => match parsed.try_get_number(name) {
+            Ok(value) => {
+                *out = value as i64;
+                ARGS_OK
+            }
+            Err(err) => code(&err),
+        },
+        Err(code) => code,
+    }
+}
+
+/// Writes the value of the string argument `name` to `*out`, as a string
+/// owned by `result`.
+///
+/// # Safety
+///
+/// As for [`args_get_bool`].
+#[no_mangle]
+pub unsafe extern "C" fn args_get_str(
+    result: *const ArgsResult,
+    name: *const c_char,
+    out: *mut *const c_char,
+) -> c_int {
+    match lookup(result, name, out) {
+        Ok((parsed, name)) => match parsed.try_get_str(name) {
+            Ok(value) => {
+                let value = CString::new(value.replace('\0', "")).unwrap_or_default();
+                *out = value.as_ptr();
+                (*result).strings.borrow_mut().push(value);
+                ARGS_OK
+            }
+            Err(err) => code(&err),
+        },
+        Err(code) => code,
+    }
+}
+
+/// Frees a result of [`args_parse`], and the strings it handed out.
+///
+/// # Safety
+///
+/// `result` must be null or come from [`args_parse`] and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn args_free(result: *mut ArgsResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    #[test]
+    fn parses_through_c_calls() {
+        unsafe {
+            let mut result = ptr::null_mut();
+            let (schema, input) = (c("l,p#,d*"), c("-l -p 8080 -d /usr/logs"));
+            assert_eq!(args_parse(schema.as_ptr(), input.as_ptr(), &mut result), ARGS_OK);
+            assert!(args_error_message(result).is_null());
+            let (mut verbose, mut port, mut dir) = (false, 0, ptr::null());
+            assert_eq!(args_get_bool(result, c("l").as_ptr(), &mut verbose), ARGS_OK);
+            assert_eq!(args_get_number(result, c("p").as_ptr(), &mut port), ARGS_OK);
+            assert_eq!(args_get_str(result, c("d").as_ptr(), &mut dir), ARGS_OK);
+            assert_eq!((verbose, port), (true, 8080));
+            assert_eq!(CStr::from_ptr(dir).to_str(), Ok("/usr/logs"));
+            let code = args_get_number(result, c("l").as_ptr(), &mut port);
+            assert_eq!(code, ARGS_ERR_WRONG_TYPE);
+            let code = args_get_bool(result, c("x").as_ptr(), &mut verbose);
+            assert_eq!(code, ARGS_ERR_UNKNOWN_ARG);
+            args_free(result);
+        }
+    }
+
+    #[test]
+    fn reports_errors_by_code() {
+        unsafe {
+            let mut result = ptr::null_mut();
+            let (schema, input) = (c("p#"), c("-p eighty"));
+            let code = args_parse(schema.as_ptr(), input.as_ptr(), &mut result);
+            assert_eq!(code, ARGS_ERR_INVALID_VALUE);
+            let message = CStr::from_ptr(args_error_message(result)).to_str().unwrap();
+            let (arg, value) = ("p".to_string(), "eighty".to_string());
+            assert_eq!(message, ParseErr::NumberFormatErr { arg, value, span: None }.to_string());
+            let mut port = 0;
+            let code = args_get_number(result, c("p").as_ptr(), &mut port);
+            assert_eq!(code, ARGS_ERR_INVALID_VALUE);
+            args_free(result);
+            assert_eq!(args_parse(ptr::null(), input.as_ptr(), &mut result), ARGS_ERR_INPUT);
+            args_free(result);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Schemas and inputs generated from raw bytes, for fuzzers and property
+//! tests, with the `fuzzing` feature. [`FuzzInput`] consumes bytes the way
+//! `arbitrary::Unstructured` does, so a `cargo fuzz` target or a proptest
+//! strategy over `Vec<u8>` drives it without this crate depending on
+//! either:
+//!
+//! ```
+//! # #[cfg(feature = "fuzzing")]
+//! # {
+//! // fuzz_target!(|data: &[u8]| args::fuzzing::check_round_trip(data));
+//! // proptest! { fn round_trips(data: Vec<u8>) { check_round_trip(&data) } }
+//! args::fuzzing::check_round_trip(b"\x05\x11\x2a\x07\x93\xfe\x01\x40");
+//! # }
+//! ```
+
+use crate::schema::{flag, quote};
+use crate::{ArgKind, Schema};
+
+const NAMES: &[&str] = &["a", "b", "v", "p", "verbose", "port", "log-level", "dry-run"];
+
+const WORDS: &[&str] =
+    &["x", "in.txt", "a b", "", "-dash", "it's", "\"q\"", "back\\slash", "ünï"];
+
+/// Bytes read from the front as choices. Once they run out every choice
+/// is the first, so any input, even an empty one, gives a result.
+#[derive(Debug)]
+pub struct FuzzInput<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> FuzzInput<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        FuzzInput { bytes }
+    }
+
+    /// Whether every byte has been used.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    fn byte(&mut self) -> u8 {
+        match self.bytes.split_first() {
+            Some((&byte, rest)) => {
+                self.bytes = rest;
+                byte
+            }
+            None => 0,
+        }
+    }
+
+    /// A number below `n`, which must not be 0.
+    fn below(&mut self, n: usize) -> usize {
+        self.byte() as usize % n
+    }
+
+    fn pick<T: Copy>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())]
+    }
+
+    /// A schema of one to five flags of any type, each named once, and
+    /// possibly a positional argument before them.
+    pub fn schema(&mut self) -> Schema {
+        let mut builder = Schema::builder();
+        if self.below(4) == 0 {
+            builder = builder.positional("file");
+        }
+        let mut names = vec![];
+        for _ in 0..1 + self.below(5) {
+            let name = self.pick(NAMES);
+            if names.contains(&name) {
+                continue;
+            }
+            names.push(name);
+            builder = match self.below(5) {
+                0 => builder.bool(name),
+                1 => builder.string(name),
+                2 => builder.number(name),
+                3 => builder.string_list(name),
+                _ => builder.number_list(name),
+            };
+        }
+        builder.build().expect("generated schema is valid")
+    }
+
+    /// An input `schema` accepts: its positional arguments, then some of
+    /// its flags, each with values of its type.
+    pub fn input(&mut self, schema: &Schema) -> String {
+        let mut words = vec![];
+        for _ in schema.args().filter(|spec| spec.positional) {
+            words.push(quote(self.pick(WORDS)));
+        }
+        for spec in schema.args().filter(|spec| !spec.positional) {
+            if self.below(3) == 0 {
+                continue;
+            }
+            words.push(flag(spec.name()));
+            let count = match spec.kind() {
+                ArgKind::Bool => 0,
+                ArgKind::StrArray | ArgKind::NumberArray => 1 + self.below(3),
+                _ => 1,
+            };
+            for _ in 0..count {
+                let value = match spec.kind() {
+                    ArgKind::Number | ArgKind::NumberArray => {
+                        i16::from_le_bytes([self.byte(), self.byte()]).to_string()
+                    }
+                    _ => self.pick(WORDS).to_string(),
+                };
+                words.push(quote(&value));
+            }
+        }
+        words.join(" ")
+    }
+
+    /// The remaining bytes as text, lossily, for input that needn't be
+    /// valid.
+    pub fn text(&mut self) -> String {
+        let text = String::from_utf8_lossy(self.bytes).into_owned();
+        self.bytes = &[];
+        text
+    }
+}
+
+/// Generates a schema and an input it accepts from `data`, and panics
+/// unless the input parses and its [`ParsedArgs::to_command_line`]
+/// parses back to the same values, then parses the rest of `data` as
+/// arbitrary text, which must fail or succeed without panicking.
+///
+/// [`ParsedArgs::to_command_line`]: crate::ParsedArgs::to_command_line
+pub fn check_round_trip(data: &[u8]) {
+    let mut data = FuzzInput::new(data);
+    let schema = data.schema();
+    let input = data.input(&schema);
+    let args = match schema.parse(&input) {
+        Ok(args) => args,
+        Err(err) => panic!("generated input `{}` failed to parse: {:?}", input, err),
+    };
+    let line = args.to_command_line();
+    let reparsed = match schema.parse(&line) {
+        Ok(reparsed) => reparsed,
+        Err(err) => panic!("`{}`, written as `{}`, failed to parse: {:?}", input, line, err),
+    };
+    let (values, reread) = (args.iter().collect::<Vec<_>>(), reparsed.iter().collect::<Vec<_>>());
+    assert_eq!(values, reread, "`{}` read back differently as `{}`", input, line);
+    let _ = schema.parse(&data.text());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_inputs_round_trip() {
+        let mut state = 0x2545_f491_u32;
+        for _ in 0..2000 {
+            let data: Vec<u8> = (0..24)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 17;
+                    state ^= state << 5;
+                    state as u8
+                })
+                .collect();
+            check_round_trip(&data);
+        }
+        check_round_trip(&[]);
+    }
+
+    #[test]
+    fn bytes_choose_the_schema() {
+        let mut data = FuzzInput::new(&[1, 0, 5, 1, 1, 1]);
+        let schema = data.schema();
+        assert_eq!(schema.usage("app"), "usage: app [--port <string>]");
+        assert_eq!(data.input(&schema), "--port in.txt");
+        assert!(data.is_empty());
+    }
+}
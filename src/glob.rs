@@ -0,0 +1,193 @@
+//! Expansion of `*`, `?` and `[...]` patterns in values to the paths they
+//! match, for input that didn't go through a shell doing it, as on Windows.
+
+use crate::schema::ArgKind;
+use crate::{ParseErr, Schema};
+use std::path::{is_separator, Path};
+
+/// What a pattern matching no path gives, see [`Schema::glob`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NoMatches {
+    /// Fails with [`ParseErr::NoMatches`].
+    Fail,
+    /// Keeps the pattern as the value, as POSIX shells do.
+    Keep,
+    /// Drops the pattern, adding no value.
+    Skip,
+}
+
+impl Schema {
+    /// Replaces each value of the list `arg` given on the command line that
+    /// holds a `*`, `?` or `[...]` pattern with the paths matching it, in
+    /// order, so `-i *.txt` reads as `-i a.txt -i b.txt`. Like in a shell,
+    /// wildcards don't match a leading `.` or a `/`, and `[!...]` negates a
+    /// class. `no_matches` decides what a pattern matching nothing gives.
+    pub fn glob(mut self, arg: &str, no_matches: NoMatches) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.glob = Some(no_matches);
+        }
+        self
+    }
+
+    /// `values` of `name` with patterns expanded, if it is a list set up
+    /// with [`Schema::glob`].
+    pub(crate) fn expand_globs(
+        &self,
+        name: &str,
+        values: Vec<String>,
+    ) -> Result<Vec<String>, ParseErr> {
+        let spec = self.spec(name).filter(|spec| spec.kind == ArgKind::StrArray);
+        let no_matches = match spec.and_then(|spec| spec.glob) {
+            Some(no_matches) => no_matches,
+            None => return Ok(values),
+        };
+        let mut expanded = vec![];
+        for value in values {
+            if !has_wildcards(&value) {
+                expanded.push(value);
+                continue;
+            }
+            let paths = paths_matching(&value);
+            match no_matches {
+                _ if !paths.is_empty() => expanded.extend(paths),
+                NoMatches::Fail => {
+                    return Err(ParseErr::NoMatches { arg: name.to_string(), pattern: value });
+                }
+                NoMatches::Keep => expanded.push(value),
+                NoMatches::Skip => {}
+            }
+        }
+        Ok(expanded)
+    }
+}
+
+fn has_wildcards(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+/// The existing paths `pattern` matches, sorted within each directory.
+fn paths_matching(pattern: &str) -> Vec<String> {
+    let mut found = vec![String::new()];
+    for part in pattern.split_inclusive(is_separator) {
+        let name = part.trim_end_matches(is_separator);
+        if !has_wildcards(name) {
+            found.iter_mut().for_each(|path| path.push_str(part));
+            continue;
+        }
+        let separator = &part[name.len()..];
+        let name: Vec<char> = name.chars().collect();
+        let mut next = vec![];
+        for base in &found {
+            let entries = match std::fs::read_dir(if base.is_empty() { "." } else { base }) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut names: Vec<String> = entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .filter(|entry| !entry.starts_with('.') || name.first() == Some(&'.'))
+                .filter(|entry| matches(&name, &entry.chars().collect::<Vec<_>>()))
+                .collect();
+            names.sort();
+            for entry in names {
+                let path = format!("{}{}", base, entry);
+                if separator.is_empty() || Path::new(&path).is_dir() {
+                    next.push(path + separator);
+                }
+            }
+        }
+        found = next;
+    }
+    found.retain(|path| Path::new(path).exists());
+    found
+}
+
+/// Whether the file name `name` matches `pattern`.
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, _) => name.is_empty(),
+        (Some(('*', rest)), _) => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+        (Some(_), None) => false,
+        (Some(('?', rest)), Some((_, name))) => matches(rest, name),
+        (Some(('[', rest)), Some((&c, name))) => match class(rest, c) {
+            Some((hit, rest)) => hit && matches(rest, name),
+            None => c == '[' && matches(rest, name),
+        },
+        (Some((p, rest)), Some((c, name))) => p == c && matches(rest, name),
+    }
+}
+
+/// Whether the class at the start of `pattern`, just after its `[`,
+/// matches `c`, and the pattern after its `]`. `None` if it isn't closed,
+/// and so is a literal `[`.
+fn class(pattern: &[char], c: char) -> Option<(bool, &[char])> {
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    let start = negated as usize;
+    let end = start + 1 + pattern.get(start + 1..)?.iter().position(|&c| c == ']')?;
+    let set = &pattern[start..end];
+    let mut hit = false;
+    let mut i = 0;
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            hit |= (set[i]..=set[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            hit |= set[i] == c;
+            i += 1;
+        }
+    }
+    Some((hit != negated, &pattern[end + 1..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches_name(pattern: &str, name: &str) -> bool {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        matches(&chars(pattern), &chars(name))
+    }
+
+    #[test]
+    fn matches_wildcards_and_classes() {
+        assert!(matches_name("*.txt", "notes.txt"));
+        assert!(!matches_name("*.txt", "notes.md"));
+        assert!(matches_name("a?c", "abc") && !matches_name("a?c", "ac"));
+        assert!(matches_name("[a-c]x", "bx") && !matches_name("[a-c]x", "dx"));
+        assert!(matches_name("[!a]x", "bx") && !matches_name("[!a]x", "ax"));
+        assert!(matches_name("[]]", "]") && matches_name("a[", "a["));
+    }
+
+    #[test]
+    fn expands_patterns_to_paths() {
+        let dir = std::env::temp_dir().join(format!("args-glob-{}", std::process::id()));
+        for path in ["b.txt", "a.txt", ".c.txt", "sub/d.txt", "e.md"] {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, "").unwrap();
+        }
+        let dir = dir.display().to_string();
+        let schema = Schema::new("i[*],o[*]")
+            .unwrap()
+            .glob("i", NoMatches::Fail)
+            .glob("o", NoMatches::Skip);
+        let args = schema.parse(&format!("-i '{0}/*.txt' '{0}/s*/*' -i x -o '{0}/*.rs'", dir));
+        let expected = [
+            format!("{}/a.txt", dir),
+            format!("{}/b.txt", dir),
+            format!("{}/sub/d.txt", dir),
+            "x".to_string(),
+        ];
+        let args = args.unwrap();
+        assert_eq!(args.values_of("i"), Some(&expected[..]));
+        assert_eq!(args.values_of("o"), Some(&[][..]));
+        let pattern = format!("{}/*.rs", dir);
+        assert_eq!(
+            schema.parse(&format!("-i '{}'", pattern)).unwrap_err(),
+            ParseErr::NoMatches { arg: "i".to_string(), pattern: pattern.clone() }
+        );
+        let keep = Schema::new("i[*]").unwrap().glob("i", NoMatches::Keep);
+        let args = keep.parse(&format!("-i '{}'", pattern)).unwrap();
+        assert_eq!(args.values_of("i"), Some(&[pattern][..]));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
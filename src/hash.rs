@@ -0,0 +1,75 @@
+//! The hasher of the maps in [`ParsedArgs`](crate::ParsedArgs). Their keys
+//! are interned argument names from the schema, tiny and never chosen by the input,
+//! so with the `fast-hash` feature they skip SipHash's protection against
+//! crafted keys for a multiply-and-rotate hash in the style of rustc's
+//! `FxHasher`.
+
+use crate::symbols::Symbol;
+use std::collections::HashMap;
+
+#[cfg(feature = "fast-hash")]
+pub(crate) type BuildHasher = std::hash::BuildHasherDefault<FxHasher>;
+#[cfg(not(feature = "fast-hash"))]
+pub(crate) type BuildHasher = std::collections::hash_map::RandomState;
+
+/// A map keyed by argument name.
+pub(crate) type NameMap<V> = HashMap<Symbol, V, BuildHasher>;
+
+/// An empty [`NameMap`] with room for `capacity` names, e.g. one for each
+/// argument of the schema, so filling it doesn't grow it.
+pub(crate) fn name_map<V>(capacity: usize) -> NameMap<V> {
+    NameMap::with_capacity_and_hasher(capacity, BuildHasher::default())
+}
+
+#[cfg(feature = "fast-hash")]
+#[derive(Clone, Copy, Default)]
+pub(crate) struct FxHasher(u64);
+
+#[cfg(feature = "fast-hash")]
+impl FxHasher {
+    const SEED: u64 = 0x51_7c_c1_b7_27_22_0a_95;
+
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0.rotate_left(5) ^ word).wrapping_mul(Self::SEED);
+    }
+}
+
+#[cfg(feature = "fast-hash")]
+impl std::hash::Hasher for FxHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_le_bytes(word));
+        }
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.add(i as u64);
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.add(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(all(test, feature = "fast-hash"))]
+mod tests {
+    use super::*;
+    use std::hash::BuildHasher as _;
+
+    #[test]
+    fn hashes_names_apart() {
+        let hasher = BuildHasher::default();
+        let names = ["l", "p", "d", "log-level", "log-levels", "lp"];
+        let mut hashes: Vec<u64> = names.iter().map(|name| hasher.hash_one(name)).collect();
+        assert_eq!(hasher.hash_one("log-level"), hashes[3]);
+        hashes.sort_unstable();
+        hashes.dedup();
+        assert_eq!(hashes.len(), names.len());
+    }
+}
@@ -0,0 +1,510 @@
+//! Help screens and reference docs rendered from the schema, so they can't
+//! drift from it.
+
+use crate::replay::json_string;
+use crate::schema::{flag, ArgKind, Classification, Constraint, MASK};
+use crate::Schema;
+
+use std::io::IsTerminal;
+
+pub(crate) type Entries = Vec<(String, String)>;
+
+/// How [`Schema::help_styled`] lays out the help screen. The default is
+/// plain text with descriptions left unwrapped, as [`Schema::help`] gives.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct HelpStyle {
+    /// The number of columns to wrap descriptions at.
+    pub width: Option<usize>,
+    /// Whether to make headings bold and flags cyan with ANSI escapes.
+    pub color: bool,
+}
+
+/// The narrowest a wrapped description gets, however deep the flags are.
+const MIN_DESCRIPTION_WIDTH: usize = 20;
+
+impl HelpStyle {
+    /// A style for standard output: wrapped at `$COLUMNS`, or 80 columns on
+    /// a terminal, and colored on a terminal unless `NO_COLOR` is set.
+    pub fn detect() -> Self {
+        let terminal = std::io::stdout().is_terminal();
+        let columns = std::env::var("COLUMNS").ok().and_then(|columns| columns.parse().ok());
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        HelpStyle {
+            width: columns.or(Some(80).filter(|_| terminal)),
+            color: terminal && !no_color,
+        }
+    }
+
+    fn paint(&self, text: &str, code: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// `text` split into lines of at most `width` characters, breaking at
+/// spaces. A word longer than that gets a line of its own.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if line.chars().count() + 1 + word.chars().count() <= width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
+
+impl Schema {
+    /// A complete help screen: the [`Schema::usage`] line, then the
+    /// positional arguments and the options, one per line with their
+    /// descriptions aligned, followed by their defaults, allowed values
+    /// and deprecations. Hidden arguments are left out, and defaults of
+    /// arguments that aren't [`Classification::Public`] are masked.
+    pub fn help(&self, program: &str) -> String {
+        self.help_styled(program, &HelpStyle::default())
+    }
+
+    /// [`Schema::help`] laid out in `style`, e.g. [`HelpStyle::detect`]
+    /// for the terminal the program runs in.
+    pub fn help_styled(&self, program: &str, style: &HelpStyle) -> String {
+        let (arguments, options) = self.help_entries();
+        let width = arguments.iter().chain(&options).map(|(left, _)| left.chars().count()).max();
+        let width = width.unwrap_or(0);
+        let wrap_at = style.width.map(|columns| {
+            columns.saturating_sub(width + 4).max(MIN_DESCRIPTION_WIDTH)
+        });
+        let mut help = self.usage(program);
+        for (title, entries) in [("arguments", &arguments), ("options", &options)] {
+            if entries.is_empty() {
+                continue;
+            }
+            help.push_str(&format!("\n\n{}", style.paint(&format!("{}:", title), "1")));
+            for (left, text) in entries {
+                let lines = match wrap_at {
+                    Some(wrap_at) => wrap(text, wrap_at),
+                    None => vec![text.clone()],
+                };
+                let pad = " ".repeat(width - left.chars().count());
+                let first = lines.first().map_or("", String::as_str);
+                let line = format!("  {}{}  {}", style.paint(left, "36"), pad, first);
+                help.push('\n');
+                help.push_str(line.trim_end());
+                for line in lines.iter().skip(1) {
+                    help.push_str(&format!("\n{}{}", " ".repeat(width + 4), line));
+                }
+            }
+        }
+        help
+    }
+
+    /// The positional arguments and the options of the help screen, each
+    /// as it is written and what it is for.
+    pub(crate) fn help_entries(&self) -> (Entries, Entries) {
+        let visible = |name: &str| !self.hidden.iter().any(|hidden| hidden == name);
+        let mut arguments = vec![];
+        let mut options = vec![];
+        for spec in self.specs.iter().filter(|spec| visible(&spec.name)) {
+            let mut notes = vec![];
+            if let Some(default) = &spec.default {
+                let default = match spec.classification {
+                    Classification::Public => default.as_str(),
+                    _ => MASK,
+                };
+                notes.push(format!("default: {}", default));
+            }
+            for (other, value, default) in &spec.defaults_if {
+                let default = match spec.classification {
+                    Classification::Public => default.as_str(),
+                    _ => MASK,
+                };
+                let when = match value.as_str() {
+                    "true" => format!("{} is set", flag(other)),
+                    value => format!("{} is {}", flag(other), value),
+                };
+                notes.push(format!("default: {} when {}", default, when));
+            }
+            if !spec.choices.is_empty() {
+                notes.push(format!("choices: {}", spec.choices.join(", ")));
+            }
+            if let Some(var) = &spec.env {
+                notes.push(format!("env: {}", var));
+            }
+            if self.preset_arg.as_ref() == Some(&spec.name) && !self.presets.is_empty() {
+                notes.push(format!("presets: {}", self.presets().collect::<Vec<_>>().join(", ")));
+            }
+            if let Some(note) = self.deprecation_note(&spec.name) {
+                notes.push(note);
+            }
+            let mut text = spec.description.clone().unwrap_or_default();
+            if !notes.is_empty() {
+                text = format!("{} [{}]", text, notes.join("; ")).trim_start().to_string();
+            }
+            if spec.positional {
+                let dots = if spec.kind == ArgKind::StrArray { "..." } else { "" };
+                arguments.push((format!("<{}>{}", spec.name, dots), text));
+            } else {
+                let metavar = spec.value_metavar();
+                options.push((format!("{}{}", self.dashed(&spec.name), metavar), text));
+            }
+        }
+        for (switch, arg) in self.inversions.iter().filter(|(switch, _)| visible(switch)) {
+            options.push((self.dashed(switch), format!("turns {} off", self.dashed(arg))));
+        }
+        if self.help_program.is_some() {
+            options.push(("-h, --help".to_string(), "shows this help".to_string()));
+        }
+        if self.version.is_some() {
+            options.push(("-V, --version".to_string(), "shows the version".to_string()));
+        }
+        (arguments, options)
+    }
+
+    /// A Markdown table of the visible arguments, with their flag, type,
+    /// default and description, for reference docs and READMEs. Defaults of
+    /// arguments that aren't [`Classification::Public`] are masked.
+    pub fn to_markdown(&self) -> String {
+        let cell = |text: &str| text.replace('|', "\\|");
+        let mut table = "| Flag | Type | Default | Description |\n|---|---|---|---|\n".to_string();
+        let visible = self.specs.iter().filter(|spec| !self.hidden.contains(&spec.name));
+        for spec in visible {
+            let name = if spec.positional {
+                format!("<{}>", spec.name)
+            } else {
+                self.dashed(&spec.name)
+            };
+            let kind = match &spec.custom {
+                Some((_, type_name)) => type_name.as_str(),
+                None => spec.kind.type_name(),
+            };
+            let default = match (&spec.default, spec.classification) {
+                (Some(default), Classification::Public) => format!("`{}`", cell(default)),
+                (Some(_), _) => format!("`{}`", MASK),
+                (None, _) => String::new(),
+            };
+            let description = cell(spec.description.as_deref().unwrap_or(""));
+            let row = format!("| `{}` | {} | {} | {} |", name, cell(kind), default, description);
+            table.push_str(&row);
+            table.push('\n');
+        }
+        table
+    }
+
+    /// A JSON Schema (draft 2020-12) of the object the arguments make up,
+    /// compact on one line, for tools that consume the definition: one
+    /// property per argument with its type, description, allowed values and
+    /// default, the required arguments, and `requires`/`conflicts_with`
+    /// constraints as `dependentRequired` and `not`/`required` clauses, and
+    /// `one_of`/`any_of` groups as `oneOf`/`anyOf` of `required` clauses.
+    /// Defaults of arguments that aren't [`Classification::Public`] are left
+    /// out.
+    pub fn to_json_schema(&self) -> String {
+        let strings = |items: &[String]| -> String {
+            let items: Vec<String> = items.iter().map(|item| json_string(item)).collect();
+            format!("[{}]", items.join(","))
+        };
+        let mut properties = vec![];
+        for spec in &self.specs {
+            let scalar = match spec.kind {
+                ArgKind::Bool => "boolean",
+                ArgKind::Number | ArgKind::NumberArray => "integer",
+                _ => "string",
+            };
+            let literal = |value: &str| match spec.kind {
+                ArgKind::Bool | ArgKind::Number | ArgKind::NumberArray => value.to_string(),
+                _ => json_string(value),
+            };
+            let mut fields = vec![];
+            if let Some(description) = &spec.description {
+                fields.push(format!("\"description\":{}", json_string(description)));
+            }
+            let mut item = format!("\"type\":\"{}\"", scalar);
+            if !spec.choices.is_empty() {
+                item.push_str(&format!(",\"enum\":{}", strings(&spec.choices)));
+            }
+            let list = matches!(spec.kind, ArgKind::StrArray | ArgKind::NumberArray);
+            if list {
+                fields.push(format!("\"type\":\"array\",\"items\":{{{}}}", item));
+            } else {
+                fields.push(item);
+            }
+            let default = spec.default.as_ref().filter(|_| {
+                spec.classification == Classification::Public
+            });
+            match default {
+                Some(default) if list => {
+                    let items: Vec<String> = default.split(',').map(literal).collect();
+                    fields.push(format!("\"default\":[{}]", items.join(",")));
+                }
+                Some(default) => fields.push(format!("\"default\":{}", literal(default))),
+                None if spec.kind == ArgKind::Bool => fields.push("\"default\":false".into()),
+                None => {}
+            }
+            properties.push(format!("{}:{{{}}}", json_string(&spec.name), fields.join(",")));
+        }
+        let required: Vec<String> = self
+            .specs
+            .iter()
+            .filter(|spec| spec.required)
+            .map(|spec| spec.name.clone())
+            .collect();
+        let mut schema = vec![
+            "\"$schema\":\"https://json-schema.org/draft/2020-12/schema\"".to_string(),
+            "\"type\":\"object\"".to_string(),
+            format!("\"properties\":{{{}}}", properties.join(",")),
+            format!("\"required\":{}", strings(&required)),
+            "\"additionalProperties\":false".to_string(),
+        ];
+        let mut dependent: Vec<(&String, Vec<String>)> = vec![];
+        let mut conflicts = vec![];
+        for constraint in &self.constraints {
+            match constraint {
+                Constraint::Requires(arg, other) => {
+                    match dependent.iter_mut().find(|(name, _)| *name == arg) {
+                        Some((_, others)) => others.push(other.clone()),
+                        None => dependent.push((arg, vec![other.clone()])),
+                    }
+                }
+                Constraint::ConflictsWith(arg, other) => conflicts.push(format!(
+                    "{{\"not\":{{\"required\":{}}}}}",
+                    strings(&[arg.clone(), other.clone()])
+                )),
+                Constraint::OneOf(args, exactly) => {
+                    let required = |arg| format!("{{\"required\":{}}}", strings(arg));
+                    let members: Vec<String> =
+                        args.iter().map(|arg| required(std::slice::from_ref(arg))).collect();
+                    let keyword = if *exactly { "oneOf" } else { "anyOf" };
+                    conflicts.push(format!("{{\"{}\":[{}]}}", keyword, members.join(",")));
+                }
+                Constraint::RequiredIf(arg, other, value) => {
+                    let kind = self.spec(other).map(|spec| spec.kind);
+                    let typed = matches!(kind, Some(ArgKind::Bool) | Some(ArgKind::Number));
+                    let literal = match value.parse::<isize>() {
+                        _ if typed && (value == "true" || value == "false") => value.clone(),
+                        Ok(_) if typed => value.clone(),
+                        _ => json_string(value),
+                    };
+                    conflicts.push(format!(
+                        "{{\"if\":{{\"properties\":{{{}:{{\"const\":{}}}}},\
+                         \"required\":{}}},\"then\":{{\"required\":{}}}}}",
+                        json_string(other),
+                        literal,
+                        strings(std::slice::from_ref(other)),
+                        strings(std::slice::from_ref(arg))
+                    ));
+                }
+            }
+        }
+        if !dependent.is_empty() {
+            let entries: Vec<String> = dependent
+                .iter()
+                .map(|(arg, others)| format!("{}:{}", json_string(arg), strings(others)))
+                .collect();
+            schema.push(format!("\"dependentRequired\":{{{}}}", entries.join(",")));
+        }
+        if !conflicts.is_empty() {
+            schema.push(format!("\"allOf\":[{}]", conflicts.join(",")));
+        }
+        format!("{{{}}}", schema.join(","))
+    }
+
+    fn deprecation_note(&self, name: &str) -> Option<String> {
+        let (_, replacement) = self.deprecations.iter().find(|(arg, _)| arg == name)?;
+        Some(match replacement {
+            Some(replacement) => format!("deprecated, use {}", flag(replacement)),
+            None => "deprecated".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ParseErr;
+
+    #[test]
+    fn renders_the_whole_screen() {
+        let schema = Schema::builder()
+            .positional("input")
+            .description("file to read")
+            .bool("l")
+            .description("verbose logging")
+            .number("port")
+            .default("8080")
+            .description("port to listen on")
+            .string("c")
+            .metavar("WHEN")
+            .choices(&["auto", "never"])
+            .string("d")
+            .bool("x")
+            .build()
+            .unwrap()
+            .inverted("q", "l")
+            .deprecated("d", Some("c"))
+            .hidden("x");
+        assert_eq!(
+            schema.help("app"),
+            "usage: app [-l] [--port <number>] [-c WHEN] [-d <string>] [-q] <input>\n\
+             \n\
+             arguments:\n  \
+               <input>          file to read\n\
+             \n\
+             options:\n  \
+               -l               verbose logging\n  \
+               --port <number>  port to listen on [default: 8080]\n  \
+               -c WHEN          [choices: auto, never]\n  \
+               -d <string>      [deprecated, use -c]\n  \
+               -q               turns -l off"
+        );
+    }
+
+    #[test]
+    fn styled_help_wraps_and_colors() {
+        let schema = Schema::builder()
+            .bool("l")
+            .description("log every request and response in full detail")
+            .number("p")
+            .build()
+            .unwrap();
+        let style = HelpStyle { width: Some(40), color: false };
+        assert_eq!(
+            schema.help_styled("app", &style),
+            "usage: app [-l] [-p <number>]\n\
+             \n\
+             options:\n  \
+               -l           log every request and\n               \
+                            response in full detail\n  \
+               -p <number>"
+        );
+        let style = HelpStyle { width: None, color: true };
+        assert!(schema
+            .help_styled("app", &style)
+            .contains("\x1b[1moptions:\x1b[0m\n  \x1b[36m-l\x1b[0m           log"));
+        assert_eq!(wrap("a verylongword b", 5), vec!["a", "verylongword", "b"]);
+    }
+
+    #[test]
+    fn help_flags_stop_parsing() {
+        let schema = Schema::new("l,p#").unwrap().help_flags("app");
+        let help = "usage: app [-l] [-p <number>]\n\
+                    \n\
+                    options:\n  \
+                      -l\n  \
+                      -p <number>\n  \
+                      -h, --help   shows this help";
+        let requested = || ParseErr::HelpRequested(help.to_string());
+        assert_eq!(schema.parse("-l --help"), Err(requested()));
+        assert_eq!(schema.parse("-p x -h"), Err(requested()));
+        assert_eq!(crate::validate(&schema, "-q -h"), Err(vec![requested()]));
+        assert!(schema.parse("-l -- -h").is_ok());
+        let own = Schema::new("h*").unwrap().help_flags("app");
+        assert_eq!(own.parse("-h host").unwrap().get_str("h"), Some("host".to_string()));
+        assert_eq!(
+            Schema::new("l").unwrap().parse("-h").unwrap_err(),
+            ParseErr::UnknownArg {
+                arg: "h".into(),
+                typed: "-h".into(),
+                suggestion: Some("-l".into()),
+            }
+        );
+    }
+
+    #[test]
+    fn version_flags_stop_parsing() {
+        let schema = Schema::new("l").unwrap().version_flags("app", "1.2.0").help_flags("app");
+        let version = || ParseErr::VersionRequested("app 1.2.0".to_string());
+        assert_eq!(schema.parse("-l --version"), Err(version()));
+        assert_eq!(schema.parse("-V -h"), Err(version()));
+        assert!(matches!(schema.parse("-h -V"), Err(ParseErr::HelpRequested(_))));
+        assert!(schema.help("app").ends_with("-V, --version  shows the version"));
+        let own = Schema::new("V").unwrap().version_flags("app", "1.2.0");
+        assert_eq!(own.parse("-V").unwrap().get_bool("V"), Some(true));
+    }
+
+    #[test]
+    fn markdown_reference_table() {
+        let schema = Schema::builder()
+            .positional("input")
+            .bool("l")
+            .description("verbose | noisy")
+            .number("port")
+            .default("8080")
+            .string("token")
+            .default("abc")
+            .bool("x")
+            .build()
+            .unwrap()
+            .classify("token", Classification::Secret)
+            .hidden("x");
+        assert_eq!(
+            schema.to_markdown(),
+            "| Flag | Type | Default | Description |\n\
+             |---|---|---|---|\n\
+             | `<input>` | string |  |  |\n\
+             | `-l` | bool |  | verbose \\| noisy |\n\
+             | `--port` | number | `8080` |  |\n\
+             | `--token` | string | `***` |  |\n"
+        );
+    }
+
+    #[test]
+    fn json_schema_export() {
+        let schema = Schema::builder()
+            .bool("l")
+            .number("p")
+            .default("8080")
+            .description("port")
+            .string("c")
+            .choices(&["auto", "never"])
+            .required()
+            .number_list("n")
+            .default("1,2")
+            .string("k")
+            .string("t")
+            .default("secret")
+            .build()
+            .unwrap()
+            .requires("k", "c")
+            .conflicts_with("l", "k")
+            .one_of(&["l", "p"])
+            .required_if("k", "l", "true")
+            .classify("t", Classification::Secret);
+        assert_eq!(
+            schema.to_json_schema(),
+            "{\"$schema\":\"https://json-schema.org/draft/2020-12/schema\",\"type\":\"object\",\
+             \"properties\":{\
+             \"l\":{\"type\":\"boolean\",\"default\":false},\
+             \"p\":{\"description\":\"port\",\"type\":\"integer\",\"default\":8080},\
+             \"c\":{\"type\":\"string\",\"enum\":[\"auto\",\"never\"]},\
+             \"n\":{\"type\":\"array\",\"items\":{\"type\":\"integer\"},\"default\":[1,2]},\
+             \"k\":{\"type\":\"string\"},\
+             \"t\":{\"type\":\"string\"}},\
+             \"required\":[\"c\"],\"additionalProperties\":false,\
+             \"dependentRequired\":{\"k\":[\"c\"]},\
+             \"allOf\":[{\"not\":{\"required\":[\"l\",\"k\"]}},\
+             {\"oneOf\":[{\"required\":[\"l\"]},{\"required\":[\"p\"]}]},\
+             {\"if\":{\"properties\":{\"l\":{\"const\":true}},\"required\":[\"l\"]},\
+             \"then\":{\"required\":[\"k\"]}}]}"
+        );
+    }
+
+    #[test]
+    fn choices_limit_values() {
+        let schema = Schema::builder().string("c").choices(&["auto", "never"]).build().unwrap();
+        assert_eq!(schema.parse("-c auto").unwrap().get_str("c"), Some("auto".to_string()));
+        assert_eq!(
+            schema.parse("-c always").unwrap_err(),
+            ParseErr::ValidationFailed {
+                arg: "c".to_string(),
+                message: "expected one of: auto, never".to_string(),
+            }
+        );
+        let misplaced = Schema::builder().bool("l").choices(&["true"]).build();
+        assert_eq!(misplaced.unwrap_err(), ParseErr::InvalidSchema);
+    }
+}
@@ -1,334 +1,2881 @@
 #![allow(unused_imports)]
 use core::fmt::Debug;
-use std::{collections::HashMap, marker::PhantomData, str::FromStr};
+use std::{any::Any, collections::HashMap, marker::PhantomData, ops::Range, str::FromStr};
+use std::{borrow::Cow, path::PathBuf, time::Duration};
 
-fn token_to_kv(token: &str) -> Result<(&str, Box<dyn Args>), ParseErr> {
-    match token.len() {
-        0 => Err(ParseErr::InvalidSchema),
-        1 => Ok((token, Box::new(BoolArg(false)))),
-        _ => {
-            let arg_name = &token[..=0];
-            match &token[1..] {
-                "*" => Ok((arg_name, Box::new(StringArg(None)))),
-                "#" => Ok((arg_name, Box::new(NumberArg(None)))),
-                "[*]" => Ok((arg_name, Box::new(StrArrayArg(vec![])))),
-                "[#]" => Ok((arg_name, Box::new(NumberArrayArg(vec![])))),
-                t => Err(ParseErr::UnsupportedArgType(t.to_string())),
-            }
-        }
-    }
+#[cfg(test)]
+extern crate self as args;
+
+mod commands;
+mod completion;
+mod env_expand;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+mod glob;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod hash;
+mod help;
+mod limits;
+#[cfg(feature = "man")]
+mod man;
+mod matcher;
+mod os;
+mod parser;
+mod prompt;
+pub mod registry;
+pub mod replay;
+mod resolver;
+mod response_file;
+mod schema;
+mod suggest;
+mod symbols;
+mod warnings;
+#[cfg(any(feature = "toml", feature = "yaml"))]
+mod schema_file;
+mod template;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod types;
+mod validate;
+#[cfg(feature = "wire")]
+mod wire;
+
+use hash::NameMap;
+use symbols::Symbol;
+pub use commands::{Commands, ParsedCommand};
+pub use completion::{complete, Completion, Shell, ValueHint};
+pub use glob::NoMatches;
+pub use help::HelpStyle;
+pub use limits::Limits;
+pub use matcher::Matcher;
+pub use os::{parse_env, parse_os};
+pub use parser::{Parser, ParserBuilder, Profile, UnknownArgs};
+pub use resolver::{Resolver, Source};
+pub use schema::{
+    AbsentBool, Accumulation, ArgKind, ArgSpec, Classification, PathCheck, Schema, SchemaBuilder,
+    SchemaError,
+};
+pub use suggest::Suggestion;
+pub use template::render_template;
+pub use validate::validate;
+#[cfg(feature = "macros")]
+pub use args_macros::{schema_str, ArgEnum, FromArgs};
+pub use warnings::{Deduplicated, StderrSink, WarningSink};
+
+/// A bare `-`, read as a value meaning "standard input" rather than as a
+/// flag.
+pub const STDIN: &str = "-";
+
+pub fn parse(schema: &str, input: &str) -> Result<ParsedArgs, ParseErr> {
+    Schema::new(schema)?.parse(input)
 }
 
-pub fn parse<'a>(
-    schema: &'a str,
-    input: &'a str,
-) -> Result<HashMap<&'a str, Box<dyn Args>>, ParseErr> {
-    let args: Result<HashMap<&str, Box<dyn Args>>, ParseErr> =
-        schema.split(',').map(str::trim).map(token_to_kv).collect();
-    args.and_then(|mut args| {
-        for token in TokensIterator::from(input.to_string()) {
-            if let Some(arg) = args.get_mut(&token.modifier[..]) {
-                let result = arg.set(token.values);
-                if result.is_err() {
-                    return Err(result.unwrap_err());
-                }
-                
-            } else {
-                return Err(ParseErr::UnknownArg(token.modifier));
-            }
-        }
-        Ok(args)
-    })
+/// Parses words that have already been split, see [`Schema::parse_from`].
+pub fn parse_from<I, S>(schema: &str, args: I) -> Result<ParsedArgs, ParseErr>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    Schema::new(schema)?.parse_from(args)
+}
+
+/// Parses `input` into a type that knows its own schema, such as one
+/// deriving [`FromArgs`] with the `macros` feature:
+///
+/// ```
+/// # #[cfg(feature = "macros")]
+/// # {
+/// #[derive(args::FromArgs)]
+/// struct ServerConfig {
+///     /// Port to listen on
+///     #[arg(name = "p", default = "8080")]
+///     port: u16,
+///     verbose: bool,
+///     log_file: Option<String>,
+/// }
+///
+/// let cfg: ServerConfig = args::parse_into("--verbose --log-file out.log").unwrap();
+/// assert_eq!((cfg.port, cfg.verbose, cfg.log_file.as_deref()), (8080, true, Some("out.log")));
+/// # }
+/// ```
+pub fn parse_into<T: ArgsSchema>(input: &str) -> Result<T, ParseErr> {
+    T::schema().parse_into(input)
+}
+
+/// Parses with defaults computed at run time, see
+/// [`Schema::parse_with_defaults`].
+pub fn parse_with_defaults(
+    schema: &str,
+    input: &str,
+    defaults: &HashMap<&str, ArgValue>,
+) -> Result<ParsedArgs, ParseErr> {
+    Schema::new(schema)?.parse_with_defaults(input, defaults)
 }
 
-struct TokensIterator {
+/// The outcome of a successful parse: the argument values keyed by name,
+/// plus any non-fatal warnings raised along the way. Two results are equal
+/// when they hold the same values, however the input spelled them.
+#[derive(Clone)]
+pub struct ParsedArgs {
+    args: NameMap<ArgValue>,
+    order: Vec<Symbol>,
+    positionals: Vec<String>,
+    kinds: NameMap<schema::ArgKind>,
+    classifications: NameMap<Classification>,
+    warnings: Vec<ParseWarning>,
+    normalizations: Vec<Normalization>,
+    sources: NameMap<Source>,
+    occurrences: NameMap<usize>,
+    given: NameMap<Vec<String>>,
+    trailing: Vec<String>,
+    unknown: Vec<String>,
+    tokens: Vec<RawToken>,
     input: String,
-    cursor: usize,
+    os_words: Vec<(String, std::ffi::OsString)>,
 }
 
-impl TokensIterator {
-    fn from(input: String) -> Self {
-        Self {
-            input,
-            cursor: 0,
+impl ParsedArgs {
+    pub fn get(&self, name: &str) -> Option<&dyn Args> {
+        self.args.get(name).map(|value| value as &dyn Args)
+    }
+
+    /// The value of `name` as an [`ArgValue`], to match on.
+    pub fn value(&self, name: &str) -> Option<&ArgValue> {
+        self.args.get(name)
+    }
+
+    /// Every argument of the schema with its value, in declaration order,
+    /// e.g. to log or compare whole results.
+    pub fn iter(&self) -> std::vec::IntoIter<(&str, &ArgValue)> {
+        let args = self.order.iter().map(|name| (&**name, &self.args[name]));
+        args.collect::<Vec<_>>().into_iter()
+    }
+
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get(name)?.as_bool()
+    }
+
+    pub fn get_str(&self, name: &str) -> Option<String> {
+        self.get(name)?.get()
+    }
+
+    pub fn get_number(&self, name: &str) -> Option<isize> {
+        self.get(name)?.as_number()
+    }
+
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        self.get(name)?.as_float()
+    }
+
+    pub fn get_path(&self, name: &str) -> Option<PathBuf> {
+        self.get(name)?.as_path()
+    }
+
+    pub fn get_duration(&self, name: &str) -> Option<Duration> {
+        self.get(name)?.as_duration()
+    }
+
+    pub fn get_list(&self, name: &str) -> Vec<String> {
+        self.get(name).map(Args::as_list).unwrap_or_default()
+    }
+
+    pub fn get_str_list(&self, name: &str) -> Vec<String> {
+        self.get(name).map(Args::as_str_array).unwrap_or_default()
+    }
+
+    pub fn get_number_list(&self, name: &str) -> Vec<isize> {
+        self.get(name).map(Args::as_num_array).unwrap_or_default()
+    }
+
+    /// The value of `name`, or [`ParseErr::UnknownArg`] for names outside
+    /// the schema.
+    fn known(&self, name: &str) -> Result<&ArgValue, ParseErr> {
+        self.value(name).ok_or_else(|| ParseErr::unknown_arg(name))
+    }
+
+    /// Like [`ParsedArgs::get_bool`], failing with [`ParseErr::UnknownArg`]
+    /// for names outside the schema and [`ParseErr::WrongType`] for
+    /// arguments that aren't bools.
+    pub fn try_get_bool(&self, name: &str) -> Result<bool, ParseErr> {
+        match self.known(name)? {
+            ArgValue::Bool(value) => Ok(*value),
+            ArgValue::OptBool(value) => {
+                value.ok_or_else(|| ParseErr::MissingValue(name.to_string()))
+            }
+            other => Err(ParseErr::WrongType { arg: name.to_string(), expected: other.kind() }),
         }
     }
-}
 
-#[derive(Debug, PartialEq)]
-struct Token {
-    modifier: String,
-    values: Vec<String>,
-}
+    /// Like [`ParsedArgs::get_str`], failing the way
+    /// [`ParsedArgs::try_get_bool`] does, and with [`ParseErr::MissingValue`]
+    /// if the argument wasn't given.
+    pub fn try_get_str(&self, name: &str) -> Result<String, ParseErr> {
+        match self.known(name)? {
+            value @ ArgValue::Str(_) | value @ ArgValue::OptStr { .. } => {
+                value.get().ok_or_else(|| ParseErr::MissingValue(name.to_string()))
+            }
+            other => Err(ParseErr::WrongType { arg: name.to_string(), expected: other.kind() }),
+        }
+    }
 
+    pub fn try_get_number(&self, name: &str) -> Result<isize, ParseErr> {
+        match self.known(name)? {
+            ArgValue::Number(Some(value)) => Ok(*value),
+            ArgValue::Number(None) => Err(ParseErr::MissingValue(name.to_string())),
+            other => Err(ParseErr::WrongType { arg: name.to_string(), expected: other.kind() }),
+        }
+    }
 
-impl Iterator for TokensIterator {
-    type Item = Token;
+    /// Like [`ParsedArgs::get_str_list`], failing the way
+    /// [`ParsedArgs::try_get_str`] does for a list without items.
+    pub fn try_get_str_list(&self, name: &str) -> Result<Vec<String>, ParseErr> {
+        match self.known(name)? {
+            ArgValue::StrArray(list) if list.is_empty() => {
+                Err(ParseErr::MissingValue(name.to_string()))
+            }
+            ArgValue::StrArray(list) => Ok(list.clone()),
+            other => Err(ParseErr::WrongType { arg: name.to_string(), expected: other.kind() }),
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        for segment  in self.input.split('-').skip(self.cursor) {
-            self.cursor += 1; //advance the cursor
-            if segment.len() > 0 {
-                let modifier = segment.split(' ').nth(0).expect("").to_string();
-                let values: Vec<String> = segment.split(' ').skip(1).filter(|i| i.len() > 0).map(ToString::to_string).collect();
-                return Some(Token {modifier, values});
+    pub fn try_get_number_list(&self, name: &str) -> Result<Vec<isize>, ParseErr> {
+        match self.known(name)? {
+            ArgValue::NumberArray(list) if list.is_empty() => {
+                Err(ParseErr::MissingValue(name.to_string()))
             }
+            ArgValue::NumberArray(list) => Ok(list.clone()),
+            other => Err(ParseErr::WrongType { arg: name.to_string(), expected: other.kind() }),
         }
-        None
     }
-}
 
-pub trait Args {
-    fn set(&mut self, tokens: Vec<String>) -> Result<(), ParseErr>;
-    fn get(&self) -> Option<String>;
-    fn as_number(&self) -> Option<isize> {
-        self.get().and_then(|v| v.parse().ok())
+    /// The value of `name` read as any [`FromStr`] type, e.g.
+    /// `args.get_as::<u16>("p")`. Fails with [`ParseErr::UnknownArg`] for
+    /// names outside the schema, [`ParseErr::MissingValue`] if it has no
+    /// value and [`ParseErr::InvalidValue`] if the value isn't a `T`.
+    pub fn get_as<T: FromStr>(&self, name: &str) -> Result<T, ParseErr> {
+        let arg = self.get(name).ok_or_else(|| ParseErr::unknown_arg(name))?;
+        let value = arg.get().ok_or_else(|| ParseErr::MissingValue(name.to_string()))?;
+        value
+            .parse()
+            .map_err(|_| ParseErr::InvalidValue { arg: name.to_string(), value })
     }
-    fn as_bool(&self) -> Option<bool> {
-        self.get().and_then(|v| v.parse().ok())
+
+    /// The value of `name` read as a `T`, or `default` if it has none, isn't
+    /// a `T` or isn't in the schema.
+    pub fn get_or<T: FromStr>(&self, name: &str, default: T) -> T {
+        self.get_str(name).and_then(|value| value.parse().ok()).unwrap_or(default)
     }
-    fn as_str_array(&self) -> Vec<String> {
-        self.get().map(|v| v.split(',').map(ToString::to_string).collect()).unwrap_or(vec![])
+
+    /// Like [`ParsedArgs::get_as`], with `None` for an argument without a
+    /// value rather than an error, for optional fields of a [`FromArgs`]
+    /// type.
+    pub fn get_opt_as<T: FromStr>(&self, name: &str) -> Result<Option<T>, ParseErr> {
+        match self.get_as(name) {
+            Err(ParseErr::MissingValue(_)) => Ok(None),
+            result => result.map(Some),
+        }
     }
-    fn as_num_array(&self) -> Vec<isize> {
-        self.get().map(|v| v.split(',').filter_map(|v|v.parse().ok()).collect()).unwrap_or(vec![])
+
+    /// Every value of `name` read as a `T`, see [`Args::as_list`]. Fails the
+    /// way [`ParsedArgs::get_as`] does, except that a list without items is
+    /// empty.
+    pub fn get_list_as<T: FromStr>(&self, name: &str) -> Result<Vec<T>, ParseErr> {
+        let arg = self.get(name).ok_or_else(|| ParseErr::unknown_arg(name))?;
+        let values = arg.as_list().into_iter();
+        values
+            .map(|value| value.parse().map_err(|_| ParseErr::InvalidValue {
+                arg: name.to_string(),
+                value,
+            }))
+            .collect()
     }
-}
 
-#[derive(Debug)]
-struct StringArg(Option<String>);
-#[derive(Debug)]
-struct BoolArg(bool);
-#[derive(Debug)]
-struct NumberArg(Option<isize>);
-#[derive(Debug)]
-struct StrArrayArg(Vec<String>);
-#[derive(Debug)]
-struct NumberArrayArg(Vec<isize>);
-
-impl Args for NumberArrayArg {
-    fn set(&mut self, tokens: Vec<String>) -> Result<(), ParseErr> {
-        self.0.append(
-            &mut tokens.into_iter()
-                .filter_map(|t| t.parse().ok())
-                .collect()
-        );
-        Ok(())
+    /// The result converted into an application's own type.
+    pub fn extract<T: FromArgs>(&self) -> Result<T, ParseErr> {
+        T::from_args(self)
     }
 
-    fn get(&self) -> Option<String> {
-        Some(self.0.iter().map(ToString::to_string).collect::<Vec<String>>().join(","))
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
     }
-}
-impl Args for StrArrayArg {
-    fn set(&mut self, mut tokens: Vec<String>) -> Result<(), ParseErr> {
-        self.0.append(&mut tokens);
-        Ok(())
+
+    /// How the input was rewritten before being interpreted, in input
+    /// order, for tools that need to log exactly what they acted on.
+    pub fn normalizations(&self) -> &[Normalization] {
+        &self.normalizations
     }
 
-    fn get(&self) -> Option<String> {
-        Some(self.0.join(","))
+    /// Everything after a bare `--`, taken as is rather than as flags. With
+    /// [`Schema::capture_rest`], everything from the first word the schema
+    /// has no place for.
+    pub fn trailing(&self) -> &[String] {
+        &self.trailing
     }
-}
 
-impl Args for StringArg {
-    fn set(&mut self, val: Vec<String>) -> Result<(), ParseErr> {
-        self.0.replace(val.join(""));
-        Ok(())
+    /// The flags the schema doesn't declare, with their values, as given,
+    /// when parsing with [`Schema::collect_unknown`].
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
     }
 
-    fn get(&self) -> Option<String> {
-        self.0.to_owned()
+    /// Every flag read from the input, in the order given, with the values
+    /// it took and where it was, for audit logs and tools that replay
+    /// exactly what was typed. Flags the schema doesn't declare are
+    /// included when parsing with [`Schema::collect_unknown`]; positional
+    /// arguments and words after `--` aren't flags. Values are as given,
+    /// including those of arguments that aren't [`Classification::Public`].
+    pub fn tokens(&self) -> &[RawToken] {
+        &self.tokens
     }
-}
-impl Args for BoolArg {
-    fn set(&mut self, values: Vec<String>) -> Result<(), ParseErr> {
-        if values.len() == 0 || values.join("").to_lowercase() == "true" {
-            self.0 = true;
-        } else {
-            self.0 = false;
+
+    /// Whether `name` was given the [`STDIN`] sentinel, as in `-f -`, for
+    /// reading standard input instead of a file. For lists, whether any
+    /// element is the sentinel.
+    pub fn is_stdin(&self, name: &str) -> bool {
+        let values = self.get(name).map(Args::as_str_array).unwrap_or_default();
+        values.iter().any(|value| value == STDIN)
+    }
+
+    /// Whether `name` was given on the command line, whatever its value.
+    /// Unlike [`Args::as_bool`], this tells a bool left out from one given
+    /// as `false`.
+    pub fn is_present(&self, name: &str) -> bool {
+        self.occurrences_of(name) > 0
+    }
+
+    /// How many times `name` was given on the command line, e.g. 3 for
+    /// `-v -v -v` or `-vvv` when repeats are allowed. Negations such as
+    /// `--no-v` count too.
+    pub fn occurrences_of(&self, name: &str) -> usize {
+        self.occurrences.get(name).copied().unwrap_or(0)
+    }
+
+    /// Every value given to `name` on the command line, in order and as
+    /// read, before they are combined into its value: `-d a b` gives `a`
+    /// and `b` where [`ParsedArgs::get_str`] gives `a b`. `None` if it wasn't
+    /// given, and empty if it was given without values.
+    pub fn values_of(&self, name: &str) -> Option<&[String]> {
+        self.given.get(name).map(Vec::as_slice)
+    }
+
+    /// Where the value of `name` came from, or `None` if nothing set it.
+    /// E.g. a value merged from elsewhere should only override a setting
+    /// when this is [`Source::CommandLine`], the user having passed it.
+    pub fn source(&self, name: &str) -> Option<Source> {
+        self.sources.get(name).copied()
+    }
+
+    /// Takes the value of `name` from `other`, along with where it came
+    /// from and how it was given.
+    pub(crate) fn adopt(&mut self, name: &str, other: &ParsedArgs) {
+        let (name, value) = match other.args.get_key_value(name) {
+            Some(entry) => entry,
+            None => return,
+        };
+        self.args.insert(name.clone(), value.clone());
+        match other.sources.get(name) {
+            Some(source) => self.sources.insert(name.clone(), *source),
+            None => self.sources.remove(name),
+        };
+        match other.occurrences.get(name) {
+            Some(count) => self.occurrences.insert(name.clone(), *count),
+            None => self.occurrences.remove(name),
+        };
+        match other.given.get(name) {
+            Some(values) => self.given.insert(name.clone(), values.clone()),
+            None => self.given.remove(name),
+        };
+    }
+
+    /// Parses more input into this result, as if it had followed everything
+    /// parsed so far: lists keep growing, repeats count towards occurrence
+    /// limits and constraints see all of it. Values from the environment or
+    /// defaults stay unless the new input sets them. `schema` must be the
+    /// one that produced this result, and nothing changes if parsing fails.
+    pub fn absorb(&mut self, schema: &Schema, input: &str) -> Result<(), ParseErr> {
+        let combined = format!("{} {}", self.input, input);
+        let mut next = schema.read_input(Input::typed(&combined))?;
+        for (name, source) in &self.sources {
+            let value = self.args.get(name).and_then(|arg| arg.get());
+            if let (false, Some(value)) = (*source == Source::CommandLine, value) {
+                schema.fill(&mut next, name, value, *source)?;
+            }
         }
+        schema.apply_defaults(&mut next)?;
+        schema.check_parsed(&next)?;
+        let new_warnings: Vec<_> = next
+            .warnings
+            .iter()
+            .filter(|warning| !self.warnings.contains(warning))
+            .cloned()
+            .collect();
+        schema.emit_warnings(&new_warnings);
+        next.os_words = std::mem::take(&mut self.os_words);
+        *self = next;
         Ok(())
     }
 
-    fn get(&self) -> Option<String> {
-        Some(self.0.to_string())
+    /// Environment-variable style view of every argument that has a value,
+    /// e.g. `p` becomes `APP_P=8080` for the prefix `APP`. Dashes in names
+    /// are turned into underscores.
+    pub fn to_env_map(&self, prefix: &str) -> HashMap<String, String> {
+        self.args
+            .iter()
+            .filter_map(|(name, arg)| {
+                let name = name.replace('-', "_").to_uppercase();
+                let key = if prefix.is_empty() {
+                    name
+                } else {
+                    format!("{}_{}", prefix, name)
+                };
+                arg.get().map(|value| (key, value))
+            })
+            .collect()
     }
-}
-impl Args for NumberArg {
-    fn set(&mut self, val: Vec<String>) -> Result<(), ParseErr> {
-        match val.join("").parse() {
-            Ok(val) => {
-                self.0.replace(val);
-                Ok(())
+
+    /// Every argument that has a value, keyed by name. Lists are joined
+    /// with `,` the way [`Args::get`] returns them.
+    pub fn to_string_map(&self) -> HashMap<String, String> {
+        let values = self.args.iter();
+        values.filter_map(|(name, arg)| Some((name.to_string(), arg.get()?))).collect()
+    }
+
+    /// A JSON object of every argument in schema order, keeping the types:
+    /// bools and numbers stay unquoted, lists become arrays and arguments
+    /// without a value are `null`.
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .iter()
+            .map(|(name, arg)| {
+                let value = match arg {
+                    ArgValue::Bool(value) | ArgValue::OptBool(Some(value)) => value.to_string(),
+                    ArgValue::Number(Some(number)) => number.to_string(),
+                    ArgValue::StrArray(list) => {
+                        let items: Vec<String> =
+                            list.iter().map(|item| replay::json_string(item)).collect();
+                        format!("[{}]", items.join(","))
+                    }
+                    ArgValue::NumberArray(list) => {
+                        let items: Vec<String> = list.iter().map(ToString::to_string).collect();
+                        format!("[{}]", items.join(","))
+                    }
+                    _ => arg.get().map_or("null".to_string(), |value| replay::json_string(&value)),
+                };
+                format!("{}:{}", replay::json_string(name), value)
+            })
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    /// A command line that parses back to the same values, e.g. to log the
+    /// effective invocation or to spawn a child process with it: positional
+    /// arguments first, then every flag that has a value, then the trailing
+    /// words after `--`. Values are quoted where needed but, unlike the
+    /// [`Debug`] output, not masked.
+    pub fn to_command_line(&self) -> String {
+        let mut words = vec![];
+        for name in &self.positionals {
+            let values = self.args[name.as_str()].given_words().unwrap_or_default();
+            words.extend(values.iter().map(|word| schema::quote(word)));
+        }
+        for (name, arg) in self.iter() {
+            if self.positionals.iter().any(|positional| positional == name) {
+                continue;
             }
-            Err(_) => Err(ParseErr::NumberFormatErr(val.join(""))),
+            if let Some(values) = arg.given_words() {
+                words.push(schema::flag(name));
+                words.extend(values.iter().map(|value| schema::quote(value)));
+            }
+        }
+        if !self.trailing.is_empty() {
+            words.push("--".to_string());
+            words.extend(self.trailing.iter().map(|word| schema::quote(word)));
         }
+        words.join(" ")
     }
+}
 
-    fn get(&self) -> Option<String> {
-        self.0.map(|v| v.to_string())
+impl PartialEq for ParsedArgs {
+    fn eq(&self, other: &Self) -> bool {
+        self.args == other.args
+            && self.sources == other.sources
+            && self.occurrences == other.occurrences
+            && self.given == other.given
+            && self.trailing == other.trailing
+            && self.unknown == other.unknown
+            && self.warnings == other.warnings
     }
 }
 
-impl Debug for dyn Args {
+impl Debug for ParsedArgs {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}", self.get())
+        let args = self.iter().map(|(name, arg)| {
+            let value = match self.classifications.get(name) {
+                Some(Classification::Secret) => arg.get().map(|_| schema::MASK.to_string()),
+                _ => arg.get(),
+            };
+            (name, value)
+        });
+        let sources = self.order.iter().filter_map(|name| Some((name, self.sources.get(name)?)));
+        // A quoted secret would still show in the word it was read from.
+        let secret = Some(&Classification::Secret);
+        let secrets: Vec<&String> = self
+            .given
+            .iter()
+            .filter(|(name, _)| self.classifications.get(*name) == secret)
+            .flat_map(|(_, values)| values.iter().filter(|value| !value.is_empty()))
+            .collect();
+        let masked = |read: &str| secrets.iter().any(|value| read.contains(value.as_str()));
+        let normalizations = self.normalizations.iter().map(|normalization| match normalization {
+            Normalization::Unquoted(_, read) if masked(read) => {
+                Normalization::Unquoted(schema::MASK.to_string(), schema::MASK.to_string())
+            }
+            normalization => normalization.clone(),
+        });
+        f.debug_struct("ParsedArgs")
+            .field("args", &OrderedMap(args.collect()))
+            .field("warnings", &self.warnings)
+            .field("normalizations", &normalizations.collect::<Vec<_>>())
+            .field("sources", &OrderedMap(sources.collect()))
+            .field("trailing", &self.trailing)
+            .finish()
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    mod token_iterator {
-        use super::*;
-        #[test]
-        fn test_token_iter() {
-            let tokens = TokensIterator::from("-d /var/logs -p 8080 -l".to_string());
-            let mut iter = tokens.into_iter();
-            assert_eq!(iter.next().unwrap(), Token {
-                modifier: 'd'.to_string(),
-                values: vec!["/var/logs".to_string()],
-            });
-            assert_eq!(iter.next().unwrap(), Token {
-                modifier: 'p'.to_string(),
-                values: vec!["8080".to_string()],
-            });
-            assert_eq!(iter.next().unwrap(), Token {
-                modifier: 'l'.to_string(),
-                values: vec![],
-            });
-            assert_eq!(iter.next(), None);
-        }   
-    }
-    mod boolean_args {
-        use super::*;
-        #[test]
-        fn parse_bool_arg_true() {
-            let args = parse("l", "-l").unwrap();
-            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
+/// One `name = value` line per argument, in schema order and with the
+/// names aligned, for debug dumps. Lists show as `[a, b]`, arguments
+/// without a value as `-`, and secrets are masked.
+impl std::fmt::Display for ParsedArgs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let width = self.order.iter().map(|name| name.chars().count()).max().unwrap_or(0);
+        for (i, (name, arg)) in self.iter().enumerate() {
+            let value = match arg {
+                _ if arg.get().is_none() => "-".to_string(),
+                _ if self.classifications.get(name) == Some(&Classification::Secret) => {
+                    schema::MASK.to_string()
+                }
+                ArgValue::StrArray(list) => format!("[{}]", list.join(", ")),
+                ArgValue::NumberArray(list) => {
+                    let list: Vec<String> = list.iter().map(ToString::to_string).collect();
+                    format!("[{}]", list.join(", "))
+                }
+                _ => arg.get().unwrap_or_default(),
+            };
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:width$} = {}", name, value, width = width)?;
         }
+        Ok(())
+    }
+}
 
-        #[test]
-        fn parse_explicit_true() {
-            let args = parse("l", "-l true").unwrap();
-            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
-        }
+/// Entries shown the way a map is, but in the order given.
+struct OrderedMap<K, V>(Vec<(K, V)>);
 
-        #[test]
-        fn parse_explicit_true_case_insensitive() {
-            let args = parse("l", "-l True").unwrap();
-            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
+impl<K: Debug, V: Debug> Debug for OrderedMap<K, V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.0.iter().map(|(key, value)| (key, value))).finish()
+    }
+}
 
-            let args = parse("l", "-l TRUE").unwrap();
-            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
-        }
+/// Whether a flag takes numbers, or `None` if there's no such flag.
+type Numeric<'f> = &'f dyn Fn(&str) -> Option<bool>;
 
-        #[test]
-        fn parse_explicit_false() {
-            let args = parse("l", "-l false").unwrap();
-            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), false);
-        }
+/// What the tokenizer knows of the flags, from a schema or a
+/// [`Numeric`] lookup.
+#[derive(Clone, Copy, Default)]
+struct Names<'s> {
+    schema: Option<&'s Schema>,
+    numeric: Option<Numeric<'s>>,
+    prefixes: &'s str,
+}
 
-        #[test]
-        fn parse_bool_arg_false() {
-            let args = parse("l", "").unwrap();
-            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), false);
+impl Names<'_> {
+    /// Whether the flag, or the flag in a cluster taking a value, takes
+    /// numbers. `None` for unknown flags.
+    fn takes_numbers(&self, flag: &str) -> Option<bool> {
+        match (self.schema, self.numeric) {
+            (Some(schema), _) => {
+                let name = schema.canonical_name(flag.to_string());
+                let kind = match schema.flag_spec(&name) {
+                    Some(spec) => spec.kind,
+                    None => return schema.is_name(&name).then_some(false),
+                };
+                Some(matches!(kind, ArgKind::Number | ArgKind::NumberArray))
+            }
+            (None, Some(numeric)) => numeric(flag),
+            (None, None) => None,
         }
     }
-    mod no_args {
-        use super::*;
-        #[test]
-        #[should_panic]
-        fn no_args() {
-            let args = parse("", "").unwrap();
-            assert!(args.get("d").is_none());
+
+    /// `word` as `-x` or `--name` if it is a flag, `None` if it is a value
+    /// such as [`STDIN`].
+    /// Words starting with one of the alternative `prefixes` are flags only
+    /// if they name one, so `/var/logs` stays a value.
+    fn flag<'a>(&self, word: &Word<'a>) -> Option<Cow<'a, str>> {
+        if word.raw == STDIN {
+            return None;
         }
-    }
-    mod str_args {
-        use super::*;
-        #[test]
-        fn parses_single_arg() {
-            let args = parse("d*", "-d /var/logs").unwrap();
-            assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/logs");
+        if word.raw.starts_with('-') {
+            return Some(word.text.clone());
         }
+        let prefix = word.raw.chars().next().filter(|c| self.prefixes.contains(*c))?;
+        let flag = &word.text[prefix.len_utf8()..];
+        let name = flag.split('=').next().unwrap_or(flag);
+        self.takes_numbers(name)?;
+        let dashes = if name.chars().count() > 1 { "--" } else { "-" };
+        Some(Cow::Owned(format!("{}{}", dashes, flag)))
+    }
 
-        #[test]
-        fn parse_single_arg_2() {
-            let args = parse("n*", "-n foo").unwrap();
-            assert_eq!(args.get("n").unwrap().get().unwrap(), "foo");
-        }
+    /// Whether `word` is a negative number given to the flag `modifier`,
+    /// rather than a flag of its own.
+    fn is_negative_value(&self, modifier: &str, word: &Word) -> bool {
+        let digits = match word.raw.strip_prefix('-') {
+            Some(digits) if word.text.parse::<isize>().is_ok() => digits,
+            _ => return false,
+        };
+        self.takes_numbers(modifier) == Some(true) && self.takes_numbers(digits).is_none()
+    }
 
-        #[test]
-        fn parses_multiple_args() {
-            let args = parse("d*,n*", "-d /var/logs -n foo").unwrap();
-            assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/logs");
-            assert_eq!(args.get("n").unwrap().get().unwrap(), "foo");
+    /// The long flag `text` with its name spelled out if it abbreviates a
+    /// single one, and the name as given: `--verbose` for `--verb`.
+    fn expand(&self, text: &str) -> Option<(String, String)> {
+        let flag = text.strip_prefix("--")?;
+        let (name, rest) = flag.split_at(flag.find('=').unwrap_or(flag.len()));
+        match self.schema?.expansions(name).as_slice() {
+            [full] => Some((name.to_string(), format!("--{}{}", full, rest))),
+            _ => None,
         }
     }
-    mod number_args {
-        use super::*;
-        #[test]
-        fn parse_number_arg() {
-            let args = parse("p#", "-p 8080").unwrap();
-            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
-        }
+
+    fn is_value(&self, modifier: &str, word: &Word) -> bool {
+        self.flag(word).is_none() || self.is_negative_value(modifier, word)
     }
 
-    mod error_cases {
-        use super::*;
+    /// How many of the words `taken` after the flag `modifier` and its
+    /// `attached` values it takes, see [`Schema::split_taken`].
+    fn takes(&self, modifier: &str, attached: &[Cow<str>], taken: &[Word]) -> usize {
+        let schema = match self.schema {
+            Some(schema) => schema,
+            None => return taken.len(),
+        };
+        let name = schema.canonical_name(modifier.to_string());
+        let kind = schema.flag_spec(&name).map_or(ArgKind::Bool, |spec| spec.kind);
+        let mut values: Vec<String> = attached.iter().map(|value| value.to_string()).collect();
+        values.extend(taken.iter().map(|word| word.text.to_string()));
+        let kept = schema.split_taken(kind, values).0.len();
+        kept.saturating_sub(attached.len())
+    }
 
-        #[test]
-        fn should_return_err_if_no_schema() {
-            let args = parse("", "");
-            assert_eq!(args.unwrap_err(), ParseErr::InvalidSchema);
-        }
+    /// The name of the flag `modifier` if it is a list, which takes every
+    /// value after it.
+    fn list(&self, modifier: &str) -> Option<String> {
+        let name = self.schema?.canonical_name(modifier.to_string());
+        let spec = self.schema?.flag_spec(&name)?;
+        matches!(spec.kind, ArgKind::StrArray | ArgKind::NumberArray).then_some(name)
+    }
+}
 
-        #[test]
-        fn should_return_invalid_arg_type_err() {
-            let args = parse("p!", "-p 8080");
-            assert_eq!(
-                args.unwrap_err(),
-                ParseErr::UnsupportedArgType("!".to_string())
-            );
-        }
+impl<'a> IntoIterator for &'a ParsedArgs {
+    type Item = (&'a str, &'a ArgValue);
+    type IntoIter = std::vec::IntoIter<Self::Item>;
 
-        #[test]
-        fn should_return_unknown_arg_err() {
-            let args = parse("d*", "-p 8080");
-            assert_eq!(args.unwrap_err(), ParseErr::UnknownArg("p".to_string()));
-        }
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
 
-        #[test]
-        fn should_return_number_format_err() {
-            let args = parse("p#", "-p foo");
-            assert_eq!(args.unwrap_err(), ParseErr::NumberFormatErr("foo".to_string()));
+/// `args["p"]` is `args.get("p")`, but panics for names outside the schema.
+impl std::ops::Index<&str> for ParsedArgs {
+    type Output = dyn Args;
+
+    fn index(&self, name: &str) -> &Self::Output {
+        match self.args.get(name) {
+            Some(arg) => arg,
+            None => panic!("no argument named '{}' in the schema", name),
         }
     }
+}
 
-    mod array_args {
-        use super::*;
+/// Reads the flags of an input one [`Token`] at a time, without checking
+/// them against a schema: see [`TokensIterator::new`] and
+/// [`Schema::tokens`]. Words after a `--` end up in
+/// [`TokensIterator::trailing`].
+pub struct TokensIterator<'s> {
+    words: Lexer<'s>,
+    rewritten: Vec<(String, String)>,
+    abbreviated: Vec<(String, String)>,
+    names: Names<'s>,
+    pending: std::collections::VecDeque<Token<'s>>,
+    trailing: Vec<String>,
+    diverting: bool,
+    positionals: Vec<String>,
+    capturing: bool,
+    slots: Option<usize>,
+    stopping: bool,
+    remainder: Vec<String>,
+    limited: bool,
+    read: usize,
+    exceeded: Option<ParseErr>,
+}
 
-        #[test]
-        fn parse_str_arr_arg() {
-            let args = parse("s[*]", "-s this is an array");
-            assert_eq!(args.unwrap().get("s").unwrap().get().unwrap(), "this,is,an,array");
-            let args = parse("s[*]", "-s this is an array");
-            assert_eq!(args.unwrap().get("s").unwrap().as_str_array(), vec!["this","is","an","array"]);
-        }
+impl<'s> TokensIterator<'s> {
+    /// Reads every word starting with `-` as a flag, taking the words up to
+    /// the next flag as its values.
+    pub fn new(input: &'s str) -> Self {
+        Self::from(input)
+    }
 
-        #[test]
-        fn parse_number_arr_arg() {
-            let args = parse("p[#]", "-p 1 2 3 4 5");
-            assert_eq!(args.unwrap().get("p").unwrap().as_num_array(), vec![1,2,3,4,5]);
+    /// The words after a `--`, once the iterator has reached it.
+    pub fn trailing(&self) -> &[String] {
+        &self.trailing
+    }
+
+    /// The words read as positional arguments so far, when reading with
+    /// [`Schema::tokens`] for a schema that has them.
+    pub fn positionals(&self) -> &[String] {
+        &self.positionals
+    }
+
+    fn from(input: &'s str) -> Self {
+        Self {
+            words: split_words(input),
+            rewritten: vec![],
+            abbreviated: vec![],
+            names: Names::default(),
+            pending: Default::default(),
+            trailing: vec![],
+            diverting: false,
+            positionals: vec![],
+            capturing: false,
+            slots: None,
+            stopping: false,
+            remainder: vec![],
+            limited: false,
+            read: 0,
+            exceeded: None,
         }
     }
-}
 
-#[derive(PartialEq, Debug)]
+    /// Reads words such as `-1` as negative numbers rather than flags when
+    /// they follow a flag taking numbers.
+    fn with_numeric(mut self, numeric: Numeric<'s>) -> Self {
+        self.names.numeric = Some(numeric);
+        self
+    }
+
+    /// Also reads words such as `/p` as flags, for each character of
+    /// `prefixes`.
+    fn with_prefixes(mut self, prefixes: &'s str) -> Self {
+        self.names.prefixes = prefixes;
+        self
+    }
+
+    /// Splits the input following POSIX shell rules, see [`lex`].
+    fn with_posix_quoting(mut self) -> Self {
+        self.words.posix = true;
+        self
+    }
+
+    /// Sets words that don't follow a flag aside as positional instead of
+    /// reading them as flag names.
+    fn with_positionals(mut self) -> Self {
+        self.diverting = true;
+        self
+    }
+
+    /// Ends at the first word that isn't a flag nor taken by one, or after a
+    /// `--`, leaving the words from there on as typed in `remainder`.
+    fn stopping_at_words(mut self) -> Self {
+        self.stopping = true;
+        self
+    }
+
+    /// Ends as soon as the input goes over the schema's [`Limits`] on words
+    /// or on the values of a list, leaving why in `exceeded`, rather than
+    /// lexing the rest of it first.
+    fn within_limits(mut self) -> Self {
+        self.limited = true;
+        self
+    }
+
+    /// Counts a word read, and whether the limit on words still holds.
+    fn count_word(&mut self) -> bool {
+        self.read += 1;
+        match self.names.schema.filter(|_| self.limited) {
+            Some(schema) => self.record(schema.check_token_count(self.read)),
+            None => true,
+        }
+    }
+
+    /// Whether `check` passed, keeping its error if not.
+    fn record(&mut self, check: Result<(), ParseErr>) -> bool {
+        match check {
+            Ok(()) => true,
+            Err(err) => {
+                self.exceeded = Some(err);
+                false
+            }
+        }
+    }
+
+    /// Like [`TokensIterator::from`], but also reads clustered short flags
+    /// and attached values, such as `-lrt` or `-p8080`, negative numbers
+    /// such as `-p -1` and alternative flag prefixes, the way `schema`
+    /// declares them.
+    fn for_schema(input: &'s str, schema: &'s Schema) -> Self {
+        Self::for_input(Input::typed(input), schema)
+    }
+
+    /// [`TokensIterator::for_schema`] for input that may be split already.
+    fn for_input(input: Input<'s>, schema: &'s Schema) -> Self {
+        let names = Names {
+            schema: Some(schema),
+            prefixes: &schema.prefixes,
+            ..Names::default()
+        };
+        let positionals = schema.specs.iter().filter(|spec| spec.positional);
+        let mut slots = Some(0);
+        for spec in positionals {
+            match spec.kind {
+                ArgKind::StrArray | ArgKind::NumberArray => slots = None,
+                _ => slots = slots.map(|slots| slots + 1),
+            }
+        }
+        let tokens = Self {
+            names,
+            diverting: schema.has_positionals(),
+            capturing: schema.capture_rest,
+            slots: slots.filter(|_| schema.capture_rest),
+            words: input.lexer(),
+            ..Self::from(input.text)
+        };
+        if schema.shell_words {
+            tokens.with_posix_quoting()
+        } else {
+            tokens
+        }
+    }
+
+    /// The (as typed, as read) spelling of each word whose quotes or
+    /// escapes were resolved since the last call.
+    fn take_rewritten(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.rewritten)
+    }
+
+    /// The (as typed, as declared) name of each long flag read as an
+    /// abbreviation since the last call, see [`Schema::abbreviations`].
+    fn take_abbreviated(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.abbreviated)
+    }
+
+    fn take_positional(&mut self, word: Word<'s>) {
+        if word.text != word.raw {
+            self.rewritten.push((word.raw.to_string(), word.text.to_string()));
+        }
+        self.positionals.push(word.text.into_owned());
+        if let Some(slots) = &mut self.slots {
+            *slots -= 1;
+        }
+    }
+
+    /// Sets `words`, which the flag before them doesn't take, aside as
+    /// positional while positional arguments are left to take them, and
+    /// captures the rest.
+    fn divert(&mut self, words: Vec<Word<'s>>) {
+        let mut words = words.into_iter();
+        while self.slots != Some(0) {
+            match words.next() {
+                Some(word) => self.take_positional(word),
+                None => return,
+            }
+        }
+        let rest: Vec<Word<'s>> = words.collect();
+        if !rest.is_empty() {
+            self.capture(rest);
+        }
+    }
+
+    /// The quote left open at the end of the input, once the word it opens
+    /// has been read.
+    fn open_quote(&self) -> Option<char> {
+        self.words.open
+    }
+
+    /// Moves `words` and the rest of the input to `remainder`.
+    fn stop(&mut self, words: Vec<Word<'s>>) {
+        self.remainder.extend(words.into_iter().map(|word| word.raw.to_string()));
+        self.remainder.extend(self.words.by_ref().map(|word| word.raw.to_string()));
+    }
+
+    /// Moves `words` and the rest of the input to `trailing`.
+    fn capture(&mut self, words: Vec<Word<'s>>) {
+        self.trailing.extend(words.into_iter().map(|word| word.text.into_owned()));
+        self.trailing.extend(self.words.by_ref().map(|word| word.text.into_owned()));
+    }
+}
+
+/// A flag read from the input, with the values following it. The name and
+/// values borrow from the input, except for words whose quotes or escapes
+/// were resolved and for the flags of a cluster.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Token<'a> {
+    /// The flag without its dashes, e.g. `port` for `--port`.
+    pub name: Cow<'a, str>,
+    pub values: Vec<Cow<'a, str>>,
+    /// Byte offsets of the flag and its values in the input. Flags from
+    /// one cluster, such as `-lrt`, share the span of the whole word.
+    pub span: Range<usize>,
+}
+
+/// A flag as it was read from the input, see [`ParsedArgs::tokens`].
+#[derive(Clone, PartialEq, Debug)]
+pub struct RawToken {
+    /// The flag without its dashes, before case folding, e.g. `P` for `-P`.
+    pub name: String,
+    /// The values the flag took, with quotes and escapes resolved.
+    pub values: Vec<String>,
+    /// The flag and its values as typed, e.g. `-d 'my logs'`.
+    pub text: String,
+    /// Byte offsets of `text` in the input, after response files were
+    /// expanded. Flags from one cluster share the span of the whole word.
+    pub span: Range<usize>,
+}
+
+/// One word of the input, with quotes and backslash escapes resolved.
+/// Only a word whose raw spelling starts with `-`, or an alternative
+/// prefix, is a flag, so `"-1"` can be passed as a value.
+#[derive(Debug, PartialEq)]
+struct Word<'a> {
+    /// Borrowed from the input unless quotes or escapes were resolved.
+    text: Cow<'a, str>,
+    raw: &'a str,
+    span: Range<usize>,
+}
+
+/// Splits `input` on whitespace outside of quotes. Within single quotes
+/// everything is literal; within double quotes `\"` and `\\` are escapes;
+/// elsewhere a backslash escapes any character. An unterminated quote runs
+/// to the end of the input.
+fn split_words(input: &str) -> Lexer<'_> {
+    lex(input, false)
+}
+
+/// Input that [`lex`] splits back into `words`, in either mode. Escaping
+/// each character rather than quoting the word keeps a flag recognizable
+/// as one, and a newline is quoted since a backslash before it joins lines.
+fn join_words<I, S>(words: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    let words: Vec<String> = words.into_iter().map(|word| escape_word(word.as_ref())).collect();
+    words.join(" ")
+}
+
+/// `word` the way [`join_words`] writes it.
+fn escape_word(word: &str) -> String {
+    if word.is_empty() {
+        return "\"\"".to_string();
+    }
+    let mut escaped = String::new();
+    for c in word.chars() {
+        match c {
+            '\n' => escaped.push_str("'\n'"),
+            c if c.is_whitespace() || c == '"' || c == '\'' || c == '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Input to read, either typed as one string or already split into
+/// `words`. Errors and tokens refer to spans of `text`, which for split
+/// words is [`join_words`] of them: the words are read as given rather
+/// than lexed back out of it.
+#[derive(Clone, Copy)]
+pub(crate) struct Input<'a> {
+    pub(crate) text: &'a str,
+    words: Option<&'a [String]>,
+}
+
+impl<'a> Input<'a> {
+    pub(crate) fn typed(text: &'a str) -> Self {
+        Self { text, words: None }
+    }
+
+    /// `words`, which `text` must join as [`join_words`] does.
+    pub(crate) fn split(text: &'a str, words: &'a [String]) -> Self {
+        Self { text, words: Some(words) }
+    }
+
+    /// The words of the input, lexed from `text` unless already split.
+    fn lexer(self) -> Lexer<'a> {
+        let mut lexer = split_words(self.text);
+        if let Some(words) = self.words {
+            let mut start = 0;
+            let words = words.iter().map(|word| {
+                let span = start..start + escape_word(word).len();
+                start = span.end + 1;
+                Word { text: Cow::Borrowed(word.as_str()), raw: &self.text[span.clone()], span }
+            });
+            lexer.given = Some(words.collect());
+        }
+        lexer
+    }
+}
+
+/// [`split_words`], following POSIX shell rules if `posix` is set: within
+/// double quotes `\$` and `` \` `` are escapes too, and a backslash before
+/// a newline joins the lines.
+fn lex(input: &str, posix: bool) -> Lexer<'_> {
+    let chars = input.char_indices().peekable();
+    Lexer { input, chars, posix, open: None, peeked: None, given: None }
+}
+
+/// The words of an input, lexed one at a time as they're asked for, so the
+/// input is scanned once and never split up ahead of the tokens. A quote
+/// left open, which runs to the end of the input, is in `open` once the
+/// last word has been lexed. Words already split are in `given` instead.
+struct Lexer<'a> {
+    input: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    posix: bool,
+    open: Option<char>,
+    peeked: Option<Word<'a>>,
+    given: Option<std::collections::VecDeque<Word<'a>>>,
+}
+
+impl<'a> Lexer<'a> {
+    /// The next word if `f` accepts it, as [`Peekable::next_if`] gives.
+    ///
+    /// [`Peekable::next_if`]: std::iter::Peekable::next_if
+    fn next_if(&mut self, f: impl FnOnce(&Word<'a>) -> bool) -> Option<Word<'a>> {
+        let word = self.next()?;
+        if f(&word) {
+            return Some(word);
+        }
+        self.peeked = Some(word);
+        None
+    }
+
+    fn lex_word(&mut self) -> Option<Word<'a>> {
+        if let Some(given) = &mut self.given {
+            return given.pop_front();
+        }
+        let (input, posix) = (self.input, self.posix);
+        let escapable = |c: &char| *c == '"' || *c == '\\' || posix && (*c == '$' || *c == '`');
+        let chars = &mut self.chars;
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let start = chars.peek()?.0;
+        // The text is only copied out of the input once a quote or escape
+        // makes it differ from the raw word.
+        let mut text: Option<String> = None;
+        let mut quote = None;
+        while let Some((i, c)) = chars.next_if(|(_, c)| quote.is_some() || !c.is_whitespace()) {
+            let verbatim = match (quote, c) {
+                (None, '"') | (None, '\'') => {
+                    quote = Some(c);
+                    false
+                }
+                (Some(q), c) if q == c => {
+                    quote = None;
+                    false
+                }
+                (Some('\''), _) => true,
+                (Some('"'), '\\') | (None, '\\')
+                    if posix && chars.next_if(|(_, c)| *c == '\n').is_some() =>
+                {
+                    false
+                }
+                (Some(_), '\\') => match chars.next_if(|(_, c)| escapable(c)) {
+                    Some((_, escaped)) => {
+                        text.get_or_insert_with(|| input[start..i].to_string()).push(escaped);
+                        continue;
+                    }
+                    None => true,
+                },
+                (None, '\\') => match chars.next() {
+                    Some((_, escaped)) => {
+                        text.get_or_insert_with(|| input[start..i].to_string()).push(escaped);
+                        continue;
+                    }
+                    None => true,
+                },
+                _ => true,
+            };
+            match (verbatim, &mut text) {
+                (true, Some(text)) => text.push(c),
+                (true, None) => {}
+                (false, _) => {
+                    text.get_or_insert_with(|| input[start..i].to_string());
+                }
+            }
+        }
+        self.open = quote;
+        let end = chars.peek().map_or(input.len(), |&(end, _)| end);
+        let raw = &input[start..end];
+        Some(Word {
+            text: text.map_or(Cow::Borrowed(raw), Cow::Owned),
+            raw,
+            span: start..end,
+        })
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Word<'a>;
+
+    fn next(&mut self) -> Option<Word<'a>> {
+        self.peeked.take().or_else(|| self.lex_word())
+    }
+}
+
+/// The part of `text` at `range`, borrowing from the input if `text` does.
+fn slice<'a>(text: &Cow<'a, str>, range: Range<usize>) -> Cow<'a, str> {
+    match text {
+        Cow::Borrowed(text) => Cow::Borrowed(&text[range]),
+        Cow::Owned(text) => Cow::Owned(text[range].to_string()),
+    }
+}
+
+impl<'s> Iterator for TokensIterator<'s> {
+    type Item = Token<'s>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(token) = self.pending.pop_front() {
+            return Some(token);
+        }
+        if self.exceeded.is_some() {
+            return None;
+        }
+        let names = self.names;
+        while self.diverting {
+            match self.words.next_if(|word| names.flag(word).is_none()) {
+                Some(word) if self.stopping => {
+                    self.stop(vec![word]);
+                    return None;
+                }
+                Some(word) if self.slots == Some(0) => {
+                    self.capture(vec![word]);
+                    return None;
+                }
+                Some(_) if !self.count_word() => return None,
+                Some(word) => self.take_positional(word),
+                None => break,
+            }
+        }
+        let word = self.words.next()?;
+        if word.raw == "--" && self.stopping {
+            self.stop(vec![]);
+            return None;
+        }
+        if word.raw == "--" {
+            self.trailing.extend(self.words.by_ref().map(|word| word.text.into_owned()));
+            return None;
+        }
+        if word.text != word.raw {
+            self.rewritten.push((word.raw.to_string(), word.text.to_string()));
+        }
+        let text = match names.flag(&word) {
+            Some(text) => text,
+            None if self.stopping => {
+                self.stop(vec![word]);
+                return None;
+            }
+            None if self.capturing => {
+                self.capture(vec![word]);
+                return None;
+            }
+            None => word.text.clone(),
+        };
+        if !self.count_word() {
+            return None;
+        }
+        let text = match names.expand(&text) {
+            Some((given, expanded)) => {
+                let name = expanded[2..].split('=').next().unwrap_or_default().to_string();
+                self.abbreviated.push((given, name));
+                Cow::Owned(expanded)
+            }
+            None => text,
+        };
+        let flag = text.trim_start_matches('-');
+        let dashes = text.len() - flag.len();
+        let short = dashes == 1;
+        let clusters = names.schema.filter(|schema| short && !schema.single_dash);
+        let cluster = clusters.and_then(|schema| schema.split_short(flag));
+        let name = flag.split('=').next().unwrap_or(flag);
+        if self.capturing && cluster.is_none() && names.takes_numbers(name).is_none() {
+            self.capture(vec![word]);
+            return None;
+        }
+        let (modifier, mut values): (Cow<str>, Vec<Cow<str>>) = match (cluster, flag.find('=')) {
+            (Some(mut cluster), _) => {
+                let (modifier, value) = cluster.pop()?;
+                self.pending.extend(cluster.into_iter().map(|(name, _)| Token {
+                    name: Cow::Owned(name),
+                    values: vec![],
+                    span: word.span.clone(),
+                }));
+                (Cow::Owned(modifier), value.into_iter().map(Cow::Owned).collect())
+            }
+            (None, Some(equals)) => {
+                let equals = dashes + equals;
+                (slice(&text, dashes..equals), vec![slice(&text, equals + 1..text.len())])
+            }
+            (None, None) => (slice(&text, dashes..text.len()), vec![]),
+        };
+        let mut span = word.span;
+        let mut taken = vec![];
+        let list = names.list(&modifier).filter(|_| self.limited);
+        while let Some(value) = self.words.next_if(|word| names.is_value(&modifier, word)) {
+            taken.push(value);
+            if !self.count_word() {
+                return None;
+            }
+            if let (Some(name), Some(schema)) = (&list, names.schema) {
+                if !self.record(schema.check_value_count(name, values.len() + taken.len())) {
+                    return None;
+                }
+            }
+        }
+        if self.stopping || self.capturing {
+            let excess = taken.split_off(names.takes(&modifier, &values, &taken));
+            if !self.stopping {
+                self.divert(excess);
+            } else if !excess.is_empty() {
+                self.stop(excess);
+            }
+        }
+        for value in taken {
+            span.end = value.span.end;
+            if value.text != value.raw {
+                self.rewritten.push((value.raw.to_string(), value.text.to_string()));
+            }
+            values.push(value.text);
+        }
+        let token = Token {
+            name: modifier,
+            values,
+            span,
+        };
+        match self.pending.pop_front() {
+            Some(first) => {
+                self.pending.push_back(token);
+                Some(first)
+            }
+            None => Some(token),
+        }
+    }
+}
+
+/// Conversion of parsed arguments into an application's own type, such as
+/// a config struct, in one place. The `try_get_*` accessors and
+/// [`ParsedArgs::get_as`] report unknown names, missing values and wrong
+/// types as a [`ParseErr`], so `?` is usually all the error handling needed:
+///
+/// ```
+/// use args::{FromArgs, ParseErr, ParsedArgs, Schema};
+///
+/// struct Config {
+///     port: u16,
+///     dir: Option<String>,
+///     verbose: bool,
+/// }
+///
+/// impl FromArgs for Config {
+///     fn from_args(args: &ParsedArgs) -> Result<Self, ParseErr> {
+///         Ok(Config {
+///             port: args.get_as("p")?,
+///             dir: args.get_opt_as("d")?,
+///             verbose: args.try_get_bool("v")?,
+///         })
+///     }
+/// }
+///
+/// let schema = Schema::new("p#,d*,v").unwrap();
+/// let config: Config = schema.parse_into("-p 8080 -v").unwrap();
+/// assert_eq!((config.port, config.dir, config.verbose), (8080, None, true));
+/// ```
+pub trait FromArgs: Sized {
+    fn from_args(args: &ParsedArgs) -> Result<Self, ParseErr>;
+}
+
+/// A [`FromArgs`] type that declares the schema it's read with, as
+/// `#[derive(FromArgs)]` does, see [`parse_into`].
+pub trait ArgsSchema: FromArgs {
+    fn schema() -> Schema;
+}
+
+/// An enum whose variants are the values of a choice argument, declared
+/// with [`SchemaBuilder::choices_of`] and read back with
+/// [`ParsedArgs::get_as`]. `#[derive(ArgEnum)]` with the `macros` feature
+/// implements it, with a `FromStr` that ignores case:
+///
+/// ```
+/// # #[cfg(feature = "macros")]
+/// # {
+/// use args::{ArgEnum, Schema};
+///
+/// #[derive(ArgEnum, Debug, PartialEq)]
+/// enum Format {
+///     Json,
+///     Yaml,
+///     #[arg(name = "toml")]
+///     TomlFile,
+/// }
+///
+/// let schema = Schema::builder().string("format").choices_of::<Format>().build().unwrap();
+/// let args = schema.parse("--format JSON").unwrap();
+/// assert_eq!(args.get_as::<Format>("format").unwrap(), Format::Json);
+/// assert_eq!(Format::VARIANTS, &["json", "yaml", "toml"]);
+/// # }
+/// ```
+pub trait ArgEnum: FromStr {
+    /// The values, as typed and listed in help, in declaration order.
+    const VARIANTS: &'static [&'static str];
+}
+
+/// Reading and setting the value of an argument. [`ArgValue`] implements it
+/// for every [`ArgKind`].
+pub trait Args {
+    /// Sets the value of the argument `name` from the words given to it.
+    fn set(&mut self, name: &str, tokens: Vec<String>) -> Result<(), ParseErr>;
+    fn get(&self) -> Option<String>;
+    fn as_number(&self) -> Option<isize> {
+        self.get().and_then(|v| v.parse().ok())
+    }
+    fn as_bool(&self) -> Option<bool> {
+        self.get().and_then(|v| v.parse().ok())
+    }
+    fn as_str_array(&self) -> Vec<String> {
+        self.get().map(|v| v.split(',').map(ToString::to_string).collect()).unwrap_or(vec![])
+    }
+    fn as_num_array(&self) -> Vec<isize> {
+        self.get().map(|v| v.split(',').filter_map(|v|v.parse().ok()).collect()).unwrap_or(vec![])
+    }
+    fn as_float(&self) -> Option<f64> {
+        self.get().and_then(|v| v.parse().ok())
+    }
+    fn as_path(&self) -> Option<PathBuf> {
+        self.get().map(PathBuf::from)
+    }
+    /// A number followed by `ms`, `s`, `m`, `h` or `d`, such as `90s` or
+    /// `1.5h`. A bare number is in seconds.
+    fn as_duration(&self) -> Option<Duration> {
+        self.get().and_then(|v| parse_duration(&v))
+    }
+    /// Every value given, one per item of a list and a single one for
+    /// other arguments, without splitting values that contain a `,`.
+    fn as_list(&self) -> Vec<String> {
+        self.as_str_array()
+    }
+    fn presence(&self) -> Presence {
+        self.get().map_or(Presence::Absent, Presence::Value)
+    }
+    /// The concrete value behind a `dyn Args`, to downcast with
+    /// [`Any::downcast_ref`].
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// Whether an argument was given, and with what, telling a bare
+/// `--color` apart from `--color never` for optional-value arguments.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Presence {
+    Absent,
+    Bare,
+    Value(String),
+}
+
+/// The value of an argument, by [`ArgKind`]. Lists and scalars that
+/// weren't given are empty, and bools `false`.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ArgValue {
+    Bool(bool),
+    /// A bool that is unset until given, see [`AbsentBool::Unset`].
+    OptBool(Option<bool>),
+    Str(Option<String>),
+    /// The value taken when given bare is `implicit`.
+    OptStr { implicit: String, presence: Presence },
+    Number(Option<isize>),
+    StrArray(Vec<String>),
+    NumberArray(Vec<isize>),
+}
+
+impl ArgValue {
+    pub(crate) fn kind(&self) -> ArgKind {
+        match self {
+            ArgValue::Bool(_) | ArgValue::OptBool(_) => ArgKind::Bool,
+            ArgValue::Str(_) => ArgKind::Str,
+            ArgValue::OptStr { .. } => ArgKind::OptStr,
+            ArgValue::Number(_) => ArgKind::Number,
+            ArgValue::StrArray(_) => ArgKind::StrArray,
+            ArgValue::NumberArray(_) => ArgKind::NumberArray,
+        }
+    }
+
+    /// The words that set this value on the command line, or `None` when
+    /// there's nothing to write.
+    pub(crate) fn given_words(&self) -> Option<Vec<String>> {
+        match self {
+            ArgValue::Bool(value) => Some(vec![]).filter(|_| *value),
+            ArgValue::OptBool(value) => {
+                value.map(|on| if on { vec![] } else { vec![on.to_string()] })
+            }
+            ArgValue::Str(value) => value.clone().map(|value| vec![value]),
+            ArgValue::OptStr { presence, .. } => match presence {
+                Presence::Absent => None,
+                Presence::Bare => Some(vec![]),
+                Presence::Value(value) => Some(vec![value.clone()]),
+            },
+            ArgValue::Number(value) => value.map(|value| vec![value.to_string()]),
+            ArgValue::StrArray(list) => Some(list.clone()).filter(|list| !list.is_empty()),
+            ArgValue::NumberArray(list) => {
+                let list: Vec<String> = list.iter().map(ToString::to_string).collect();
+                Some(list).filter(|list| !list.is_empty())
+            }
+        }
+    }
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let seconds = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        "d" => 86400.0,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    Duration::try_from_secs_f64(number * seconds).ok()
+}
+
+impl Args for ArgValue {
+    fn set(&mut self, name: &str, values: Vec<String>) -> Result<(), ParseErr> {
+        match self {
+            ArgValue::Bool(value) => {
+                *value = values.is_empty() || values.join("").to_lowercase() == "true";
+            }
+            ArgValue::OptBool(value) => {
+                *value = Some(values.is_empty() || values.join("").to_lowercase() == "true");
+            }
+            ArgValue::Str(value) => *value = Some(values.join("")),
+            ArgValue::OptStr { presence, .. } => {
+                *presence = if values.is_empty() {
+                    Presence::Bare
+                } else {
+                    Presence::Value(values.join(""))
+                };
+            }
+            ArgValue::Number(value) => match values.join("").parse() {
+                Ok(number) => *value = Some(number),
+                Err(_) => {
+                    let (arg, value) = (name.to_string(), values.join(""));
+                    return Err(ParseErr::NumberFormatErr { arg, value, span: None });
+                }
+            },
+            ArgValue::StrArray(list) => list.extend(values),
+            ArgValue::NumberArray(list) => {
+                let numbers = values.into_iter().filter_map(|value| value.parse::<isize>().ok());
+                list.extend(numbers)
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<String> {
+        match self {
+            ArgValue::Bool(value) => Some(value.to_string()),
+            ArgValue::OptBool(value) => value.map(|value| value.to_string()),
+            ArgValue::Str(value) => value.clone(),
+            ArgValue::OptStr { implicit, presence } => match presence {
+                Presence::Absent => None,
+                Presence::Bare => Some(implicit.clone()),
+                Presence::Value(value) => Some(value.clone()),
+            },
+            ArgValue::Number(value) => value.map(|v| v.to_string()),
+            ArgValue::StrArray(list) => Some(list.join(",")),
+            ArgValue::NumberArray(list) => {
+                Some(list.iter().map(ToString::to_string).collect::<Vec<String>>().join(","))
+            }
+        }
+    }
+
+    fn as_list(&self) -> Vec<String> {
+        match self {
+            ArgValue::StrArray(list) => list.clone(),
+            ArgValue::NumberArray(list) => list.iter().map(ToString::to_string).collect(),
+            _ => self.get().into_iter().collect(),
+        }
+    }
+
+    fn presence(&self) -> Presence {
+        match self {
+            ArgValue::OptStr { presence, .. } => presence.clone(),
+            _ => self.get().map_or(Presence::Absent, Presence::Value),
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+impl Debug for dyn Args {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    mod token_iterator {
+        use super::*;
+        #[test]
+        fn test_token_iter() {
+            let tokens = TokensIterator::from("-d /var/logs -p 8080 -l");
+            let mut iter = tokens.into_iter();
+            assert_eq!(iter.next().unwrap(), Token {
+                name: "d".into(),
+                values: vec!["/var/logs".into()],
+                span: 0..12,
+            });
+            assert_eq!(iter.next().unwrap(), Token {
+                name: "p".into(),
+                values: vec!["8080".into()],
+                span: 13..20,
+            });
+            assert_eq!(iter.next().unwrap(), Token {
+                name: "l".into(),
+                values: vec![],
+                span: 21..23,
+            });
+            assert_eq!(iter.next(), None);
+        }   
+
+        #[test]
+        fn test_token_iter_keeps_dashes_inside_values() {
+            let mut iter = TokensIterator::from("--log-dir /var/log-files");
+            assert_eq!(iter.next().unwrap(), Token {
+                name: "log-dir".into(),
+                values: vec!["/var/log-files".into()],
+                span: 0..24,
+            });
+            assert_eq!(iter.next(), None);
+        }
+
+        #[test]
+        fn test_token_iter_reads_long_options() {
+            let tokens: Vec<_> = TokensIterator::from("--verbose --port 8080").collect();
+            assert_eq!(tokens, vec![
+                Token { name: "verbose".into(), values: vec![], span: 0..9 },
+                Token { name: "port".into(), values: vec!["8080".into()], span: 10..21 },
+            ]);
+        }
+
+        #[test]
+        fn test_token_iter_splits_on_equals() {
+            let input = r#"-p=8080 --log-dir="/var/my logs" --empty= -s=a b"#;
+            let tokens: Vec<_> = TokensIterator::from(input).collect();
+            assert_eq!(tokens, vec![
+                Token { name: "p".into(), values: vec!["8080".into()], span: 0..7 },
+                Token {
+                    name: "log-dir".into(),
+                    values: vec!["/var/my logs".into()],
+                    span: 8..32,
+                },
+                Token { name: "empty".into(), values: vec!["".into()], span: 33..41 },
+                Token {
+                    name: "s".into(),
+                    values: vec!["a".into(), "b".into()],
+                    span: 42..48,
+                },
+            ]);
+        }
+
+        #[test]
+        fn test_token_iter_respects_quotes() {
+            let input = r#"-d "/var/my logs" -n 'it''s' -x "-1" -y a\ b -z "say \"hi\"""#;
+            let values: Vec<_> = TokensIterator::from(input).map(|t| t.values).collect();
+            assert_eq!(
+                values,
+                vec![
+                    vec!["/var/my logs".to_string()],
+                    vec!["its".to_string()],
+                    vec!["-1".to_string()],
+                    vec!["a b".to_string()],
+                    vec!["say \"hi\"".to_string()],
+                ]
+            );
+        }
+
+        #[test]
+        fn test_token_iter_spans() {
+            let input = "-d 'ä b' -x  -- rest";
+            let mut iter = TokensIterator::new(input);
+            let token = iter.next().unwrap();
+            assert_eq!(&input[token.span], "-d 'ä b'");
+            assert_eq!(iter.next().unwrap().span, 10..12);
+            assert_eq!(iter.next(), None);
+            assert_eq!(iter.trailing(), &["rest"]);
+            let schema = Schema::new("l,p#,<input>").unwrap();
+            let input = "in.txt -lp -1";
+            let tokens: Vec<_> = schema
+                .tokens(input)
+                .map(|token| (token.name, token.span))
+                .collect();
+            assert_eq!(tokens, vec![("l".into(), 7..10), ("p".into(), 7..13)]);
+            let mut iter = schema.tokens(input);
+            iter.by_ref().count();
+            assert_eq!(iter.positionals(), &["in.txt"]);
+        }
+
+        #[test]
+        fn test_token_iter_borrows_from_the_input() {
+            let borrowed = |value: &Cow<str>| matches!(value, Cow::Borrowed(_));
+            let tokens: Vec<_> = TokensIterator::new(r#"--port=8080 -d "a b" -x y\ z"#).collect();
+            assert!(tokens.iter().all(|token| borrowed(&token.name)));
+            let values = tokens.iter().flat_map(|token| token.values.iter().map(borrowed));
+            assert_eq!(values.collect::<Vec<_>>(), vec![true, false, false]);
+        }
+
+        #[test]
+        fn test_token_iter_lexes_words_as_it_goes() {
+            let mut words = split_words("-l 'a b' -d");
+            assert_eq!(words.next().unwrap().raw, "-l");
+            assert_eq!(words.chars.peek(), Some(&(2, ' ')));
+            assert_eq!(words.map(|word| word.raw).collect::<Vec<_>>(), vec!["'a b'", "-d"]);
+            let open = |input| {
+                let mut words = lex(input, true);
+                words.by_ref().for_each(drop);
+                words.open
+            };
+            assert_eq!((open("-d 'a b"), open("-d 'a b'")), (Some('\''), None));
+        }
+
+        #[test]
+        fn test_token_iter_unterminated_quote_runs_to_end() {
+            let mut iter = TokensIterator::from("-d 'a b");
+            assert_eq!(iter.next().unwrap().values, vec!["a b".to_string()]);
+            assert_eq!(iter.take_rewritten(), vec![("'a b".to_string(), "a b".to_string())]);
+        }
+    }
+    #[allow(clippy::bool_assert_comparison)]
+    mod boolean_args {
+        use super::*;
+        #[test]
+        fn parse_bool_arg_true() {
+            let args = parse("l", "-l").unwrap();
+            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
+        }
+
+        #[test]
+        fn parse_explicit_true() {
+            let args = parse("l", "-l true").unwrap();
+            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
+        }
+
+        #[test]
+        fn parse_explicit_true_case_insensitive() {
+            let args = parse("l", "-l True").unwrap();
+            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
+
+            let args = parse("l", "-l TRUE").unwrap();
+            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), true);
+        }
+
+        #[test]
+        fn parse_explicit_false() {
+            let args = parse("l", "-l false").unwrap();
+            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), false);
+        }
+
+        #[test]
+        fn parse_bool_arg_false() {
+            let args = parse("l", "").unwrap();
+            assert_eq!(args.get("l").unwrap().as_bool().unwrap(), false);
+        }
+
+        #[test]
+        fn parse_inverted_flag() {
+            let schema = Schema::new("c").unwrap().inverted("disable-cache", "c");
+            let args = schema.parse("").unwrap();
+            assert!(args.get("c").unwrap().as_bool().unwrap());
+            let args = schema.parse("--disable-cache").unwrap();
+            assert!(!args.get("c").unwrap().as_bool().unwrap());
+            let args = schema.parse("--disable-cache false").unwrap();
+            assert!(args.get("c").unwrap().as_bool().unwrap());
+        }
+
+        #[test]
+        fn inverted_flag_requires_bool_arg() {
+            let schema = Schema::new("c*").unwrap().inverted("disable-cache", "c");
+            assert_eq!(schema.parse("").unwrap_err(), ParseErr::InvalidSchema);
+        }
+    }
+    mod no_args {
+        use super::*;
+        #[test]
+        #[should_panic]
+        fn no_args() {
+            let args = parse("", "").unwrap();
+            assert!(args.get("d").is_none());
+        }
+    }
+    mod str_args {
+        use super::*;
+        #[test]
+        fn parses_single_arg() {
+            let args = parse("d*", "-d /var/logs").unwrap();
+            assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/logs");
+        }
+
+        #[test]
+        fn parse_single_arg_2() {
+            let args = parse("n*", "-n foo").unwrap();
+            assert_eq!(args.get("n").unwrap().get().unwrap(), "foo");
+        }
+
+        #[test]
+        fn parses_multiple_args() {
+            let args = parse("d*,n*", "-d /var/logs -n foo").unwrap();
+            assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/logs");
+            assert_eq!(args.get("n").unwrap().get().unwrap(), "foo");
+        }
+
+        #[test]
+        fn keeps_dashes_inside_values() {
+            let args = parse("d*,l", "-d /var/log-files -l").unwrap();
+            assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/log-files");
+            let args = parse("d*", "-d a-b -c").unwrap_err();
+            assert_eq!(args, ParseErr::UnknownArg {
+                arg: "c".into(),
+                typed: "-c".into(),
+                suggestion: Some("-d".into()),
+            });
+        }
+
+        #[test]
+        fn bare_dash_is_the_stdin_sentinel() {
+            let args = parse("f*,l,i[*]", "-f - -l -i a - b").unwrap();
+            assert_eq!(args.get("f").unwrap().get().unwrap(), STDIN);
+            assert!(args.is_stdin("f"));
+            assert!(args.is_stdin("i"));
+            assert!(args.get("l").unwrap().as_bool().unwrap());
+            let args = parse("f*,<input>", "-f in.txt -").unwrap();
+            assert!(!args.is_stdin("f"));
+            assert!(args.is_stdin("input"));
+        }
+
+        #[test]
+        fn parser_is_compiled_once() {
+            let parser = Parser::new("l,p#").unwrap();
+            for (input, port) in [("-p 1", 1), ("-l -p 2", 2)] {
+                assert_eq!(parser.parse(input).unwrap().get("p").unwrap().as_number(), Some(port));
+            }
+            let err = Parser::new("p?").unwrap_err();
+            assert_eq!(err, ParseErr::UnsupportedArgType("?".to_string()));
+        }
+
+        #[test]
+        fn parse_from_keeps_words_whole() {
+            let words = ["-d", "my \"logs\"\n", "-s", "a b", "", "c\\d", "--", "-x y"];
+            let args = parse_from("d*,s[*]", words).unwrap();
+            assert_eq!(args.get("d").unwrap().get().unwrap(), "my \"logs\"\n");
+            assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a b", "", "c\\d"]);
+            assert_eq!(args.trailing(), &["-x y"]);
+            let schema = Schema::new("d*").unwrap().shell_words();
+            let args = schema.parse_from(vec!["-d".to_string(), "a\nb'".to_string()]).unwrap();
+            assert_eq!(args.get("d").unwrap().get().unwrap(), "a\nb'");
+            let err = parse_from("p#", ["-p", "8 0"]).unwrap_err();
+            let (value, span) = ("8 0".to_string(), Some(0..7));
+            assert_eq!(err, ParseErr::NumberFormatErr { arg: "p".to_string(), value, span });
+        }
+    }
+    mod number_args {
+        use super::*;
+        #[test]
+        fn parse_number_arg() {
+            let args = parse("p#", "-p 8080").unwrap();
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        }
+
+        #[test]
+        fn parse_negative_number_arg() {
+            let args = parse("p#,l", "-p -1 -l").unwrap();
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), -1);
+            assert!(args.get("l").unwrap().as_bool().unwrap());
+            let args = parse("n[#]", "-n -1 2 -3").unwrap();
+            assert_eq!(args.get("n").unwrap().as_num_array(), vec![-1, 2, -3]);
+        }
+
+        #[test]
+        fn negative_number_is_a_flag_where_no_number_is_taken() {
+            let args = parse("l,d*", "-d -1");
+            assert_eq!(args.unwrap_err(), ParseErr::UnknownArg {
+                arg: "1".into(),
+                typed: "-1".into(),
+                suggestion: Some("-d".into()),
+            });
+        }
+    }
+
+    mod error_cases {
+        use super::*;
+
+        #[test]
+        fn should_return_err_if_no_schema() {
+            let args = parse("", "");
+            assert_eq!(args.unwrap_err(), ParseErr::InvalidSchema);
+        }
+
+        #[test]
+        fn should_return_invalid_arg_type_err() {
+            let args = parse("p!", "-p 8080");
+            assert_eq!(
+                args.unwrap_err(),
+                ParseErr::UnsupportedArgType("!".to_string())
+            );
+        }
+
+        #[test]
+        fn should_return_unknown_arg_err() {
+            let args = parse("d*", "-p 8080");
+            assert_eq!(args.unwrap_err(), ParseErr::UnknownArg {
+                arg: "p".into(),
+                typed: "-p".into(),
+                suggestion: Some("-d".into()),
+            });
+        }
+
+        #[test]
+        fn should_return_number_format_err() {
+            let args = parse("p#", "-p foo");
+            assert_eq!(
+                args.unwrap_err(),
+                ParseErr::NumberFormatErr {
+                    arg: "p".to_string(),
+                    value: "foo".to_string(),
+                    span: Some(0..6),
+                }
+            );
+        }
+
+        #[test]
+        fn context_of_errors() {
+            let err = parse("p#", "-p foo").unwrap_err();
+            assert_eq!((err.arg(), err.value()), (Some("p"), Some("foo")));
+            assert_eq!((err.expected(), err.span()), (Some(ArgKind::Number), Some(0..6)));
+            let schema = Schema::new("d*,p#").unwrap().requires("d", "p");
+            let err = schema.parse("-d /tmp").unwrap_err();
+            assert_eq!((err.arg(), err.related_arg()), (Some("d"), Some("p")));
+            let err = ParseErr::ValidationFailed {
+                arg: "p".to_string(),
+                message: "too low".to_string(),
+            };
+            assert_eq!((err.arg(), err.message(), err.value()), (Some("p"), Some("too low"), None));
+            let err = ArgValue::Number(None).set("port", vec!["x".to_string()]).unwrap_err();
+            assert_eq!((err.arg(), err.span()), (Some("port"), None));
+            let err = parse("p#,d*", "-d x -p 1 a").unwrap_err();
+            assert_eq!((err.arg(), err.value(), err.span()), (Some("p"), Some("1a"), Some(5..11)));
+        }
+    }
+
+    mod array_args {
+        use super::*;
+
+        #[test]
+        fn parse_str_arr_arg() {
+            let args = parse("s[*]", "-s this is an array");
+            assert_eq!(args.unwrap().get("s").unwrap().get().unwrap(), "this,is,an,array");
+            let args = parse("s[*]", "-s this is an array");
+            assert_eq!(args.unwrap().get("s").unwrap().as_str_array(), vec!["this","is","an","array"]);
+        }
+
+        #[test]
+        fn parse_number_arr_arg() {
+            let args = parse("p[#]", "-p 1 2 3 4 5");
+            assert_eq!(args.unwrap().get("p").unwrap().as_num_array(), vec![1,2,3,4,5]);
+        }
+    }
+
+    mod usage {
+        use super::*;
+
+        #[test]
+        fn usage_lists_args_in_schema_order() {
+            let schema = Schema::new("l,p#,d*,s[*]").unwrap();
+            assert_eq!(
+                schema.usage("myapp"),
+                "usage: myapp [-l] [-p <number>] [-d <string>] [-s <string>...]"
+            );
+        }
+
+        #[test]
+        fn usage_includes_inverted_switches() {
+            let schema = Schema::new("c").unwrap().inverted("disable-cache", "c");
+            assert_eq!(schema.usage("myapp"), "usage: myapp [-c] [--disable-cache]");
+        }
+
+        #[test]
+        fn hidden_args_are_omitted_but_still_parsed() {
+            let schema = Schema::new("l,x").unwrap().hidden("x");
+            assert_eq!(schema.usage("myapp"), "usage: myapp [-l]");
+            let args = schema.parse("-x").unwrap();
+            assert!(args.get("x").unwrap().as_bool().unwrap());
+        }
+    }
+
+    mod deprecations {
+        use super::*;
+
+        #[test]
+        fn deprecated_arg_still_parses_with_warning() {
+            let schema = Schema::new("o*,d*").unwrap().deprecated("o", Some("d"));
+            let args = schema.parse("-o /tmp").unwrap();
+            assert_eq!(args.get("o").unwrap().get().unwrap(), "/tmp");
+            assert_eq!(
+                args.warnings(),
+                &[ParseWarning::Deprecated("o".to_string(), Some("d".to_string()))]
+            );
+        }
+
+        #[test]
+        fn repeated_deprecated_arg_warns_once() {
+            let schema = Schema::new("x")
+                .unwrap()
+                .deprecated("x", None)
+                .max_occurrences("x", None);
+            let args = schema.parse("-x -x").unwrap();
+            assert_eq!(args.warnings(), &[ParseWarning::Deprecated("x".to_string(), None)]);
+        }
+
+        #[test]
+        fn no_warnings_without_deprecated_args() {
+            let schema = Schema::new("x,y").unwrap().deprecated("x", None);
+            assert!(schema.parse("-y").unwrap().warnings().is_empty());
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    mod schema_str {
+        use super::*;
+
+        #[test]
+        fn expands_to_the_checked_literal() {
+            const SCHEMA: &str = schema_str!("l,p#,d*");
+            assert_eq!(SCHEMA, "l,p#,d*");
+            let args = parse(SCHEMA, "-p 8080").unwrap();
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        }
+    }
+
+    #[cfg(feature = "macros")]
+    mod derive {
+        use super::*;
+
+        #[derive(FromArgs, Debug, PartialEq)]
+        struct Config {
+            /// Port to listen on
+            #[arg(name = "p", default = "8080")]
+            port: u16,
+            verbose: bool,
+            dir: String,
+            log_file: Option<std::path::PathBuf>,
+            #[arg(name = "n")]
+            retries: Vec<u8>,
+        }
+
+        #[test]
+        fn derives_the_schema_and_conversion() {
+            let schema = Config::schema();
+            assert_eq!(
+                schema.usage("app"),
+                "usage: app [-p <number>] [--verbose] --dir <string> [--log-file <string>] \
+                 [-n <number>...]"
+            );
+            assert_eq!(schema.args().next().unwrap().description(), Some("Port to listen on"));
+            let config: Config = parse_into("--dir /tmp -n 1 2 --log-file out.log").unwrap();
+            let expected = Config {
+                port: 8080,
+                verbose: false,
+                dir: "/tmp".to_string(),
+                log_file: Some("out.log".into()),
+                retries: vec![1, 2],
+            };
+            assert_eq!(config, expected);
+            let missing = parse_into::<Config>("").unwrap_err();
+            assert_eq!(missing, ParseErr::MissingArg("dir".to_string()));
+            assert_eq!(
+                parse_into::<Config>("--dir a -n 300").unwrap_err(),
+                ParseErr::InvalidValue { arg: "n".to_string(), value: "300".to_string() }
+            );
+        }
+
+        #[derive(ArgEnum, Debug, PartialEq)]
+        enum Format {
+            Json,
+            JsonLines,
+            #[arg(name = "yml")]
+            Yaml,
+        }
+
+        #[test]
+        fn derives_choices_from_enums() {
+            assert_eq!(Format::VARIANTS, &["json", "json-lines", "yml"]);
+            assert_eq!("JSON-Lines".parse::<Format>(), Ok(Format::JsonLines));
+            let err = "toml".parse::<Format>().unwrap_err();
+            assert_eq!(err, "expected one of: json, json-lines, yml");
+            let schema = Schema::builder().string("f").choices_of::<Format>().build().unwrap();
+            let args = schema.parse("-f YML").unwrap();
+            assert_eq!(args.get_str("f"), Some("yml".to_string()));
+            assert_eq!(args.get_as::<Format>("f"), Ok(Format::Yaml));
+            assert_eq!(
+                schema.parse("-f toml").unwrap_err(),
+                ParseErr::ValidationFailed {
+                    arg: "f".to_string(),
+                    message: "expected one of: json, json-lines, yml".to_string(),
+                }
+            );
+        }
+    }
+
+    mod absorb {
+        use super::*;
+
+        #[test]
+        fn accumulates_across_calls() {
+            let schema = Schema::new("s[*],p#,l").unwrap().conflicts_with("l", "p");
+            let mut args = schema.parse("-s a").unwrap();
+            args.absorb(&schema, "-s b -p 1").unwrap();
+            assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a", "b"]);
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 1);
+        }
+
+        #[test]
+        fn failed_absorb_leaves_result_untouched() {
+            let schema = Schema::new("s[*],p#,l").unwrap().conflicts_with("l", "p");
+            let mut args = schema.parse("-p 1").unwrap();
+            assert_eq!(
+                args.absorb(&schema, "-p 2").unwrap_err(),
+                ParseErr::TooManyOccurrences { arg: "p".to_string(), max: 1 }
+            );
+            assert_eq!(
+                args.absorb(&schema, "-l").unwrap_err(),
+                ParseErr::ConflictingArgs { arg: "l".to_string(), other: "p".to_string() }
+            );
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 1);
+            assert!(!args.get("l").unwrap().as_bool().unwrap());
+        }
+
+        #[test]
+        fn keeps_values_from_other_sources() {
+            std::env::set_var("ARGS_TEST_ABSORB_D", "/env");
+            let schema = Schema::builder().string("d").number("p").default("80").build().unwrap();
+            let resolver = Resolver::new(schema.clone()).env("d", "ARGS_TEST_ABSORB_D");
+            let mut args = resolver.resolve("").unwrap();
+            args.absorb(&schema, "-p 8080").unwrap();
+            assert_eq!(args.get("d").unwrap().get().unwrap(), "/env");
+            assert_eq!(args.source("d"), Some(Source::Env));
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        }
+    }
+
+    mod classification {
+        use super::*;
+
+        #[test]
+        fn debug_output_masks_secrets() {
+            let schema = Schema::new("t*,u*,p#")
+                .unwrap()
+                .classify("t", Classification::Secret)
+                .classify("u", Classification::Internal);
+            let debug = format!("{:?}", schema.parse("-t hunter2 -u alice -p 1").unwrap());
+            assert!(!debug.contains("hunter2"));
+            assert!(debug.contains("\"***\""));
+            assert!(debug.contains("alice"));
+            let debug = format!("{:?}", schema.parse("-t 'hunter2' -u 'alice'").unwrap());
+            assert!(!debug.contains("hunter2"));
+            assert!(debug.contains("Unquoted(\"'alice'\", \"alice\")"));
+            let args = schema.parse("-t hunter2").unwrap();
+            assert_eq!(args.get("t").unwrap().get().unwrap(), "hunter2");
+        }
+    }
+
+    mod typed_getters {
+        use super::*;
+
+        #[test]
+        fn values_by_type() {
+            let args = parse("l,p#,d*,s[*],n[#]", "-l -p 8080 -s a b -n 1 2").unwrap();
+            assert_eq!(args.get_bool("l"), Some(true));
+            assert_eq!(args.get_number("p"), Some(8080));
+            assert_eq!(args.get_str("d"), None);
+            assert_eq!(args.get_str_list("s"), vec!["a", "b"]);
+            assert_eq!(args.get_number_list("n"), vec![1, 2]);
+            assert_eq!(args.get_number("x"), None);
+            assert!(args.get_str_list("x").is_empty());
+        }
+
+        #[test]
+        fn converted_values() {
+            let input = "-r 0.5 -f /tmp/out.txt -t 1.5m -s a,b c";
+            let args = parse("r*,f*,t*,w*,s[*]", input).unwrap();
+            assert_eq!(args.get_float("r"), Some(0.5));
+            assert_eq!(args.get_path("f"), Some(PathBuf::from("/tmp/out.txt")));
+            assert_eq!(args.get_duration("t"), Some(Duration::from_secs(90)));
+            assert_eq!(args.get_duration("w"), None);
+            assert_eq!(args.get_list("s"), vec!["a,b", "c"]);
+            assert_eq!(args.get_list("f"), vec!["/tmp/out.txt"]);
+            assert!(args.get_list("w").is_empty());
+            let durations = [("250ms", 250), ("2", 2000), ("1h", 3_600_000), ("1d", 86_400_000)];
+            for (given, millis) in &durations {
+                assert_eq!(parse_duration(given), Some(Duration::from_millis(*millis)));
+            }
+            assert_eq!(parse_duration("5 weeks"), None);
+            assert_eq!(parse_duration("ms"), None);
+        }
+
+        #[test]
+        fn strict_values() {
+            let args = parse("l,p#,d*,s[*],n[#]", "-p 8080 -s a b").unwrap();
+            assert_eq!(args.try_get_bool("l"), Ok(false));
+            assert_eq!(args.try_get_number("p"), Ok(8080));
+            assert_eq!(args.try_get_str_list("s"), Ok(vec!["a".to_string(), "b".to_string()]));
+            assert_eq!(args.try_get_str("d"), Err(ParseErr::MissingValue("d".to_string())));
+            assert_eq!(args.try_get_number_list("n"), Err(ParseErr::MissingValue("n".to_string())));
+            assert_eq!(args.try_get_number("x"), Err(ParseErr::unknown_arg("x")));
+            assert_eq!(
+                args.try_get_str("p"),
+                Err(ParseErr::WrongType { arg: "p".to_string(), expected: ArgKind::Number })
+            );
+            assert_eq!(
+                args.try_get_bool("s"),
+                Err(ParseErr::WrongType { arg: "s".to_string(), expected: ArgKind::StrArray })
+            );
+        }
+
+        #[test]
+        fn extract_into_a_struct() {
+            #[derive(Debug, PartialEq)]
+            struct Server {
+                port: u16,
+                hosts: Vec<String>,
+            }
+            impl FromArgs for Server {
+                fn from_args(args: &ParsedArgs) -> Result<Self, ParseErr> {
+                    Ok(Server { port: args.get_as("p")?, hosts: args.try_get_str_list("h")? })
+                }
+            }
+            let schema = Schema::new("p#,h[*],d*").unwrap();
+            let server = schema.parse_into::<Server>("-p 80 -h a b").unwrap();
+            assert_eq!(server, Server { port: 80, hosts: vec!["a".to_string(), "b".to_string()] });
+            assert_eq!(
+                schema.parse_into::<Server>("-p 80").unwrap_err(),
+                ParseErr::MissingValue("h".to_string())
+            );
+            let args = schema.parse("-p 70000 -d logs").unwrap();
+            assert_eq!(
+                args.extract::<Server>().unwrap_err(),
+                ParseErr::InvalidValue { arg: "p".to_string(), value: "70000".to_string() }
+            );
+            assert_eq!(args.get_opt_as::<String>("d"), Ok(Some("logs".to_string())));
+            assert_eq!(parse("d*", "").unwrap().get_opt_as::<String>("d"), Ok(None));
+        }
+
+        #[test]
+        fn values_with_fallback() {
+            let args = parse("p#,d*,l", "-p 8080").unwrap();
+            assert_eq!(args.get_or("p", 80), 8080);
+            assert_eq!(args.get_or("d", "/tmp".to_string()), "/tmp");
+            assert!(!args.get_or("l", true));
+            assert_eq!(args.get_or("x", 1u16), 1);
+            assert_eq!(parse("d*", "-d logs").unwrap().get_or("d", 0), 0);
+        }
+
+        #[test]
+        fn values_of_any_type() {
+            let args = parse("p#,d*,a*,l", "-p 8080 -d logs -a 127.0.0.1").unwrap();
+            assert_eq!(args.get_as::<u16>("p"), Ok(8080));
+            assert_eq!(args.get_as::<std::net::Ipv4Addr>("a"), Ok([127, 0, 0, 1].into()));
+            assert_eq!(args.get_as::<bool>("l"), Ok(false));
+            assert_eq!(
+                args.get_as::<u8>("p"),
+                Err(ParseErr::InvalidValue { arg: "p".to_string(), value: "8080".to_string() })
+            );
+            assert_eq!(
+                parse("d*", "").unwrap().get_as::<String>("d"),
+                Err(ParseErr::MissingValue("d".to_string()))
+            );
+            assert_eq!(args.get_as::<String>("x"), Err(ParseErr::unknown_arg("x")));
+        }
+
+        #[test]
+        fn iterates_in_declaration_order() {
+            let args = parse("p#,d*,l", "-l -p 8080").unwrap();
+            let values: Vec<_> = args.iter().map(|(name, arg)| (name, arg.get())).collect();
+            assert_eq!(
+                values,
+                vec![("p", Some("8080".to_string())), ("d", None), ("l", Some("true".to_string()))]
+            );
+            let mut names = vec![];
+            for (name, _) in &args {
+                names.push(name);
+            }
+            assert_eq!(names, vec!["p", "d", "l"]);
+            assert_eq!(
+                format!("{:?}", args),
+                "ParsedArgs { args: {\"p\": Some(\"8080\"), \"d\": None, \"l\": Some(\"true\")}, \
+                 warnings: [], normalizations: [], \
+                 sources: {\"p\": CommandLine, \"l\": CommandLine}, trailing: [] }"
+            );
+        }
+
+        #[test]
+        fn results_outlive_the_schema() {
+            fn parse_port(input: &str) -> ParsedArgs {
+                let definition = format!("{}#", "p");
+                let schema = Schema::new(&definition).unwrap();
+                schema.parse(input).unwrap()
+            }
+            let args = parse_port("-p 8080");
+            assert_eq!(args.get_number("p"), Some(8080));
+        }
+
+        #[test]
+        fn values_to_match_on() {
+            let args = parse("l,p#,d*,s[*]", "-p 8080 -s a b").unwrap();
+            match args.value("p") {
+                Some(ArgValue::Number(Some(port))) => assert_eq!(*port, 8080),
+                other => panic!("unexpected {:?}", other),
+            }
+            assert_eq!(args.value("l"), Some(&ArgValue::Bool(false)));
+            assert_eq!(args.value("d"), Some(&ArgValue::Str(None)));
+            let list = ArgValue::StrArray(vec!["a".to_string(), "b".to_string()]);
+            assert_eq!(args.clone().value("s"), Some(&list));
+            assert_eq!(args.value("x"), None);
+        }
+
+        #[test]
+        fn display_as_a_table() {
+            let schema = Schema::builder()
+                .bool("l")
+                .number("port")
+                .string("d")
+                .string_list("s")
+                .string("t")
+                .build()
+                .unwrap()
+                .classify("t", Classification::Secret);
+            let args = schema.parse("--port 8080 -s a b -t hunter2").unwrap();
+            let table = "l    = false\nport = 8080\nd    = -\ns    = [a, b]\nt    = ***";
+            assert_eq!(args.to_string(), table);
+            let schema = Schema::builder().bool("größe").bool("n").build().unwrap();
+            assert_eq!(schema.parse("-n").unwrap().to_string(), "größe = false\nn     = true");
+        }
+
+        #[test]
+        fn compare_and_clone_results() {
+            let schema = Schema::new("l,p#,s[*]").unwrap();
+            let snapshot = schema.parse("-l -p 8080 -s a b").unwrap();
+            assert_eq!(snapshot.clone(), snapshot);
+            assert_eq!(schema.parse("-l -p 8080 -s a b").unwrap(), snapshot);
+            assert_ne!(schema.parse("-l -p 8081 -s a b").unwrap(), snapshot);
+        }
+
+        #[test]
+        fn downcast_to_the_concrete_value() {
+            let args = parse("p#", "-p 8080").unwrap();
+            let value = args.get("p").unwrap().as_any().downcast_ref::<ArgValue>();
+            assert_eq!(value, Some(&ArgValue::Number(Some(8080))));
+            let mut flag = ArgValue::Bool(false);
+            let arg: &mut dyn Args = &mut flag;
+            if let Some(ArgValue::Bool(value)) = arg.as_any_mut().downcast_mut::<ArgValue>() {
+                *value = true;
+            }
+            assert_eq!(flag, ArgValue::Bool(true));
+        }
+
+        #[test]
+        fn index_by_name() {
+            let args = parse("p#,l", "-p 8080").unwrap();
+            assert_eq!(args["p"].as_number(), Some(8080));
+            assert_eq!(args["l"].as_bool(), Some(false));
+        }
+
+        #[test]
+        #[should_panic(expected = "no argument named 'x' in the schema")]
+        fn index_panics_on_unknown_names() {
+            let args = parse("p#", "").unwrap();
+            args["x"].get();
+        }
+
+        #[test]
+        fn values_as_given() {
+            let schema = Schema::new("d*,l,s[*],n[#]").unwrap().delimiter("n", ':');
+            let args = schema.parse("-d a b -s x -s 'y z' -n 1:2 -l").unwrap();
+            assert_eq!(args.get_str("d").unwrap(), "a b");
+            assert_eq!(args.values_of("d").unwrap(), &["a", "b"]);
+            assert_eq!(args.values_of("s").unwrap(), &["x", "y z"]);
+            assert_eq!(args.values_of("n").unwrap(), &["1", "2"]);
+            assert!(args.values_of("l").unwrap().is_empty());
+            assert_eq!(schema.parse("-l").unwrap().values_of("d"), None);
+            let schema = schema.accumulate("s", Accumulation::Replace);
+            assert_eq!(schema.parse("-s a -s b").unwrap().values_of("s").unwrap(), &["b"]);
+        }
+
+        #[test]
+        fn presence_and_occurrences() {
+            let schema = Schema::builder()
+                .bool("v")
+                .bool("l")
+                .number("p")
+                .default("80")
+                .string_list("s")
+                .positional("input")
+                .build()
+                .unwrap()
+                .max_occurrences("v", None);
+            let args = schema.parse("in.txt -vv -l false -s a -v -s b c").unwrap();
+            assert_eq!(args.occurrences_of("v"), 3);
+            assert!(args.is_present("l"));
+            assert_eq!(args.get_bool("l"), Some(false));
+            assert_eq!(args.occurrences_of("s"), 2);
+            assert_eq!(args.occurrences_of("input"), 1);
+            assert!(!args.is_present("p"));
+            assert_eq!(args.get_number("p"), Some(80));
+            let args = schema.parse("--no-v in.txt").unwrap();
+            assert_eq!(args.occurrences_of("v"), 1);
+            assert_eq!(args.occurrences_of("x"), 0);
+        }
+    }
+
+    mod env_map {
+        use super::*;
+
+        #[test]
+        fn to_env_map_uses_prefix_and_uppercase_names() {
+            let args = parse("p#,d*,l", "-p 8080 -l").unwrap();
+            let env = args.to_env_map("APP");
+            assert_eq!(env.get("APP_P").unwrap(), "8080");
+            assert_eq!(env.get("APP_L").unwrap(), "true");
+            assert!(!env.contains_key("APP_D"));
+        }
+
+        #[test]
+        fn to_string_map_and_json() {
+            let args = parse("l,p#,d*,s[*],n[#]", "-l -p 8080 -s a \"b c\" -n 1 2").unwrap();
+            let map = args.to_string_map();
+            assert_eq!(map.get("p"), Some(&"8080".to_string()));
+            assert_eq!(map.get("s"), Some(&"a,b c".to_string()));
+            assert_eq!(map.get("d"), None);
+            assert_eq!(
+                args.to_json(),
+                r#"{"l":true,"p":8080,"d":null,"s":["a","b c"],"n":[1,2]}"#
+            );
+        }
+
+        #[test]
+        fn to_command_line_reads_back_the_same() {
+            let schema = Schema::builder()
+                .positional("file")
+                .bool("l")
+                .number("n")
+                .string("d")
+                .string_list("s")
+                .optional_string("color")
+                .build()
+                .unwrap()
+                .capture_rest();
+            let args = schema.parse("in.txt -s a 'b c' -n -1 --color -l -- -x").unwrap();
+            let line = args.to_command_line();
+            assert_eq!(line, "in.txt -l -n \"-1\" -s a \"b c\" --color -- \"-x\"");
+            let reparsed = schema.parse(&line).unwrap();
+            assert_eq!(reparsed.iter().collect::<Vec<_>>(), args.iter().collect::<Vec<_>>());
+            assert_eq!(reparsed.trailing(), args.trailing());
+            assert_eq!(reparsed, args);
+        }
+
+        #[test]
+        fn equal_values_compare_equal() {
+            let schema = Schema::new("l,p#,d*").unwrap();
+            let args = schema.parse("-l -p 1").unwrap();
+            assert_eq!(schema.parse("-p 1 -l").unwrap(), args);
+            assert_eq!(schema.parse("-l  -p   1").unwrap(), args);
+            assert_eq!(schema.parse("-l -p=1").unwrap(), args);
+            assert_ne!(schema.parse("-l -p 2").unwrap(), args);
+            assert_ne!(schema.parse("-l -p 1 -d x").unwrap(), args);
+        }
+
+        #[test]
+        fn to_env_map_without_prefix() {
+            let args = parse("s[*]", "-s a b").unwrap();
+            assert_eq!(args.to_env_map("").get("S").unwrap(), "a,b");
+        }
+    }
+
+    mod constraints {
+        use super::*;
+
+        #[test]
+        fn required_arg_present() {
+            let schema = Schema::new("k*,c*").unwrap().requires("k", "c");
+            let args = schema.parse("-k key.pem -c cert.pem").unwrap();
+            assert_eq!(args.get("k").unwrap().get().unwrap(), "key.pem");
+        }
+
+        #[test]
+        fn required_arg_missing() {
+            let schema = Schema::new("k*,c*").unwrap().requires("k", "c");
+            assert_eq!(
+                schema.parse("-k key.pem").unwrap_err(),
+                ParseErr::MissingRequiredArg { arg: "k".to_string(), required: "c".to_string() }
+            );
+            assert!(schema.parse("-c cert.pem").is_ok());
+        }
+
+        #[test]
+        fn conflicting_args() {
+            let schema = Schema::new("q,v").unwrap().conflicts_with("q", "v");
+            assert!(schema.parse("-q").is_ok());
+            assert_eq!(
+                schema.parse("-q -v").unwrap_err(),
+                ParseErr::ConflictingArgs { arg: "q".to_string(), other: "v".to_string() }
+            );
+        }
+
+        #[test]
+        fn one_of_a_group() {
+            let schema = Schema::new("f*,u*,v").unwrap().one_of(&["f", "u"]);
+            assert!(schema.parse("-u https://example.com").is_ok());
+            let err = schema.parse("-v").unwrap_err();
+            assert_eq!(err, ParseErr::MissingOneOf(vec!["f".to_string(), "u".to_string()]));
+            assert_eq!(err.to_string(), "one of -f, -u is required");
+            assert_eq!(
+                schema.parse("-f a.txt -u https://example.com").unwrap_err(),
+                ParseErr::ConflictingArgs { arg: "f".to_string(), other: "u".to_string() }
+            );
+            let schema = Schema::new("f*,u*,v").unwrap().any_of(&["f", "u", "v"]);
+            assert!(schema.parse("-f a.txt -v").is_ok());
+            assert!(matches!(schema.parse("").unwrap_err(), ParseErr::MissingOneOf(_)));
+            let schema = Schema::new("f*").unwrap().one_of(&["f", "u"]);
+            assert_eq!(schema.parse("-f a.txt").unwrap_err(), ParseErr::InvalidSchema);
+        }
+
+        #[test]
+        fn required_if_a_value_is_set() {
+            let schema = Schema::builder()
+                .bool("tls")
+                .string("k")
+                .string("m")
+                .default("s3")
+                .build()
+                .unwrap()
+                .required_if("k", "tls", "true")
+                .required_if("k", "m", "s3");
+            assert!(schema.parse("--tls -k key.pem").is_ok());
+            assert!(schema.parse("-m local").is_ok());
+            let err = schema.parse("--tls").unwrap_err();
+            assert_eq!(
+                err,
+                ParseErr::RequiredIf {
+                    arg: "k".to_string(),
+                    other: "tls".to_string(),
+                    value: "true".to_string(),
+                }
+            );
+            assert_eq!((err.arg(), err.related_arg()), (Some("k"), Some("tls")));
+            assert_eq!(err.to_string(), "-k is required when --tls is set");
+            let err = schema.parse("").unwrap_err();
+            assert_eq!(err.to_string(), "-k is required when -m is 's3'");
+        }
+
+        #[test]
+        fn constraint_on_unknown_arg_is_invalid_schema() {
+            let schema = Schema::new("k*").unwrap().requires("k", "c");
+            assert_eq!(schema.parse("").unwrap_err(), ParseErr::InvalidSchema);
+        }
+    }
+}
+
+/// Everything that can go wrong declaring a schema or parsing with it.
+/// More variants may be added, so matches need a catch-all arm; the
+/// accessors give the context common to several of them.
+#[derive(PartialEq, Debug)]
+#[non_exhaustive]
 pub enum ParseErr {
     InvalidSchema,
     UnsupportedArgType(String),
-    UnknownArg(String),
-    NumberFormatErr(String)
+    /// The flag `typed`, e.g. `-prot`, names no argument `arg` of the
+    /// schema. `suggestion` is the closest flag it has, e.g. `--port`, if
+    /// one is close enough.
+    UnknownArg { arg: String, typed: String, suggestion: Option<String> },
+    /// A value given to a number argument isn't an integer. `span` is where
+    /// the flag and its values are in the input, if a flag gave the value.
+    NumberFormatErr { arg: String, value: String, span: Option<Range<usize>> },
+    /// `arg` was given without `required`, see [`Schema::requires`].
+    MissingRequiredArg { arg: String, required: String },
+    /// `arg` and `other` were given together, see
+    /// [`Schema::conflicts_with`].
+    ConflictingArgs { arg: String, other: String },
+    /// A schema declaration file is malformed at `line`.
+    InvalidSchemaFile { line: usize, message: String },
+    InvalidWireFormat,
+    /// A validator rejected the value of `arg`.
+    ValidationFailed { arg: String, message: String },
+    MissingValue(String),
+    InvalidTemplate(String),
+    MissingArg(String),
+    /// `arg` was given more than `max` times.
+    TooManyOccurrences { arg: String, max: usize },
+    UnexpectedValue(String),
+    /// `value` isn't one `arg` accepts.
+    InvalidValue { arg: String, value: String },
+    /// `arg` was read as another type than the `expected` one it has.
+    WrongType { arg: String, expected: ArgKind },
+    DuplicateArg(String),
+    DuplicateSchema(String),
+    UnknownSchema(String),
+    DuplicateMarker(String),
+    /// The response file at `path` couldn't be read.
+    ResponseFile { path: String, reason: String },
+    UnsetVariable(String),
+    UnterminatedQuote(char),
+    /// `-h` or `--help` was given to a schema with
+    /// [`Schema::help_flags`]: the help screen to print.
+    HelpRequested(String),
+    /// The config file at `path` couldn't be read.
+    ConfigFile { path: String, reason: String },
+    /// A config file read with [`Resolver::config_toml`] is malformed at
+    /// `line`.
+    InvalidConfigFile { line: usize, message: String },
+    /// `-V` or `--version` was given to a schema with
+    /// [`Schema::version_flags`]: the version line to print.
+    VersionRequested(String),
+    /// The `line` of input to [`Schema::parse_lines`] couldn't be read.
+    ReadFailed { line: usize, reason: String },
+    /// The input went over the `limit` on `what` set with
+    /// [`Schema::limits`].
+    LimitExceeded { what: String, limit: usize },
+    /// Nothing followed the top-level flags of [`Commands::parse`].
+    MissingCommand,
+    /// The word after the top-level flags isn't a declared command.
+    UnknownCommand(String),
+    /// None of a group declared with [`Schema::one_of`] or
+    /// [`Schema::any_of`] was given: the members of the group.
+    MissingOneOf(Vec<String>),
+    /// The long flag `given` abbreviates each of the `candidates`, see
+    /// [`Schema::abbreviations`].
+    AmbiguousArg { given: String, candidates: Vec<String> },
+    /// `arg`, declared with [`Schema::required_if`], is missing while
+    /// `other` has `value`.
+    RequiredIf { arg: String, other: String, value: String },
+    /// Nothing exists at the `path` given to `arg`, which has a
+    /// [`PathCheck`].
+    PathNotFound { arg: String, path: String },
+    /// The `path` given to `arg`, which has [`PathCheck::File`], isn't a
+    /// file.
+    NotAFile { arg: String, path: String },
+    /// The `path` given to `arg`, which has [`PathCheck::Dir`], isn't a
+    /// directory.
+    NotADirectory { arg: String, path: String },
+    /// The directory of the `path` given to `arg`, which has
+    /// [`PathCheck::ParentExists`], is missing.
+    ParentNotFound { arg: String, path: String },
+    /// The directory `dir` that [`PathCheck::CreateParent`] needed for
+    /// `arg` couldn't be created.
+    CreateDirFailed { arg: String, dir: String, reason: String },
+    /// The `pattern` given to `arg`, set up with [`Schema::glob`], matches
+    /// no path.
+    NoMatches { arg: String, pattern: String },
+    /// The argument of [`Schema::preset_arg`] names no declared preset.
+    UnknownPreset(String),
+}
+
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub enum ParseWarning {
+    Deprecated(String, Option<String>),
+    /// An argument was given again, and the last value replaced the
+    /// earlier ones.
+    Overridden(String),
+    /// An argument was given again under [`Accumulation::Keep`], and the
+    /// later value was dropped.
+    Ignored(String),
+}
+
+/// A rewrite applied to the input while parsing it.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Normalization {
+    /// A flag was matched ignoring case: (as given, as declared).
+    CaseFolded(String, String),
+    /// Quotes or escapes were resolved in a word: (as given, as read).
+    Unquoted(String, String),
+    /// A long flag was abbreviated: (as given, as declared).
+    Abbreviated(String, String),
+}
+
+impl std::fmt::Display for Normalization {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Normalization::CaseFolded(given, name)
+            | Normalization::Abbreviated(given, name) => {
+                write!(f, "{} was read as {}", schema::flag(given), schema::flag(name))
+            }
+            Normalization::Unquoted(given, read) => write!(f, "{} was read as {:?}", given, read),
+        }
+    }
+}
+
+/// A message for the user, naming arguments the way they are typed. See
+/// [`Schema::explain`] for one that also lists what the schema expects.
+impl std::fmt::Display for ParseErr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use schema::flag;
+        match self {
+            ParseErr::InvalidSchema => write!(f, "invalid schema definition"),
+            ParseErr::UnsupportedArgType(marker) => {
+                write!(f, "unsupported argument type '{}'", marker)
+            }
+            ParseErr::UnknownArg { typed, suggestion: None, .. } => {
+                write!(f, "unknown argument '{}'", typed)
+            }
+            ParseErr::UnknownArg { typed, suggestion: Some(suggestion), .. } => {
+                write!(f, "unknown argument '{}'; did you mean {}?", typed, suggestion)
+            }
+            ParseErr::NumberFormatErr { value, .. } => write!(f, "'{}' is not a number", value),
+            ParseErr::MissingRequiredArg { arg, required } => {
+                write!(f, "{} requires {}", flag(arg), flag(required))
+            }
+            ParseErr::ConflictingArgs { arg, other } => {
+                write!(f, "{} can't be used together with {}", flag(arg), flag(other))
+            }
+            ParseErr::InvalidSchemaFile { line, message } => {
+                write!(f, "invalid schema file, line {}: {}", line, message)
+            }
+            ParseErr::InvalidWireFormat => write!(f, "malformed wire data"),
+            ParseErr::ValidationFailed { arg: name, message } => {
+                write!(f, "invalid value for {}: {}", flag(name), message)
+            }
+            ParseErr::MissingValue(name) => write!(f, "{} has no value", flag(name)),
+            ParseErr::InvalidTemplate(message) => write!(f, "invalid template: {}", message),
+            ParseErr::MissingArg(name) => write!(f, "missing required argument {}", flag(name)),
+            ParseErr::TooManyOccurrences { arg: name, max: 1 } => {
+                write!(f, "{} can only be given once", flag(name))
+            }
+            ParseErr::TooManyOccurrences { arg: name, max } => {
+                write!(f, "{} can be given at most {} times", flag(name), max)
+            }
+            ParseErr::UnexpectedValue(name) => write!(f, "{} doesn't take a value", flag(name)),
+            ParseErr::InvalidValue { arg: name, value } => {
+                write!(f, "invalid value '{}' for {}", value, flag(name))
+            }
+            ParseErr::WrongType { arg: name, expected: kind } => {
+                write!(f, "{} holds a value of type {:?}", flag(name), kind)
+            }
+            ParseErr::DuplicateArg(name) => write!(f, "{} is declared twice", flag(name)),
+            ParseErr::DuplicateSchema(name) => {
+                write!(f, "a schema named '{}' is already registered", name)
+            }
+            ParseErr::UnknownSchema(name) => write!(f, "no schema named '{}' is registered", name),
+            ParseErr::DuplicateMarker(marker) => {
+                write!(f, "the type marker '{}' is already taken", marker)
+            }
+            ParseErr::ResponseFile { path, reason: message } => {
+                write!(f, "can't read response file '{}': {}", path, message)
+            }
+            ParseErr::UnsetVariable(name) => {
+                write!(f, "environment variable '{}' is not set", name)
+            }
+            ParseErr::UnterminatedQuote(quote) => write!(f, "missing closing {}", quote),
+            ParseErr::HelpRequested(help) => write!(f, "{}", help),
+            ParseErr::ConfigFile { path, reason: message } => {
+                write!(f, "can't read config file '{}': {}", path, message)
+            }
+            ParseErr::InvalidConfigFile { line, message } => {
+                write!(f, "invalid config file, line {}: {}", line, message)
+            }
+            ParseErr::VersionRequested(version) => write!(f, "{}", version),
+            ParseErr::ReadFailed { line, reason: message } => {
+                write!(f, "can't read input line {}: {}", line, message)
+            }
+            ParseErr::LimitExceeded { what, limit } => {
+                write!(f, "limit exceeded: at most {} {}", limit, what)
+            }
+            ParseErr::MissingCommand => write!(f, "missing command"),
+            ParseErr::UnknownCommand(name) => write!(f, "unknown command '{}'", name),
+            ParseErr::MissingOneOf(args) => {
+                let flags: Vec<String> = args.iter().map(|arg| flag(arg)).collect();
+                write!(f, "one of {} is required", flags.join(", "))
+            }
+            ParseErr::RequiredIf { arg, other, value } if value == "true" => {
+                write!(f, "{} is required when {} is set", flag(arg), flag(other))
+            }
+            ParseErr::RequiredIf { arg, other, value } => {
+                write!(f, "{} is required when {} is '{}'", flag(arg), flag(other), value)
+            }
+            ParseErr::AmbiguousArg { given, candidates } => {
+                let flags: Vec<String> = candidates.iter().map(|arg| flag(arg)).collect();
+                write!(f, "'{}' is ambiguous: {}", flag(given), flags.join(", "))
+            }
+            ParseErr::PathNotFound { arg: name, path } => {
+                write!(f, "{}: '{}' does not exist", flag(name), path)
+            }
+            ParseErr::NotAFile { arg, path } => {
+                write!(f, "{}: '{}' is not a file", flag(arg), path)
+            }
+            ParseErr::NotADirectory { arg: name, path } => {
+                write!(f, "{}: '{}' is not a directory", flag(name), path)
+            }
+            ParseErr::ParentNotFound { arg: name, path } => {
+                write!(f, "{}: the directory of '{}' does not exist", flag(name), path)
+            }
+            ParseErr::CreateDirFailed { arg: name, dir, reason } => {
+                write!(f, "{}: can't create directory '{}': {}", flag(name), dir, reason)
+            }
+            ParseErr::NoMatches { arg: name, pattern } => {
+                write!(f, "{}: no paths match '{}'", flag(name), pattern)
+            }
+            ParseErr::UnknownPreset(name) => write!(f, "no preset named '{}'", name),
+        }
+    }
+}
+
+impl std::error::Error for ParseErr {}
+
+impl ParseErr {
+    /// The argument the error is about, as named in the schema.
+    pub fn arg(&self) -> Option<&str> {
+        match self {
+            ParseErr::UnknownArg { arg: name, .. }
+            | ParseErr::MissingRequiredArg { arg: name, .. }
+            | ParseErr::ConflictingArgs { arg: name, .. }
+            | ParseErr::ValidationFailed { arg: name, .. }
+            | ParseErr::MissingValue(name)
+            | ParseErr::MissingArg(name)
+            | ParseErr::RequiredIf { arg: name, .. }
+            | ParseErr::TooManyOccurrences { arg: name, .. }
+            | ParseErr::UnexpectedValue(name)
+            | ParseErr::InvalidValue { arg: name, .. }
+            | ParseErr::WrongType { arg: name, .. }
+            | ParseErr::PathNotFound { arg: name, .. }
+            | ParseErr::NotAFile { arg: name, .. }
+            | ParseErr::NotADirectory { arg: name, .. }
+            | ParseErr::ParentNotFound { arg: name, .. }
+            | ParseErr::CreateDirFailed { arg: name, .. }
+            | ParseErr::NoMatches { arg: name, .. }
+            | ParseErr::DuplicateArg(name) => Some(name),
+            ParseErr::NumberFormatErr { arg, .. } if !arg.is_empty() => Some(arg),
+            _ => None,
+        }
+    }
+
+    /// The other argument of a broken constraint, e.g. `p` for `-d`
+    /// requiring `-p`.
+    pub fn related_arg(&self) -> Option<&str> {
+        match self {
+            ParseErr::MissingRequiredArg { required: other, .. }
+            | ParseErr::ConflictingArgs { other, .. }
+            | ParseErr::RequiredIf { other, .. } => Some(other),
+            _ => None,
+        }
+    }
+
+    /// The value that was rejected.
+    pub fn value(&self) -> Option<&str> {
+        match self {
+            ParseErr::NumberFormatErr { value, .. }
+            | ParseErr::InvalidValue { value, .. }
+            | ParseErr::PathNotFound { path: value, .. }
+            | ParseErr::NotAFile { path: value, .. }
+            | ParseErr::NotADirectory { path: value, .. }
+            | ParseErr::ParentNotFound { path: value, .. }
+            | ParseErr::NoMatches { pattern: value, .. }
+            | ParseErr::UnknownPreset(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// The kind of value that was expected, for a value of the wrong type.
+    pub fn expected(&self) -> Option<ArgKind> {
+        match self {
+            ParseErr::NumberFormatErr { .. } => Some(ArgKind::Number),
+            _ => None,
+        }
+    }
+
+    /// Where in the input the flag the error is about was given, as a byte
+    /// range, when the error records it.
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            ParseErr::NumberFormatErr { span, .. } => span.clone(),
+            _ => None,
+        }
+    }
+
+    /// [`ParseErr::UnknownArg`] for a `name` asked for rather than typed.
+    pub(crate) fn unknown_arg(name: &str) -> ParseErr {
+        ParseErr::UnknownArg { arg: name.to_string(), typed: schema::flag(name), suggestion: None }
+    }
+
+    /// `self` given at `span` of the input, for an error from [`Args::set`],
+    /// which doesn't know where the words it sets came from.
+    pub(crate) fn at(self, span: Range<usize>) -> ParseErr {
+        match self {
+            ParseErr::NumberFormatErr { arg, value, .. } => {
+                ParseErr::NumberFormatErr { arg, value, span: Some(span) }
+            }
+            err => err,
+        }
+    }
+
+    /// The explanation given by a validator or a malformed file.
+    pub fn message(&self) -> Option<&str> {
+        match self {
+            ParseErr::ValidationFailed { message, .. }
+            | ParseErr::InvalidSchemaFile { message, .. }
+            | ParseErr::ResponseFile { reason: message, .. }
+            | ParseErr::ConfigFile { reason: message, .. }
+            | ParseErr::InvalidConfigFile { message, .. }
+            | ParseErr::ReadFailed { reason: message, .. }
+            | ParseErr::InvalidTemplate(message) => Some(message),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWarning::Deprecated(arg, Some(replacement)) => write!(
+                f,
+                "{} is deprecated, use {} instead",
+                schema::flag(arg),
+                schema::flag(replacement)
+            ),
+            ParseWarning::Deprecated(arg, None) => {
+                write!(f, "{} is deprecated", schema::flag(arg))
+            }
+            ParseWarning::Overridden(arg) => {
+                write!(f, "{} was given more than once, the last value wins", schema::flag(arg))
+            }
+            ParseWarning::Ignored(arg) => {
+                write!(f, "{} was given more than once, the first value wins", schema::flag(arg))
+            }
+        }
+    }
 }
@@ -0,0 +1,101 @@
+//! Caps on how much input a schema reads, for services parsing strings
+//! that users send.
+
+use crate::{ParseErr, Schema};
+
+/// Caps set with [`Schema::limits`]. Going over one fails with
+/// [`ParseErr::LimitExceeded`] before more of the input is read. `None`
+/// leaves that part of the input unbounded.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Limits {
+    /// The longest input, in bytes, once response files are expanded.
+    pub max_input_len: Option<usize>,
+    /// The most words read: flags, their values and positional words.
+    pub max_tokens: Option<usize>,
+    /// The most values any one argument collects over all its occurrences.
+    pub max_values: Option<usize>,
+    /// How deep response files may name further response files. Expansion
+    /// never goes deeper than eight files.
+    pub max_response_file_depth: Option<usize>,
+}
+
+impl Limits {
+    /// Each cap of `self`, or `other`'s where `self` has none.
+    pub(crate) fn or(self, other: Limits) -> Limits {
+        Limits {
+            max_input_len: self.max_input_len.or(other.max_input_len),
+            max_tokens: self.max_tokens.or(other.max_tokens),
+            max_values: self.max_values.or(other.max_values),
+            max_response_file_depth: self.max_response_file_depth.or(other.max_response_file_depth),
+        }
+    }
+}
+
+/// Fails if `count` is over `limit`, naming what was counted.
+fn check(limit: Option<usize>, count: usize, what: &str) -> Result<(), ParseErr> {
+    match limit {
+        Some(limit) if count > limit => {
+            Err(ParseErr::LimitExceeded { what: what.to_string(), limit })
+        }
+        _ => Ok(()),
+    }
+}
+
+impl Schema {
+    /// Fails on input going over `limits` instead of reading all of it.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Fails if input of `len` bytes is too long.
+    pub(crate) fn check_input_len(&self, len: usize) -> Result<(), ParseErr> {
+        check(self.limits.max_input_len, len, "bytes of input")
+    }
+
+    /// Fails once `count` words have been read and that's too many.
+    pub(crate) fn check_token_count(&self, count: usize) -> Result<(), ParseErr> {
+        check(self.limits.max_tokens, count, "words")
+    }
+
+    /// Fails if reading a response file would nest them `depth` deep.
+    pub(crate) fn check_response_file_depth(&self, depth: usize) -> Result<(), ParseErr> {
+        check(self.limits.max_response_file_depth, depth, "nested response files")
+    }
+
+    /// Fails if the argument `name` has collected too many values.
+    pub(crate) fn check_value_count(&self, name: &str, count: usize) -> Result<(), ParseErr> {
+        let what = format!("values for {}", crate::schema::flag(name));
+        check(self.limits.max_values, count, &what)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate;
+
+    fn exceeded(what: &str, limit: usize) -> ParseErr {
+        ParseErr::LimitExceeded { what: what.to_string(), limit }
+    }
+
+    #[test]
+    fn caps_the_input() {
+        let limits = |limits: Limits| Schema::new("l,p#,d[*],<files>...").unwrap().limits(limits);
+        let schema = limits(Limits { max_input_len: Some(10), ..Limits::default() });
+        assert!(schema.parse("-l -d a b").is_ok());
+        assert_eq!(schema.parse("-l -d a b c").unwrap_err(), exceeded("bytes of input", 10));
+        let schema = limits(Limits { max_tokens: Some(4), ..Limits::default() });
+        assert!(schema.parse("-l -p 1 a.txt").is_ok());
+        let err = schema.parse("-l -p 1 a.txt b.txt").unwrap_err();
+        assert_eq!(err, exceeded("words", 4));
+        assert_eq!(schema.parse("-d a b c d").unwrap_err(), exceeded("words", 4));
+        let errors = validate(&schema, "-l -p 1 a.txt b.txt").unwrap_err();
+        assert_eq!(errors, vec![exceeded("words", 4)]);
+        let schema = limits(Limits { max_values: Some(2), ..Limits::default() });
+        assert!(schema.parse("-d a -d b").is_ok());
+        assert_eq!(schema.parse("-d a b -d c").unwrap_err(), exceeded("values for -d", 2));
+        assert_eq!(schema.parse("-d a b c -l").unwrap_err(), exceeded("values for -d", 2));
+        assert_eq!(err.to_string(), "limit exceeded: at most 4 words");
+    }
+}
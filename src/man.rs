@@ -0,0 +1,81 @@
+//! Man pages rendered from the schema, for build scripts to install next
+//! to the binary.
+
+use crate::Schema;
+
+/// `text` with the characters roff gives a meaning escaped.
+fn escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+    match text.chars().next() {
+        Some('.') | Some('\'') => format!("\\&{}", text),
+        _ => text,
+    }
+}
+
+impl Schema {
+    /// A section 1 man page in roff: the name of `program` with a one-line
+    /// `summary`, the synopsis of [`Schema::usage`], and the entries of
+    /// [`Schema::help`] with their descriptions.
+    pub fn man_page(&self, program: &str, summary: &str) -> String {
+        let usage = self.usage(program);
+        let synopsis = usage.trim_start_matches("usage: ").trim_start_matches(program);
+        let mut page = format!(
+            ".TH {} 1\n.SH NAME\n{} \\- {}\n.SH SYNOPSIS\n.B {}\n{}\n",
+            escape(&program.to_uppercase()),
+            escape(program),
+            escape(summary),
+            escape(program),
+            escape(synopsis.trim_start())
+        );
+        let (arguments, options) = self.help_entries();
+        for (title, entries) in [("ARGUMENTS", arguments), ("OPTIONS", options)] {
+            if entries.is_empty() {
+                continue;
+            }
+            page.push_str(&format!(".SH {}\n", title));
+            for (entry, text) in entries {
+                page.push_str(&format!(".TP\n.B {}\n", escape(&entry)));
+                if !text.is_empty() {
+                    page.push_str(&format!("{}\n", escape(&text)));
+                }
+            }
+        }
+        page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Schema;
+
+    #[test]
+    fn renders_roff() {
+        let schema = Schema::builder()
+            .positional("input")
+            .bool("l")
+            .description("verbose logging")
+            .number("log-level")
+            .default("2")
+            .build()
+            .unwrap();
+        assert_eq!(
+            schema.man_page("app", "processes input"),
+            ".TH APP 1\n\
+             .SH NAME\n\
+             app \\- processes input\n\
+             .SH SYNOPSIS\n\
+             .B app\n\
+             [\\-l] [\\-\\-log\\-level <number>] <input>\n\
+             .SH ARGUMENTS\n\
+             .TP\n\
+             .B <input>\n\
+             .SH OPTIONS\n\
+             .TP\n\
+             .B \\-l\n\
+             verbose logging\n\
+             .TP\n\
+             .B \\-\\-log\\-level <number>\n\
+             [default: 2]\n"
+        );
+    }
+}
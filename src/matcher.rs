@@ -0,0 +1,201 @@
+//! Cheap yes/no checks of an input against a [`Schema`], for pre-filtering
+//! large batches before parsing the ones that pass.
+
+use crate::schema::ArgKind;
+use crate::{split_words, Schema, TokensIterator};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug)]
+enum Expect {
+    Switch,
+    Anything,
+    Number,
+    Numbers,
+}
+
+/// A lookup table compiled from a schema by [`Schema::matcher`].
+///
+/// Rejected inputs would fail to parse; accepted ones still can, since
+/// validators and `requires`/`conflicts_with` constraints aren't checked.
+#[derive(Debug)]
+pub struct Matcher {
+    names: HashMap<String, Expect>,
+    folded: Option<HashMap<String, Expect>>,
+    positionals: bool,
+    prefixes: String,
+    response_files: bool,
+    shell_words: bool,
+    capture_rest: bool,
+    collect_unknown: bool,
+}
+
+impl Matcher {
+    fn expect(&self, name: &str) -> Option<Expect> {
+        self.names.get(name).copied().or_else(|| {
+            let folded = self.folded.as_ref()?;
+            folded.get(&name.to_lowercase()).copied()
+        })
+    }
+
+    /// `true` when every flag in `input` is known and every number is
+    /// well formed. Unknown flags pass when the schema collects them. Input
+    /// naming a response file is always accepted, as files aren't read, and
+    /// so is any input when the schema captures the rest, as where the rest
+    /// begins depends on the argument types.
+    pub fn is_valid(&self, input: &str) -> bool {
+        if self.capture_rest {
+            return true;
+        }
+        if self.response_files && split_words(input).any(|word| word.raw.starts_with('@')) {
+            return true;
+        }
+        let numeric = |name: &str| self.takes_numbers(name);
+        let mut tokens = TokensIterator::from(input).with_numeric(&numeric);
+        if self.shell_words {
+            tokens = tokens.with_posix_quoting();
+        }
+        let mut tokens = tokens.with_prefixes(&self.prefixes);
+        if self.positionals {
+            tokens = tokens.with_positionals();
+        }
+        tokens.all(|token| {
+            let values = if self.positionals {
+                token.values.first().cloned().unwrap_or_default()
+            } else {
+                Cow::Owned(token.values.join(""))
+            };
+            match self.expect(&token.name) {
+                Some(Expect::Number) => values.parse::<isize>().is_ok(),
+                Some(_) => true,
+                None => self.collect_unknown || self.is_valid_cluster(&token.name, &values),
+            }
+        })
+    }
+
+    /// Whether the flag, or the flag in a cluster taking a value, takes
+    /// numbers. `None` for unknown flags.
+    fn takes_numbers(&self, name: &str) -> Option<bool> {
+        let takes_numbers = |expect| matches!(expect, Expect::Number | Expect::Numbers);
+        if let Some(expect) = self.expect(name) {
+            return Some(takes_numbers(expect));
+        }
+        for c in name.chars() {
+            match self.expect(&c.to_string())? {
+                Expect::Switch => continue,
+                expect => return Some(takes_numbers(expect)),
+            }
+        }
+        Some(false)
+    }
+
+    /// Short flags run together, e.g. `-lrt` or `-lp8080`.
+    fn is_valid_cluster(&self, flags: &str, values: &str) -> bool {
+        for (i, c) in flags.char_indices() {
+            match self.expect(&c.to_string()) {
+                Some(Expect::Switch) => continue,
+                Some(Expect::Number) => {
+                    let attached = &flags[i + c.len_utf8()..];
+                    let attached = attached.strip_prefix('=').unwrap_or(attached);
+                    return format!("{}{}", attached, values).parse::<isize>().is_ok();
+                }
+                Some(Expect::Anything | Expect::Numbers) => return true,
+                None => return false,
+            }
+        }
+        !flags.is_empty()
+    }
+}
+
+impl Schema {
+    pub fn matcher(&self) -> Matcher {
+        let flags = self.specs.iter().filter(|spec| !spec.positional);
+        let spec_names = flags.map(|spec| {
+            let expect = match spec.kind {
+                ArgKind::Bool => Expect::Switch,
+                ArgKind::Number => Expect::Number,
+                ArgKind::NumberArray => Expect::Numbers,
+                _ => Expect::Anything,
+            };
+            (spec.name.clone(), expect)
+        });
+        let switches = self
+            .inversions
+            .iter()
+            .map(|(switch, _)| (switch.clone(), Expect::Switch));
+        let bools = self.specs.iter().filter(|spec| spec.kind == ArgKind::Bool && !spec.positional);
+        let negations = bools.flat_map(|spec| {
+            let short = (spec.name.chars().count() == 1).then(|| format!("{}-", spec.name));
+            short.into_iter().chain([format!("no-{}", spec.name)])
+        });
+        let negations = negations.map(|negation| (negation, Expect::Switch));
+        let ordered: Vec<_> = spec_names.chain(switches).chain(negations).collect();
+        let folded = self.case_insensitive.then(|| {
+            let mut folded = HashMap::new();
+            for (name, expect) in &ordered {
+                folded.entry(name.to_lowercase()).or_insert(*expect);
+            }
+            folded
+        });
+        let mut names = HashMap::new();
+        for (name, expect) in ordered {
+            names.entry(name).or_insert(expect);
+        }
+        Matcher {
+            names,
+            folded,
+            positionals: self.has_positionals(),
+            prefixes: self.prefixes.clone(),
+            response_files: self.response_files,
+            shell_words: self.shell_words,
+            capture_rest: self.capture_rest,
+            collect_unknown: self.collect_unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn agrees_with_parse() {
+        let schema = Schema::new("l,p#,d*,n[#]").unwrap().inverted("q", "l");
+        let matcher = schema.matcher();
+        let inputs = [
+            "",
+            "-l -p 8080 -d /usr/logs",
+            "-n 1 2 -q",
+            "-p",
+            "-p x",
+            "-x",
+            "-l -P 1",
+            "-p8080 -d/tmp",
+            "-px",
+            "-lq",
+            "-lnq",
+            "-ln1 2",
+            "-lnx",
+            "-lz",
+            "-p -1",
+            "-p -1 -l",
+            "-lp -1",
+            "-n -1 -2",
+            "-l -1",
+            "-p -x",
+            "-l-",
+            "--no-l -l",
+            "-p-",
+        ];
+        for input in inputs {
+            assert_eq!(matcher.is_valid(input), schema.parse(input).is_ok(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn honours_case_insensitivity() {
+        let schema = Schema::new("p#").unwrap().case_insensitive();
+        assert!(schema.matcher().is_valid("-P 1"));
+        assert!(!schema.matcher().is_valid("-P one"));
+    }
+}
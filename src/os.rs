@@ -0,0 +1,157 @@
+//! Parsing `std::env::args_os` style input, whose words aren't always
+//! valid UTF-8.
+
+use crate::{ParseErr, ParsedArgs, Schema};
+use std::ffi::{OsStr, OsString};
+
+/// Parses `args` against the schema definition `schema`, see
+/// [`Schema::parse_os`].
+pub fn parse_os(schema: &str, args: &[OsString]) -> Result<ParsedArgs, ParseErr> {
+    Schema::new(schema)?.parse_os(args)
+}
+
+/// Parses the arguments the program was started with against the schema
+/// definition `schema`, see [`Schema::parse_env`].
+pub fn parse_env(schema: &str) -> Result<ParsedArgs, ParseErr> {
+    Schema::new(schema)?.parse_env()
+}
+
+impl Schema {
+    /// Parses words that have already been split, such as those of
+    /// `std::env::args_os().skip(1)`. Words that aren't valid UTF-8 are
+    /// read in their lossy form, with `U+FFFD` in place of what couldn't be
+    /// decoded, while [`ParsedArgs::get_os`] still returns them as given.
+    pub fn parse_os(&self, args: &[OsString]) -> Result<ParsedArgs, ParseErr> {
+        let mut os_words = vec![];
+        let mut words = vec![];
+        for arg in args {
+            let word = arg.to_string_lossy().to_string();
+            if arg.to_str().is_none() {
+                os_words.push((word.clone(), arg.clone()));
+            }
+            words.push(word);
+        }
+        let mut parsed = self.parse_from(words)?;
+        parsed.os_words = os_words;
+        Ok(parsed)
+    }
+
+    /// Parses the arguments the program was started with, from
+    /// `std::env::args_os` without the program name, as
+    /// [`Schema::parse_os`] does.
+    pub fn parse_env(&self) -> Result<ParsedArgs, ParseErr> {
+        let args: Vec<OsString> = std::env::args_os().skip(1).collect();
+        self.parse_os(&args)
+    }
+
+    /// Parses `input`, or prints the error and usage to stderr and exits
+    /// with status 2, as command-line tools conventionally do. Help or the
+    /// version asked for with [`Schema::help_flags`] or
+    /// [`Schema::version_flags`] is printed to stdout instead, and exits
+    /// with status 0.
+    pub fn parse_or_exit(&self, input: &str) -> ParsedArgs {
+        self.parse_or_exit_with(input, 2)
+    }
+
+    /// [`Schema::parse_or_exit`] with the exit status of `status`.
+    pub fn parse_or_exit_with(&self, input: &str, status: i32) -> ParsedArgs {
+        match self.parse(input) {
+            Ok(parsed) => parsed,
+            Err(ParseErr::HelpRequested(text)) | Err(ParseErr::VersionRequested(text)) => {
+                println!("{}", text);
+                std::process::exit(0)
+            }
+            Err(err) => {
+                eprintln!("{}", self.failure_report(input, &err, &program_name()));
+                std::process::exit(status)
+            }
+        }
+    }
+
+    fn failure_report(&self, input: &str, err: &ParseErr, program: &str) -> String {
+        format!("{}\n\n{}", self.render_error(input, err), self.usage(program))
+    }
+}
+
+/// The file name the program was started as, for usage lines.
+fn program_name() -> String {
+    let program = std::env::args_os().next().unwrap_or_default();
+    let name = std::path::Path::new(&program).file_name().map(OsStr::to_string_lossy);
+    name.map_or_else(|| "app".to_string(), |name| name.to_string())
+}
+
+impl ParsedArgs {
+    /// The value of `name` as given to [`Schema::parse_os`], bytes that
+    /// aren't valid UTF-8 included. For other input it is the same as
+    /// [`crate::Args::get`].
+    pub fn get_os(&self, name: &str) -> Option<OsString> {
+        let value = self.get(name)?.get()?;
+        Some(self.os_word(value))
+    }
+
+    /// [`ParsedArgs::get_os`] for each element of the list `name`.
+    pub fn get_os_list(&self, name: &str) -> Vec<OsString> {
+        let values = self.get(name).map(|arg| arg.as_str_array()).unwrap_or_default();
+        values.into_iter().map(|value| self.os_word(value)).collect()
+    }
+
+    fn os_word(&self, value: String) -> OsString {
+        let original = self.os_words.iter().find(|(lossy, _)| *lossy == value);
+        match original {
+            Some((_, original)) => original.clone(),
+            None => OsStr::new(&value).to_os_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn os(words: &[&str]) -> Vec<OsString> {
+        words.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn parses_words_as_given() {
+        let args = parse_os("l,d*,s[*]", &os(&["-l", "-d", "/var/my logs", "-s", "a'b", ""]))
+            .unwrap();
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/my logs");
+        assert_eq!(args.get_os("d").unwrap(), "/var/my logs");
+        assert_eq!(args.get_os_list("s"), os(&["a'b", ""]));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn keeps_values_that_are_not_utf8() {
+        use std::os::unix::ffi::OsStringExt;
+        let path = OsString::from_vec(b"/tmp/caf\xe9".to_vec());
+        let args = parse_os("d*,l", &[OsString::from("-d"), path.clone(), "-l".into()]).unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/tmp/caf\u{fffd}");
+        assert_eq!(args.get_os("d").unwrap(), path);
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+    }
+
+    #[test]
+    fn parses_the_program_arguments() {
+        let args = Schema::new("z").unwrap().capture_rest().parse_env().unwrap();
+        let mut expected: Vec<String> = std::env::args().skip(1).collect();
+        if expected.first().is_some_and(|arg| arg == "--") {
+            expected.remove(0);
+        }
+        assert_eq!(args.trailing(), expected);
+    }
+
+    #[test]
+    fn failure_report_shows_the_error_and_usage() {
+        let schema = Schema::new("l,p#").unwrap();
+        assert_eq!(schema.parse_or_exit("-p 1").get_number("p"), Some(1));
+        let err = schema.parse("-p x").unwrap_err();
+        assert_eq!(
+            schema.failure_report("-p x", &err, "app"),
+            "error: 'x' is not a number\n  | -p x\n  |    ^\n\nusage: app [-l] [-p <number>]"
+        );
+        assert!(!program_name().is_empty());
+    }
+}
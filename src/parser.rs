@@ -0,0 +1,360 @@
+//! Parsing policies set in one place, on top of a schema definition.
+
+use crate::schema::ArgKind;
+use crate::{AbsentBool, Accumulation, Limits, ParseErr, ParseWarning, ParsedArgs, Schema};
+use std::io::BufRead;
+
+/// A schema compiled once, by [`Schema::new`] or [`ParserBuilder::build`],
+/// for parsing many inputs, e.g. one per request or per line of a REPL.
+/// [`crate::parse`] compiles the definition again on every call.
+pub type Parser = Schema;
+
+/// What to do with a flag the schema doesn't declare.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UnknownArgs {
+    /// Fail with [`ParseErr::UnknownArg`].
+    Error,
+    /// End the flags there, see [`Schema::capture_rest`].
+    Capture,
+    /// Set it aside and go on, see [`Schema::collect_unknown`].
+    Collect,
+}
+
+/// A coherent set of parsing policies, applied at once with
+/// [`Schema::with_profile`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Profile {
+    /// Every scalar argument at most once while lists still grow, unknown
+    /// flags fail, and input is split the way a shell would.
+    Strict,
+    /// As a POSIX utility: repeats override earlier values, and the flags
+    /// end at the first word there's no place for, which is left with
+    /// everything after it in [`ParsedArgs::trailing`].
+    Posix,
+    /// As GNU tools: repeats override earlier values, flags and positional
+    /// arguments mix in any order, long flags can be abbreviated and
+    /// unknown flags fail.
+    Gnu,
+    /// Flags match regardless of case, repeats override earlier values, and
+    /// unknown flags are set aside in [`ParsedArgs::unknown`].
+    Lenient,
+}
+
+/// Compiles a definition together with the policies applying to all of its
+/// arguments:
+///
+/// ```
+/// use args::{Accumulation, ParserBuilder, UnknownArgs};
+///
+/// let parser = ParserBuilder::new("l,p[#]")
+///     .duplicates(Accumulation::Error)
+///     .unknown_args(UnknownArgs::Capture)
+///     .delimiter(',')
+///     .build()
+///     .unwrap();
+/// let args = parser.parse("-p 80,443 -l run -v").unwrap();
+/// assert_eq!(args.get("p").unwrap().as_num_array(), vec![80, 443]);
+/// assert_eq!(args.trailing(), &["run", "-v"]);
+/// ```
+///
+/// Per-argument settings made on the built [`Schema`] override these.
+pub struct ParserBuilder {
+    definition: String,
+    duplicates: Option<Accumulation>,
+    absent_bools: AbsentBool,
+    unknown_args: UnknownArgs,
+    case_insensitive: bool,
+    prefixes: String,
+    delimiter: Option<char>,
+    limits: Limits,
+}
+
+impl ParserBuilder {
+    pub fn new(definition: &str) -> Self {
+        Self {
+            definition: definition.to_string(),
+            duplicates: None,
+            absent_bools: AbsentBool::False,
+            unknown_args: UnknownArgs::Error,
+            case_insensitive: false,
+            prefixes: String::new(),
+            delimiter: None,
+            limits: Limits::default(),
+        }
+    }
+
+    /// What repeating any argument does, see [`Schema::accumulate`]. Unless
+    /// it is [`Accumulation::Error`], this also lifts the limit of one
+    /// occurrence that scalars have by default. Under
+    /// [`Accumulation::Concat`] only strings concatenate, and other
+    /// arguments append.
+    pub fn duplicates(mut self, accumulation: Accumulation) -> Self {
+        self.duplicates = Some(accumulation);
+        self
+    }
+
+    /// What every bool reads as when it isn't given, see
+    /// [`Schema::absent_bool`].
+    pub fn absent_bools(mut self, absent: AbsentBool) -> Self {
+        self.absent_bools = absent;
+        self
+    }
+
+    pub fn unknown_args(mut self, unknown_args: UnknownArgs) -> Self {
+        self.unknown_args = unknown_args;
+        self
+    }
+
+    /// See [`Schema::case_insensitive`].
+    pub fn case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// See [`Schema::flag_prefixes`].
+    pub fn prefixes(mut self, prefixes: &str) -> Self {
+        self.prefixes = prefixes.to_string();
+        self
+    }
+
+    /// Splits the values of every list at `delimiter`, see
+    /// [`Schema::delimiter`].
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = Some(delimiter);
+        self
+    }
+
+    /// Caps on the input, see [`Schema::limits`].
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn build(self) -> Result<Parser, ParseErr> {
+        let mut schema = Schema::new(&self.definition)?
+            .flag_prefixes(&self.prefixes)
+            .limits(self.limits);
+        schema.case_insensitive = self.case_insensitive;
+        schema.capture_rest = self.unknown_args == UnknownArgs::Capture;
+        schema.collect_unknown = self.unknown_args == UnknownArgs::Collect;
+        for spec in &mut schema.specs {
+            if let Some(accumulation) = self.duplicates {
+                spec.accumulation = match accumulation {
+                    Accumulation::Concat if !spec.kind.is_string() => Accumulation::Append,
+                    accumulation => accumulation,
+                };
+                if accumulation != Accumulation::Error {
+                    spec.max_occurrences = None;
+                }
+            }
+            if matches!(spec.kind, ArgKind::StrArray | ArgKind::NumberArray) {
+                spec.delimiter = self.delimiter;
+            }
+            spec.absent = self.absent_bools;
+        }
+        Ok(schema)
+    }
+}
+
+impl Schema {
+    /// Applies every policy of `profile`, over switches set before. Settings
+    /// made for a single argument afterwards override it.
+    ///
+    /// ```
+    /// use args::{Parser, Profile};
+    ///
+    /// let parser = Parser::new("l,p#").unwrap().with_profile(Profile::Posix);
+    /// let args = parser.parse("-p 80 -p 8080 run -l").unwrap();
+    /// assert_eq!(args.get_number("p"), Some(8080));
+    /// assert_eq!(args.trailing(), &["run", "-l"]);
+    /// ```
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        let (accumulation, unknown_args) = match profile {
+            Profile::Strict => (Accumulation::Error, UnknownArgs::Error),
+            Profile::Posix => (Accumulation::Append, UnknownArgs::Capture),
+            Profile::Gnu => (Accumulation::Append, UnknownArgs::Error),
+            Profile::Lenient => (Accumulation::Append, UnknownArgs::Collect),
+        };
+        for spec in &mut self.specs {
+            let list = matches!(spec.kind, ArgKind::StrArray | ArgKind::NumberArray);
+            spec.accumulation = match accumulation {
+                Accumulation::Error if list => Accumulation::Append,
+                accumulation => accumulation,
+            };
+            spec.max_occurrences = Some(1).filter(|_| spec.accumulation == Accumulation::Error);
+        }
+        self.capture_rest = unknown_args == UnknownArgs::Capture;
+        self.collect_unknown = unknown_args == UnknownArgs::Collect;
+        self.case_insensitive = profile == Profile::Lenient;
+        self.shell_words = profile != Profile::Lenient;
+        self.abbreviations = profile == Profile::Gnu;
+        self
+    }
+
+    /// Parses each line of `reader` as one input, e.g. argument strings
+    /// stored one per line by a job runner. Blank lines parse as empty
+    /// input, so results line up with line numbers. A line that can't be
+    /// read yields [`ParseErr::ReadFailed`] and ends the iteration.
+    pub fn parse_lines<'a, R: BufRead + 'a>(
+        &'a self,
+        reader: R,
+    ) -> impl Iterator<Item = Result<ParsedArgs, ParseErr>> + 'a {
+        let mut failed = false;
+        reader.lines().enumerate().map_while(move |(index, line)| {
+            if failed {
+                return None;
+            }
+            Some(match line {
+                Ok(line) => self.parse(&line),
+                Err(err) => {
+                    failed = true;
+                    Err(ParseErr::ReadFailed { line: index + 1, reason: err.to_string() })
+                }
+            })
+        })
+    }
+
+    /// Parses independent `inputs` on as many threads as the machine runs
+    /// in parallel, returning the results in the order of `inputs`.
+    pub fn par_parse_batch(&self, inputs: &[&str]) -> Vec<Result<ParsedArgs, ParseErr>> {
+        let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let chunk = inputs.len().div_ceil(threads).max(1);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = inputs
+                .chunks(chunk)
+                .map(|inputs| {
+                    scope.spawn(move || {
+                        inputs.iter().map(|input| self.parse(input)).collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            let join = |handle: std::thread::ScopedJoinHandle<'_, _>| {
+                handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+            };
+            handles.into_iter().flat_map(join).collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lines() {
+        let parser = Parser::new("l,p#").unwrap();
+        let results: Vec<_> = parser.parse_lines(&b"-l -p 1\n\n-p x\n"[..]).collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().get_number("p"), Some(1));
+        assert_eq!(results[1].as_ref().unwrap().get_bool("l"), Some(false));
+        let (arg, value) = ("p".to_string(), "x".to_string());
+        let err = ParseErr::NumberFormatErr { arg, value, span: Some(0..4) };
+        assert_eq!(results[2].as_ref().unwrap_err(), &err);
+        let invalid = parser.parse_lines(&b"-l\n\xff\n-l\n"[..]).collect::<Vec<_>>();
+        assert_eq!(invalid.len(), 2);
+        assert!(matches!(&invalid[1], Err(ParseErr::ReadFailed { line: 2, .. })));
+    }
+
+    #[test]
+    fn parses_batches_in_parallel() {
+        let parser = Parser::new("l,p#").unwrap();
+        let inputs: Vec<String> = (0..100).map(|port| format!("-p {}", port)).collect();
+        let mut inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+        inputs.push("-p x");
+        let results = parser.par_parse_batch(&inputs);
+        assert_eq!(results.len(), 101);
+        for (port, result) in results[..100].iter().enumerate() {
+            assert_eq!(result.as_ref().unwrap().get_number("p"), Some(port as isize));
+        }
+        let (arg, value) = ("p".to_string(), "x".to_string());
+        let err = ParseErr::NumberFormatErr { arg, value, span: Some(0..4) };
+        assert_eq!(results[100], Err(err));
+        assert!(parser.par_parse_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn profiles_bundle_policies() {
+        let schema = Schema::new("l,p#,d*,s[*],<input>").unwrap();
+        let strict = schema.clone().with_profile(Profile::Strict);
+        let err = strict.parse("-d a -d b in").unwrap_err();
+        assert_eq!(err, ParseErr::TooManyOccurrences { arg: "d".to_string(), max: 1 });
+        let err = strict.parse("-l -l in").unwrap_err();
+        assert_eq!(err, ParseErr::TooManyOccurrences { arg: "l".to_string(), max: 1 });
+        assert_eq!(strict.parse("in -s a -s b").unwrap().get_list("s"), vec!["a", "b"]);
+        assert_eq!(strict.parse("-d 'a").unwrap_err(), ParseErr::UnterminatedQuote('\''));
+        let gnu = schema.clone().with_profile(Profile::Gnu);
+        let args = gnu.parse("in -p 1 -p 2").unwrap();
+        assert_eq!((args.get_number("p"), args.get_str("input")), (Some(2), Some("in".into())));
+        assert_eq!(gnu.parse("-x in").unwrap_err(), ParseErr::UnknownArg {
+            arg: "x".into(),
+            typed: "-x".into(),
+            suggestion: Some("-d".into()),
+        });
+        let gnu = Schema::builder().bool("verbose").build().unwrap().with_profile(Profile::Gnu);
+        assert_eq!(gnu.parse("--verb").unwrap().get_bool("verbose"), Some(true));
+        let posix = schema.clone().with_profile(Profile::Posix);
+        assert_eq!(posix.parse("in -x -l").unwrap().trailing(), &["-x", "-l"]);
+        let args = schema.with_profile(Profile::Lenient).parse("in -L -x 1").unwrap();
+        assert_eq!(args.get_bool("l"), Some(true));
+        assert_eq!(args.unknown(), &["-x", "1"]);
+    }
+
+    #[test]
+    fn defaults_match_the_schema() {
+        let parser = ParserBuilder::new("l,p#,s[*]").build().unwrap();
+        let args = parser.parse("-l -s a -s b:c").unwrap();
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a", "b:c"]);
+        assert_eq!(
+            parser.parse("-p 1 -p 2").unwrap_err(),
+            ParseErr::TooManyOccurrences { arg: "p".to_string(), max: 1 }
+        );
+        assert_eq!(parser.parse("-x").unwrap_err(), ParseErr::UnknownArg {
+            arg: "x".into(),
+            typed: "-x".into(),
+            suggestion: Some("-l".into()),
+        });
+        assert_eq!(parser.fingerprint(), Schema::new("l,p#,s[*]").unwrap().fingerprint());
+    }
+
+    #[test]
+    fn policies_apply_to_every_argument() {
+        let parser = ParserBuilder::new("l,p#,s[*]")
+            .duplicates(Accumulation::Error)
+            .case_insensitive(true)
+            .prefixes("/")
+            .delimiter(':')
+            .build()
+            .unwrap();
+        let args = parser.parse("/P 1 -s a:b").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number(), Some(1));
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a", "b"]);
+        assert_eq!(
+            parser.parse("-l -l").unwrap_err(),
+            ParseErr::TooManyOccurrences { arg: "l".to_string(), max: 1 }
+        );
+        let parser = ParserBuilder::new("p#,s[*]").duplicates(Accumulation::Replace).build();
+        let args = parser.unwrap().parse("-p 1 -s a -p 2 -s b").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number(), Some(2));
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["b"]);
+        let overridden = ["p", "s"].iter().map(|name| ParseWarning::Overridden(name.to_string()));
+        assert_eq!(args.warnings(), &overridden.collect::<Vec<_>>()[..]);
+        let parser = ParserBuilder::new("p#,d*").duplicates(Accumulation::Keep).build();
+        let args = parser.unwrap().parse("-p 1 -d a -p 2 -d b").unwrap();
+        assert_eq!(args.get_number("p"), Some(1));
+        assert_eq!(args.get_str("d"), Some("a".to_string()));
+        assert_eq!(args.occurrences_of("p"), 2);
+        assert_eq!(
+            args.warnings(),
+            &[ParseWarning::Ignored("p".to_string()), ParseWarning::Ignored("d".to_string())]
+        );
+        let err = ParserBuilder::new("p?").build().unwrap_err();
+        assert_eq!(err, ParseErr::UnsupportedArgType("?".to_string()));
+        let parser = ParserBuilder::new("l,v,p#").absent_bools(AbsentBool::Unset).build();
+        let args = parser.unwrap().parse("-v false").unwrap();
+        assert_eq!((args.get_bool("l"), args.get_bool("v")), (None, Some(false)));
+        assert_eq!(args.to_json(), r#"{"l":null,"v":false,"p":null}"#);
+        let parser = ParserBuilder::new("p#,d*").duplicates(Accumulation::Concat).build();
+        let args = parser.unwrap().parse("-p 1 -d a -p 2 -d b").unwrap();
+        assert_eq!((args.get_number("p"), args.get_str("d")), (Some(2), Some("a b".into())));
+    }
+}
@@ -0,0 +1,110 @@
+//! Asking for missing required arguments on the terminal, for schemas
+//! with [`Schema::prompt_missing`].
+
+use crate::schema::Classification;
+use crate::{ParsedArgs, Schema, Source};
+use std::io::{BufRead, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Turns terminal echo on or off, for typing secrets, by running `stty`
+/// on the terminal. Where there's no `stty` the input is echoed.
+fn set_echo(on: bool) {
+    let _ = Command::new("stty")
+        .arg(if on { "echo" } else { "-echo" })
+        .stdin(Stdio::inherit())
+        .status();
+}
+
+impl Schema {
+    /// Asks for required arguments still missing after the input, the
+    /// environment and the defaults, when standard input is a terminal,
+    /// instead of failing with [`ParseErr::MissingArg`](crate::ParseErr).
+    /// Input for [`Classification::Secret`] arguments isn't echoed. An
+    /// empty answer leaves the argument missing, and values the schema
+    /// rejects are asked for again.
+    pub fn prompt_missing(mut self) -> Self {
+        self.prompt = true;
+        self
+    }
+
+    /// Prompts on the terminal, see [`Schema::prompt_missing`].
+    pub(crate) fn prompt_if_terminal(&self, parsed: &mut ParsedArgs) {
+        let stdin = std::io::stdin();
+        if self.prompt && stdin.is_terminal() {
+            self.prompt_for_missing(parsed, &mut stdin.lock(), &mut std::io::stderr(), set_echo);
+        }
+    }
+
+    /// Reads the required arguments missing from `parsed` from `input`, one
+    /// line each, writing the prompts and any errors to `output`.
+    pub(crate) fn prompt_for_missing(
+        &self,
+        parsed: &mut ParsedArgs,
+        input: &mut dyn BufRead,
+        output: &mut dyn Write,
+        echo: fn(bool),
+    ) {
+        for spec in &self.specs {
+            let secret = spec.classification == Classification::Secret;
+            while spec.required && !parsed.sources.contains_key(spec.name.as_str()) {
+                let label = spec.description.as_deref().unwrap_or(&spec.name);
+                let _ = write!(output, "{}: ", label);
+                let _ = output.flush();
+                if secret {
+                    echo(false);
+                }
+                let mut line = String::new();
+                let read = input.read_line(&mut line);
+                if secret {
+                    echo(true);
+                    let _ = writeln!(output);
+                }
+                let value = line.trim_end_matches(&['\r', '\n'][..]);
+                if read.map_or(true, |read| read == 0) || value.is_empty() {
+                    break;
+                }
+                let filled = self.fill(parsed, &spec.name, value.to_string(), Source::Prompt);
+                if let Err(err) = filled {
+                    let _ = writeln!(output, "{}", err);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Classification, Input, ParseErr, Schema, Source};
+
+    fn prompted(schema: &Schema, input: &str, answers: &str) -> (Result<String, ParseErr>, String) {
+        let mut parsed = schema.parse_input(Input::typed(input)).unwrap();
+        schema.apply_defaults(&mut parsed).unwrap();
+        let mut output = vec![];
+        schema.prompt_for_missing(&mut parsed, &mut answers.as_bytes(), &mut output, |_| {});
+        let result = schema.check_parsed(&parsed).map(|_| parsed.to_json());
+        (result, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn prompts_for_missing_required_args() {
+        let schema = Schema::builder()
+            .number("p")
+            .required()
+            .description("port")
+            .string("t")
+            .required()
+            .build()
+            .unwrap()
+            .classify("t", Classification::Secret)
+            .prompt_missing();
+        let (result, output) = prompted(&schema, "", "eighty\n8080\nhunter2\n");
+        assert_eq!(result, Ok("{\"p\":8080,\"t\":\"hunter2\"}".to_string()));
+        assert_eq!(output, "port: 'eighty' is not a number\nport: t: \n");
+        let (result, output) = prompted(&schema, "-p 1", "");
+        assert_eq!(result, Err(ParseErr::MissingArg("t".to_string())));
+        assert_eq!(output, "t: \n");
+        let mut parsed = schema.parse_input(Input::typed("")).unwrap();
+        schema.prompt_for_missing(&mut parsed, &mut "1\n".as_bytes(), &mut vec![], |_| {});
+        assert_eq!(parsed.source("p"), Some(Source::Prompt));
+    }
+}
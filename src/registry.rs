@@ -0,0 +1,108 @@
+//! A process-wide registry of named schemas, so crates can publish
+//! reusable flag bundles and applications can compose them at startup.
+//!
+//! ```
+//! use args::{registry, Schema};
+//!
+//! registry::register("doc-logging", Schema::new("l,v").unwrap()).unwrap();
+//! registry::register("doc-server", Schema::new("p#").unwrap()).unwrap();
+//! let schema = registry::compose(&["doc-logging", "doc-server"]).unwrap();
+//! assert_eq!(schema.usage("app"), "usage: app [-l] [-v] [-p <number>]");
+//! ```
+
+use crate::{ParseErr, Schema};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+fn schemas() -> &'static Mutex<HashMap<String, Schema>> {
+    static SCHEMAS: OnceLock<Mutex<HashMap<String, Schema>>> = OnceLock::new();
+    SCHEMAS.get_or_init(Default::default)
+}
+
+/// Publishes `schema` under `name`. Names can be registered only once.
+pub fn register(name: &str, schema: Schema) -> Result<(), ParseErr> {
+    let mut schemas = schemas().lock().unwrap_or_else(|e| e.into_inner());
+    if schemas.contains_key(name) {
+        return Err(ParseErr::DuplicateSchema(name.to_string()));
+    }
+    schemas.insert(name.to_string(), schema);
+    Ok(())
+}
+
+/// A copy of the schema registered under `name`.
+pub fn get(name: &str) -> Option<Schema> {
+    let schemas = schemas().lock().unwrap_or_else(|e| e.into_inner());
+    schemas.get(name).cloned()
+}
+
+/// Combines the named schemas, in order, into one. Fails if a name isn't
+/// registered or two of them declare the same argument.
+pub fn compose(names: &[&str]) -> Result<Schema, ParseErr> {
+    let schemas = schemas().lock().unwrap_or_else(|e| e.into_inner());
+    let mut found = names.iter().map(|name| {
+        schemas
+            .get(*name)
+            .ok_or_else(|| ParseErr::UnknownSchema(name.to_string()))
+    });
+    let mut composed = match found.next() {
+        Some(first) => first?.clone(),
+        None => return Err(ParseErr::InvalidSchema),
+    };
+    for schema in found {
+        composed.include(schema?)?;
+    }
+    Ok(composed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composes_registered_schemas() {
+        let logging = Schema::new("l").unwrap().inverted("q", "l");
+        register("test-logging", logging).unwrap();
+        register("test-server", Schema::new("p#,d*").unwrap().requires("d", "p")).unwrap();
+        let schema = compose(&["test-logging", "test-server"]).unwrap();
+        assert_eq!(schema.usage("app"), "usage: app [-l] [-p <number>] [-d <string>] [-q]");
+        assert_eq!(
+            schema.parse("-d /tmp").unwrap_err(),
+            ParseErr::MissingRequiredArg { arg: "d".to_string(), required: "p".to_string() }
+        );
+        assert!(get("test-server").is_some());
+    }
+
+    #[test]
+    fn rejects_duplicates_and_unknown_names() {
+        register("test-dup", Schema::new("v").unwrap()).unwrap();
+        assert_eq!(
+            register("test-dup", Schema::new("x").unwrap()),
+            Err(ParseErr::DuplicateSchema("test-dup".to_string()))
+        );
+        register("test-dup-2", Schema::new("v#").unwrap()).unwrap();
+        assert_eq!(
+            compose(&["test-dup", "test-dup-2"]).unwrap_err(),
+            ParseErr::DuplicateArg("v".to_string())
+        );
+        assert_eq!(
+            compose(&["test-dup", "test-missing"]).unwrap_err(),
+            ParseErr::UnknownSchema("test-missing".to_string())
+        );
+        assert_eq!(compose(&[]).unwrap_err(), ParseErr::InvalidSchema);
+    }
+
+    #[test]
+    fn registers_concurrently() {
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                std::thread::spawn(move || {
+                    register(&format!("test-thread-{}", i), Schema::new("t").unwrap())
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap().unwrap();
+        }
+        assert!((0..8).all(|i| get(&format!("test-thread-{}", i)).is_some()));
+    }
+}
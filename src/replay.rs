@@ -0,0 +1,275 @@
+//! Opt-in recording of parsed invocations, for "history" and "re-run last
+//! command" features.
+//!
+//! A [`Recorder`] forwards every successful parse to a [`ReplaySink`].
+//! [`JsonlFile`] appends them to a file, one JSON object per line:
+//!
+//! ```text
+//! {"timestamp":1700000000000,"command_line":"-p 8080 -l","schema":"2f0c5e8b7a3d9e41"}
+//! ```
+
+use crate::{ParseErr, ParsedArgs, Schema};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One recorded parse.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Invocation {
+    /// Milliseconds since the Unix epoch.
+    pub timestamp: u64,
+    /// The input with flag names spelled as in the schema.
+    pub command_line: String,
+    /// [`Schema::fingerprint`] of the schema the input was parsed with.
+    pub schema: String,
+}
+
+impl Invocation {
+    pub fn to_json_line(&self) -> String {
+        format!(
+            "{{\"timestamp\":{},\"command_line\":{},\"schema\":{}}}",
+            self.timestamp,
+            json_string(&self.command_line),
+            json_string(&self.schema)
+        )
+    }
+
+    /// Reads back a line written by [`Invocation::to_json_line`].
+    pub fn from_json_line(line: &str) -> Option<Invocation> {
+        let mut rest = line.trim().strip_prefix('{')?;
+        let mut timestamp = None;
+        let mut command_line = None;
+        let mut schema = None;
+        loop {
+            let (key, after_key) = read_json_string(rest.trim_start())?;
+            let after_colon = after_key.trim_start().strip_prefix(':')?.trim_start();
+            let after_value = match key.as_str() {
+                "timestamp" => {
+                    let end = after_colon
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(after_colon.len());
+                    timestamp = Some(after_colon[..end].parse().ok()?);
+                    &after_colon[end..]
+                }
+                "command_line" | "schema" => {
+                    let (value, after_value) = read_json_string(after_colon)?;
+                    if key == "schema" {
+                        schema = Some(value);
+                    } else {
+                        command_line = Some(value);
+                    }
+                    after_value
+                }
+                _ => return None,
+            };
+            let after_value = after_value.trim_start();
+            if let Some(next) = after_value.strip_prefix(',') {
+                rest = next;
+            } else if after_value.strip_prefix('}')?.trim().is_empty() {
+                break;
+            } else {
+                return None;
+            }
+        }
+        Some(Invocation {
+            timestamp: timestamp?,
+            command_line: command_line?,
+            schema: schema?,
+        })
+    }
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn read_json_string(text: &str) -> Option<(String, &str)> {
+    let mut chars = text.strip_prefix('"')?.char_indices();
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((value, &text[i + 2..])),
+            '\\' => match chars.next()?.1 {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                'u' => {
+                    let hex: String = (0..4).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                    value.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                c => value.push(c),
+            },
+            c => value.push(c),
+        }
+    }
+    None
+}
+
+/// Somewhere to keep recorded invocations. Recording is best effort: a
+/// sink must never make an otherwise successful parse fail.
+pub trait ReplaySink {
+    fn record(&mut self, invocation: &Invocation);
+}
+
+impl ReplaySink for Vec<Invocation> {
+    fn record(&mut self, invocation: &Invocation) {
+        self.push(invocation.clone());
+    }
+}
+
+/// Appends invocations to a JSONL file, creating it when needed.
+pub struct JsonlFile {
+    path: PathBuf,
+}
+
+impl JsonlFile {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Every invocation recorded so far, oldest first. Lines that can't be
+    /// read back are skipped.
+    pub fn history(&self) -> io::Result<Vec<Invocation>> {
+        let contents = match fs::read_to_string(&self.path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            contents => contents?,
+        };
+        Ok(contents.lines().filter_map(Invocation::from_json_line).collect())
+    }
+
+    pub fn last(&self) -> io::Result<Option<Invocation>> {
+        Ok(self.history()?.pop())
+    }
+}
+
+impl ReplaySink for JsonlFile {
+    fn record(&mut self, invocation: &Invocation) {
+        let file = OpenOptions::new().create(true).append(true).open(&self.path);
+        if let Ok(mut file) = file {
+            let _ = writeln!(file, "{}", invocation.to_json_line());
+        }
+    }
+}
+
+/// Parses with a schema and records every successful invocation.
+pub struct Recorder<'s, S: ReplaySink> {
+    schema: &'s Schema,
+    sink: S,
+}
+
+impl<'s, S: ReplaySink> Recorder<'s, S> {
+    pub fn new(schema: &'s Schema, sink: S) -> Self {
+        Self { schema, sink }
+    }
+
+    pub fn parse(&mut self, input: &str) -> Result<ParsedArgs, ParseErr> {
+        let parsed = self.schema.parse(input)?;
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_millis() as u64)
+            .unwrap_or(0);
+        self.sink.record(&Invocation {
+            timestamp,
+            command_line: self.schema.canonical_command_line(input),
+            schema: self.schema.fingerprint(),
+        });
+        Ok(parsed)
+    }
+
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    pub fn into_sink(self) -> S {
+        self.sink
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Classification;
+
+    #[test]
+    fn records_canonical_command_line_and_fingerprint() {
+        let schema = Schema::new("p#,l").unwrap().case_insensitive();
+        let mut recorder = Recorder::new(&schema, vec![]);
+        recorder.parse("-P   8080 -l").unwrap();
+        assert!(recorder.parse("-x").is_err());
+        let recorded = recorder.into_sink();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].command_line, "-p 8080 -l");
+        assert_eq!(recorded[0].schema, schema.fingerprint());
+        assert!(recorded[0].timestamp > 0);
+    }
+
+    #[test]
+    fn recorded_command_lines_keep_quoting() {
+        let schema = Schema::new("d*,n#").unwrap();
+        let mut recorder = Recorder::new(&schema, vec![]);
+        recorder.parse(r#"-d 'my "logs"' -n '-1'"#).unwrap();
+        let recorded = &recorder.sink()[0].command_line;
+        assert_eq!(recorded, r#"-d "my \"logs\"" -n "-1""#);
+        let replayed = schema.parse(recorded).unwrap();
+        assert_eq!(replayed.get("d").unwrap().get().unwrap(), r#"my "logs""#);
+    }
+
+    #[test]
+    fn masks_values_that_are_not_public() {
+        let schema = Schema::new("t*,u*,p#")
+            .unwrap()
+            .classify("t", Classification::Secret)
+            .classify("u", Classification::Internal);
+        let mut recorder = Recorder::new(&schema, vec![]);
+        recorder.parse("-t hunter2 -u alice -p 1").unwrap();
+        assert_eq!(recorder.sink()[0].command_line, "-t *** -u *** -p 1");
+    }
+
+    #[test]
+    fn json_line_round_trip() {
+        let invocation = Invocation {
+            timestamp: 42,
+            command_line: "-d \"quoted\\path\"\n".to_string(),
+            schema: "0123456789abcdef".to_string(),
+        };
+        let line = invocation.to_json_line();
+        assert_eq!(
+            line,
+            r#"{"timestamp":42,"command_line":"-d \"quoted\\path\"\n","schema":"0123456789abcdef"}"#
+        );
+        assert_eq!(Invocation::from_json_line(&line), Some(invocation));
+        assert_eq!(Invocation::from_json_line("{\"timestamp\":1}"), None);
+        assert_eq!(Invocation::from_json_line("not json"), None);
+    }
+
+    #[test]
+    fn jsonl_file_appends_and_reads_history() {
+        let path = std::env::temp_dir().join(format!("args-replay-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let schema = Schema::new("p#").unwrap();
+        let mut recorder = Recorder::new(&schema, JsonlFile::new(&path));
+        assert_eq!(recorder.sink().last().unwrap(), None);
+        recorder.parse("-p 1").unwrap();
+        recorder.parse("-p 2").unwrap();
+        let history = recorder.sink().history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(recorder.sink().last().unwrap().unwrap().command_line, "-p 2");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+use crate::{Input, ParseErr, ParsedArgs, Schema};
+#[cfg(feature = "dotenv")]
+use std::{collections::HashMap, io};
+#[cfg(any(feature = "dotenv", feature = "toml"))]
+use std::{fs, path::Path};
+
+/// Where the final value of an argument came from.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Source {
+    /// Given in the input that was parsed.
+    CommandLine,
+    /// Read from an environment variable, see [`Schema::env`].
+    Env,
+    /// Read from a `.env` file, see `Resolver::dotenv_file`. Only given with
+    /// the `dotenv` feature, but always declared so matches don't depend on
+    /// the features enabled.
+    DotEnv,
+    /// Read from a config file, see `Resolver::config_file`. Only given
+    /// with the `toml` feature.
+    Config,
+    /// The argument's default, from the schema or the [`Resolver`].
+    Default,
+    /// Typed in when asked for, see [`Schema::prompt_missing`].
+    Prompt,
+    /// Part of the preset chosen, see [`Schema::preset_arg`].
+    Preset,
+}
+
+/// Resolves argument values from several layers. Later layers only fill in
+/// arguments that an earlier one left unset:
+///
+/// 1. the command line
+/// 2. environment variables
+/// 3. a `.env` file (with the `dotenv` feature)
+/// 4. a config file (with the `toml` feature)
+/// 5. the preset chosen, see [`Schema::preset_arg`]
+/// 6. built-in defaults, from the resolver and then from the schema
+pub struct Resolver {
+    schema: Schema,
+    env_vars: Vec<(String, String)>,
+    defaults: Vec<(String, String)>,
+    #[cfg(feature = "dotenv")]
+    dotenv: HashMap<String, String>,
+    #[cfg(feature = "toml")]
+    config: Vec<(String, Vec<String>)>,
+}
+
+impl Resolver {
+    pub fn new(schema: Schema) -> Self {
+        Self {
+            schema,
+            env_vars: vec![],
+            defaults: vec![],
+            #[cfg(feature = "dotenv")]
+            dotenv: HashMap::new(),
+            #[cfg(feature = "toml")]
+            config: vec![],
+        }
+    }
+
+    /// Falls back to the environment variable `var` when `arg` is absent.
+    pub fn env(mut self, arg: &str, var: &str) -> Self {
+        self.env_vars.push((arg.to_string(), var.to_string()));
+        self
+    }
+
+    /// Uses `value` for `arg` when no other layer provides one. Takes
+    /// precedence over the default declared in the schema.
+    pub fn default(mut self, arg: &str, value: &str) -> Self {
+        self.defaults.push((arg.to_string(), value.to_string()));
+        self
+    }
+
+    /// Reads `KEY=VALUE` lines from a `.env` file. Its entries are looked up
+    /// under the variable names of [`Resolver::env`] and [`Schema::env`],
+    /// but only when the real environment doesn't define them.
+    #[cfg(feature = "dotenv")]
+    pub fn dotenv_file<P: AsRef<Path>>(self, path: P) -> io::Result<Self> {
+        Ok(self.dotenv_str(&fs::read_to_string(path)?))
+    }
+
+    /// Like [`Resolver::dotenv_file`], for contents already in memory.
+    #[cfg(feature = "dotenv")]
+    pub fn dotenv_str(mut self, contents: &str) -> Self {
+        self.dotenv.extend(parse_dotenv(contents));
+        self
+    }
+
+    /// Reads argument values from a TOML config file, see
+    /// [`Resolver::config_toml`]. Fails with [`ParseErr::ConfigFile`] if it
+    /// can't be read.
+    #[cfg(feature = "toml")]
+    pub fn config_file<P: AsRef<Path>>(self, path: P) -> Result<Self, ParseErr> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path).map_err(|err| {
+            ParseErr::ConfigFile { path: path.display().to_string(), reason: err.to_string() }
+        })?;
+        self.config_toml(&contents)
+    }
+
+    /// Reads argument values from `name = value` lines of TOML, where a value
+    /// is a string, a number, a boolean, or an array of them for a list.
+    /// Names outside the schema, tables and other malformed lines fail with
+    /// [`ParseErr::InvalidConfigFile`].
+    #[cfg(feature = "toml")]
+    pub fn config_toml(mut self, contents: &str) -> Result<Self, ParseErr> {
+        for (i, raw) in contents.lines().enumerate() {
+            let line = i + 1;
+            let text = raw.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            if text.starts_with('[') {
+                return Err(invalid_config(line, "tables are not supported"));
+            }
+            let (name, value) = text
+                .split_once('=')
+                .ok_or_else(|| invalid_config(line, "expected name = value"))?;
+            let name = name.trim();
+            let name = match name.strip_prefix('"').and_then(|name| name.strip_suffix('"')) {
+                Some(quoted) => quoted,
+                None => name,
+            };
+            if self.schema.spec(name).is_none() {
+                let message = format!("unknown argument '{}'", name);
+                return Err(invalid_config(line, &message));
+            }
+            let values = config_values(value.trim(), line).map_err(|err| match err {
+                ParseErr::InvalidSchemaFile { line, message } => {
+                    ParseErr::InvalidConfigFile { line, message }
+                }
+                err => err,
+            })?;
+            self.config.push((name.to_string(), values));
+        }
+        Ok(self)
+    }
+
+    pub fn resolve(&self, input: &str) -> Result<ParsedArgs, ParseErr> {
+        let mut parsed = self.schema.parse_input(Input::typed(input))?;
+        self.schema.apply_env(&mut parsed)?;
+        for (name, var) in &self.env_vars {
+            if let Ok(value) = std::env::var(var) {
+                self.schema.fill(&mut parsed, name, value, Source::Env)?;
+            }
+        }
+        #[cfg(feature = "dotenv")]
+        {
+            let declared = self.schema.specs.iter();
+            let declared = declared.filter_map(|spec| Some((&spec.name, spec.env.as_ref()?)));
+            for (name, var) in self.env_vars.iter().map(|(name, var)| (name, var)).chain(declared) {
+                if let Some(value) = self.dotenv.get(var) {
+                    self.schema.fill(&mut parsed, name, value.clone(), Source::DotEnv)?;
+                }
+            }
+        }
+        #[cfg(feature = "toml")]
+        for (name, values) in &self.config {
+            self.schema.fill_values(&mut parsed, name, values.clone(), Source::Config)?;
+        }
+        let chooses_preset = |name: &String| self.schema.preset_arg.as_ref() == Some(name);
+        if let Some((name, value)) = self.defaults.iter().find(|(name, _)| chooses_preset(name)) {
+            self.schema.fill(&mut parsed, name, value.clone(), Source::Default)?;
+        }
+        self.schema.apply_preset(&mut parsed)?;
+        for (name, value) in &self.defaults {
+            self.schema.fill(&mut parsed, name, value.clone(), Source::Default)?;
+        }
+        self.schema.apply_defaults(&mut parsed)?;
+        self.schema.check_parsed(&parsed)?;
+        Ok(parsed)
+    }
+}
+
+#[cfg(feature = "toml")]
+fn invalid_config(line: usize, message: &str) -> ParseErr {
+    ParseErr::InvalidConfigFile { line, message: message.to_string() }
+}
+
+/// The values of a config line: one for a scalar, any number for an array.
+#[cfg(feature = "toml")]
+fn config_values(text: &str, line: usize) -> Result<Vec<String>, ParseErr> {
+    use crate::schema_file::{expect_end, quoted};
+    let scalar = |text: &str| -> Result<(String, usize), ParseErr> {
+        if text.starts_with('"') || text.starts_with('\'') {
+            let (value, rest) = quoted(text, line)?;
+            return Ok((value, text.len() - rest.len()));
+        }
+        let end = text.find([',', ']', '#']).unwrap_or(text.len());
+        let bare = text[..end].trim();
+        let digits = bare.trim_start_matches(['+', '-']).replace('_', "");
+        let number = !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit());
+        if bare != "true" && bare != "false" && !number {
+            return Err(invalid_config(line, "unsupported value"));
+        }
+        Ok((bare.trim_start_matches('+').replace('_', ""), end))
+    };
+    let mut rest = match text.strip_prefix('[') {
+        Some(rest) => rest.trim_start(),
+        None => {
+            let (value, end) = scalar(text)?;
+            expect_end(&text[end..], line)?;
+            return Ok(vec![value]);
+        }
+    };
+    let mut values = vec![];
+    loop {
+        if let Some(after) = rest.strip_prefix(']') {
+            expect_end(after, line)?;
+            return Ok(values);
+        }
+        if rest.is_empty() || rest.starts_with('#') {
+            return Err(invalid_config(line, "unterminated array"));
+        }
+        let (value, end) = scalar(rest)?;
+        values.push(value);
+        rest = rest[end..].trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+}
+
+#[cfg(feature = "dotenv")]
+fn parse_dotenv(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let quoted = ['"', '\''].iter().find_map(|&quote| {
+                let rest = value.strip_prefix(quote)?;
+                let end = rest.find(quote)?;
+                let after = rest[end + 1..].trim_start();
+                Some(&rest[..end]).filter(|_| after.is_empty() || after.starts_with('#'))
+            });
+            let unquoted = quoted.unwrap_or_else(|| match value.find(" #") {
+                Some(comment) => value[..comment].trim_end(),
+                None => value,
+            });
+            Some((key.trim().to_string(), unquoted.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command_line_wins_over_env_and_default() {
+        std::env::set_var("RESOLVER_TEST_PORT_1", "9090");
+        let resolver = Resolver::new(Schema::new("p#").unwrap())
+            .env("p", "RESOLVER_TEST_PORT_1")
+            .default("p", "80");
+        let args = resolver.resolve("-p 8080").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert_eq!(args.source("p"), Some(Source::CommandLine));
+    }
+
+    #[test]
+    fn env_wins_over_default() {
+        std::env::set_var("RESOLVER_TEST_PORT_2", "9090");
+        let resolver = Resolver::new(Schema::new("p#").unwrap())
+            .env("p", "RESOLVER_TEST_PORT_2")
+            .default("p", "80");
+        let args = resolver.resolve("").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 9090);
+        assert_eq!(args.source("p"), Some(Source::Env));
+    }
+
+    #[test]
+    fn default_applies_last() {
+        let resolver = Resolver::new(Schema::new("p#,d*").unwrap())
+            .env("p", "RESOLVER_TEST_UNSET")
+            .default("p", "80");
+        let args = resolver.resolve("").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 80);
+        assert_eq!(args.source("p"), Some(Source::Default));
+        assert_eq!(args.source("d"), None);
+    }
+
+    #[test]
+    fn presets_sit_between_the_layers_and_the_defaults() {
+        std::env::set_var("RESOLVER_TEST_PROFILE", "release");
+        let schema = Schema::builder()
+            .string("profile")
+            .number("j")
+            .string("o")
+            .build()
+            .unwrap()
+            .preset("release", &[("j", "8"), ("o", "3")])
+            .unwrap()
+            .preset_arg("profile")
+            .unwrap();
+        let resolver = Resolver::new(schema.clone())
+            .env("profile", "RESOLVER_TEST_PROFILE")
+            .default("j", "1");
+        let args = resolver.resolve("-o 2").unwrap();
+        assert_eq!((args.get_number("j"), args.source("j")), (Some(8), Some(Source::Preset)));
+        assert_eq!(args.source("o"), Some(Source::CommandLine));
+        let resolver = Resolver::new(schema).default("profile", "release");
+        let args = resolver.resolve("").unwrap();
+        assert_eq!(args.get_str("o"), Some("3".to_string()));
+        assert_eq!(args.source("profile"), Some(Source::Default));
+    }
+
+    #[test]
+    fn layer_for_unknown_arg_is_an_error() {
+        let resolver = Resolver::new(Schema::new("p#").unwrap()).default("x", "1");
+        assert_eq!(
+            resolver.resolve("").unwrap_err(),
+            ParseErr::unknown_arg("x")
+        );
+    }
+
+    #[test]
+    fn invalid_layer_value_is_reported() {
+        let resolver = Resolver::new(Schema::new("p#").unwrap()).default("p", "http");
+        assert_eq!(
+            resolver.resolve("").unwrap_err(),
+            ParseErr::NumberFormatErr { arg: "p".into(), value: "http".into(), span: None }
+        );
+    }
+
+    #[cfg(feature = "dotenv")]
+    mod dotenv {
+        use super::*;
+
+        #[test]
+        fn parses_dotenv_lines() {
+            let vars = parse_dotenv("# comment\nexport A=1\nB = \"two words\"\n\nC='3'\nnot a pair");
+            assert_eq!(vars.len(), 3);
+            assert_eq!(vars["A"], "1");
+            assert_eq!(vars["B"], "two words");
+            assert_eq!(vars["C"], "3");
+            let vars = parse_dotenv("export X=\"a b\" # c\nY='#1' #two\nZ=plain # note\nW=a#b");
+            assert_eq!((&*vars["X"], &*vars["Y"]), ("a b", "#1"));
+            assert_eq!((&*vars["Z"], &*vars["W"]), ("plain", "a#b"));
+        }
+
+        #[test]
+        fn dotenv_sits_between_env_and_defaults() {
+            std::env::set_var("RESOLVER_TEST_DIR", "/from/env");
+            let resolver = Resolver::new(Schema::new("p#,d*").unwrap())
+                .env("p", "RESOLVER_TEST_DOTENV_PORT")
+                .env("d", "RESOLVER_TEST_DIR")
+                .dotenv_str("RESOLVER_TEST_DOTENV_PORT=9000\nRESOLVER_TEST_DIR=/from/dotenv")
+                .default("p", "80");
+            let args = resolver.resolve("").unwrap();
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 9000);
+            assert_eq!(args.source("p"), Some(Source::DotEnv));
+            assert_eq!(args.get("d").unwrap().get().unwrap(), "/from/env");
+            assert_eq!(args.source("d"), Some(Source::Env));
+        }
+
+        #[test]
+        fn dotenv_reads_vars_declared_in_the_schema() {
+            let schema = Schema::new("p#").unwrap().env("p", "RESOLVER_TEST_SCHEMA_PORT");
+            let resolver = Resolver::new(schema).dotenv_str("RESOLVER_TEST_SCHEMA_PORT=77");
+            let args = resolver.resolve("").unwrap();
+            assert_eq!(args.get_number("p"), Some(77));
+            assert_eq!(args.source("p"), Some(Source::DotEnv));
+            std::env::set_var("RESOLVER_TEST_SCHEMA_PORT", "78");
+            assert_eq!(resolver.resolve("").unwrap().source("p"), Some(Source::Env));
+            std::env::remove_var("RESOLVER_TEST_SCHEMA_PORT");
+        }
+    }
+
+    #[cfg(feature = "toml")]
+    mod config {
+        use super::*;
+
+        #[test]
+        fn config_sits_beneath_command_line_and_env() {
+            std::env::set_var("RESOLVER_TEST_CONFIG_DIR", "/from/env");
+            let schema = Schema::new("p#,d*,l,s[*],n#").unwrap();
+            let config = "# settings\n\
+                          p = 9000\n\
+                          d = \"/from/config\"  # overridden\n\
+                          l = true\n\
+                          s = [\"a\", 'b c']\n";
+            let resolver = Resolver::new(schema)
+                .env("d", "RESOLVER_TEST_CONFIG_DIR")
+                .config_toml(config)
+                .unwrap()
+                .default("n", "1");
+            let args = resolver.resolve("-p 1").unwrap();
+            assert_eq!(args.get_number("p"), Some(1));
+            assert_eq!(args.source("p"), Some(Source::CommandLine));
+            assert_eq!(args.get_str("d"), Some("/from/env".to_string()));
+            assert_eq!(args.get_bool("l"), Some(true));
+            assert_eq!(args.source("l"), Some(Source::Config));
+            assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a", "b c"]);
+            assert_eq!(args.source("n"), Some(Source::Default));
+        }
+
+        #[test]
+        fn malformed_config() {
+            let resolver = || Resolver::new(Schema::new("p#,s[*]").unwrap());
+            let err = |contents: &str| resolver().config_toml(contents).err().unwrap();
+            let invalid = |line, message: &str| ParseErr::InvalidConfigFile {
+                line,
+                message: message.into(),
+            };
+            assert_eq!(err("\nx = 1"), invalid(2, "unknown argument 'x'"));
+            assert_eq!(err("[args]"), invalid(1, "tables are not supported"));
+            assert_eq!(err("p = yes"), invalid(1, "unsupported value"));
+            assert_eq!(err("s = [\"a\""), invalid(1, "unterminated array"));
+            assert_eq!(err("s = \"a"), invalid(1, "unterminated string"));
+            let missing = resolver().config_file("/nonexistent/args.toml").err().unwrap();
+            let names_the_file = |path: &str| path.ends_with("args.toml");
+            assert!(matches!(missing, ParseErr::ConfigFile { path, .. } if names_the_file(&path)));
+        }
+    }
+}
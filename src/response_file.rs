@@ -0,0 +1,146 @@
+//! `@file` arguments that stand for the contents of a file, for command
+//! lines too long to type or for the OS to pass along.
+
+use crate::{split_words, Input, Lexer, ParseErr, Schema};
+use std::fs;
+
+/// How many response files may refer to each other in a chain before
+/// expansion gives up, which also catches a file that names itself.
+const MAX_DEPTH: usize = 8;
+
+impl Schema {
+    /// Reads an unquoted word such as `@build-args.txt` as the words in
+    /// that file, split the same way as the input. Files may name further
+    /// response files. A file that can't be read, or a chain of more than
+    /// eight files, fails with [`ParseErr::ResponseFile`]. Everything after
+    /// a bare `--` is left as it is.
+    pub fn response_files(mut self) -> Self {
+        self.response_files = true;
+        self
+    }
+
+    /// `input` with every response file replaced by its contents, or
+    /// `None` if it can't name one.
+    pub(crate) fn expand_response_files(&self, input: Input) -> Result<Option<String>, ParseErr> {
+        if !self.response_files || !input.text.contains('@') {
+            return Ok(None);
+        }
+        let mut expanded = String::new();
+        expand(self, input.lexer(), 0, &mut expanded)?;
+        Ok(Some(expanded))
+    }
+}
+
+/// Appends `words` to `expanded`, those of each response file in place of
+/// its name, failing as soon as a file takes `expanded` over the
+/// [`Limits`](crate::Limits) on input length.
+fn expand(
+    schema: &Schema,
+    mut words: Lexer,
+    depth: usize,
+    expanded: &mut String,
+) -> Result<(), ParseErr> {
+    let push = |expanded: &mut String, word: &str| {
+        if !expanded.is_empty() {
+            expanded.push(' ');
+        }
+        expanded.push_str(word);
+    };
+    while let Some(word) = words.next() {
+        if word.raw == "--" {
+            push(expanded, word.raw);
+            words.by_ref().for_each(|word| push(expanded, word.raw));
+            break;
+        }
+        let path = match word.text.strip_prefix('@') {
+            Some(path) if word.raw.starts_with('@') && !path.is_empty() => path,
+            _ => {
+                push(expanded, word.raw);
+                continue;
+            }
+        };
+        if depth == MAX_DEPTH {
+            let message = "too many nested response files".to_string();
+            return Err(ParseErr::ResponseFile { path: path.to_string(), reason: message });
+        }
+        schema.check_response_file_depth(depth + 1)?;
+        let contents = fs::read_to_string(path)
+            .map_err(|err| ParseErr::ResponseFile {
+                path: path.to_string(),
+                reason: err.to_string(),
+            })?;
+        schema.check_input_len(expanded.len() + 1 + contents.len())?;
+        expand(schema, split_words(&contents), depth + 1, expanded)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Limits;
+    use std::path::PathBuf;
+
+    fn write(name: &str, contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("args-{}-{}", std::process::id(), name));
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn expands_files_recursively() {
+        let inner = write("inner.txt", "-d '/var/my logs'\n");
+        let outer = write("outer.txt", &format!("-p 8080\n@{}\n", inner.display()));
+        let schema = Schema::new("l,p#,d*").unwrap().response_files();
+        let args = schema.parse(&format!("@{} -l", outer.display())).unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/my logs");
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        fs::remove_file(inner).unwrap();
+        fs::remove_file(outer).unwrap();
+    }
+
+    #[test]
+    fn gives_up_on_cycles_and_missing_files() {
+        let path = std::env::temp_dir().join(format!("args-{}-cycle.txt", std::process::id()));
+        fs::write(&path, format!("@{}", path.display())).unwrap();
+        let schema = Schema::new("l,d*").unwrap().response_files();
+        let err = schema.parse(&format!("@{}", path.display())).unwrap_err();
+        assert!(matches!(err, ParseErr::ResponseFile { reason, .. } if reason.contains("nested")));
+        fs::remove_file(&path).unwrap();
+        let err = schema.parse(&format!("@{}", path.display())).unwrap_err();
+        let shown = path.display().to_string();
+        assert!(matches!(err, ParseErr::ResponseFile { path, .. } if path == shown));
+    }
+
+    #[test]
+    fn limits_apply_while_expanding() {
+        let inner = write("limited-inner.txt", &format!("-d {}", "x".repeat(100)));
+        let outer = write("limited-outer.txt", &format!("-l @{}", inner.display()));
+        let schema = |limits| Schema::new("l,d*").unwrap().response_files().limits(limits);
+        let depth = |depth| Limits { max_response_file_depth: Some(depth), ..Limits::default() };
+        let input = format!("@{}", outer.display());
+        assert!(schema(depth(2)).parse(&input).is_ok());
+        let err = schema(depth(1)).parse(&input).unwrap_err();
+        assert_eq!(err, ParseErr::LimitExceeded {
+            what: "nested response files".to_string(),
+            limit: 1,
+        });
+        let limit = input.len() + 10;
+        let len = Limits { max_input_len: Some(limit), ..Limits::default() };
+        let err = schema(len).parse(&input).unwrap_err();
+        assert_eq!(err, ParseErr::LimitExceeded { what: "bytes of input".to_string(), limit });
+        fs::remove_file(inner).unwrap();
+        fs::remove_file(outer).unwrap();
+    }
+
+    #[test]
+    fn only_unquoted_words_outside_trailing_are_expanded() {
+        let schema = Schema::new("d*").unwrap().response_files();
+        let args = schema.parse("-d '@me' -- @rest").unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "@me");
+        assert_eq!(args.trailing(), &["@rest"]);
+        let plain = Schema::new("d*").unwrap().parse("-d @me").unwrap();
+        assert_eq!(plain.get("d").unwrap().get().unwrap(), "@me");
+    }
+}
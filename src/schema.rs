@@ -0,0 +1,3580 @@
+use crate::{
+    ArgValue, Args, FromArgs, Limits, NoMatches, Normalization, ParseErr, ParseWarning, ParsedArgs,
+    Input, Presence, RawToken, Source, Token, TokensIterator, ValueHint, WarningSink,
+};
+use crate::hash::{name_map, NameMap};
+use crate::symbols::{Symbol, Symbols};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The type of value an argument takes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArgKind {
+    Bool,
+    Str,
+    /// A string whose value may be left out, e.g. `--color` as well as
+    /// `--color never`.
+    OptStr,
+    Number,
+    StrArray,
+    NumberArray,
+}
+
+impl ArgKind {
+    /// Whether a value given again replaces the one before.
+    fn is_scalar(self) -> bool {
+        matches!(self, ArgKind::Str | ArgKind::OptStr | ArgKind::Number)
+    }
+
+    /// Whether values are joined into one string.
+    pub(crate) fn is_string(self) -> bool {
+        matches!(self, ArgKind::Str | ArgKind::OptStr)
+    }
+
+    pub(crate) fn from_marker(marker: &str) -> Result<Self, ParseErr> {
+        match marker {
+            "" => Ok(ArgKind::Bool),
+            "*" => Ok(ArgKind::Str),
+            "*?" => Ok(ArgKind::OptStr),
+            "#" => Ok(ArgKind::Number),
+            "[*]" => Ok(ArgKind::StrArray),
+            "[#]" => Ok(ArgKind::NumberArray),
+            t => Err(ParseErr::UnsupportedArgType(t.to_string())),
+        }
+    }
+
+    /// The spelled-out type name, as schema files write it.
+    pub(crate) fn type_name(self) -> &'static str {
+        match self {
+            ArgKind::Bool => "bool",
+            ArgKind::Str => "string",
+            ArgKind::OptStr => "string?",
+            ArgKind::Number => "number",
+            ArgKind::StrArray => "string[]",
+            ArgKind::NumberArray => "number[]",
+        }
+    }
+
+    /// The spelled-out type names used by schema files.
+    #[cfg(any(feature = "toml", feature = "yaml"))]
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bool" => Some(ArgKind::Bool),
+            "string" => Some(ArgKind::Str),
+            "string?" => Some(ArgKind::OptStr),
+            "number" => Some(ArgKind::Number),
+            "string[]" => Some(ArgKind::StrArray),
+            "number[]" => Some(ArgKind::NumberArray),
+            _ => None,
+        }
+    }
+
+    fn marker(self) -> &'static str {
+        match self {
+            ArgKind::Bool => "",
+            ArgKind::Str => "*",
+            ArgKind::OptStr => "*?",
+            ArgKind::Number => "#",
+            ArgKind::StrArray => "[*]",
+            ArgKind::NumberArray => "[#]",
+        }
+    }
+
+    fn value_name(self) -> &'static str {
+        match self {
+            ArgKind::Number | ArgKind::NumberArray => "<number>",
+            _ => "<string>",
+        }
+    }
+
+    /// How `value` follows the flag in usage.
+    fn metavar(self, value: &str) -> String {
+        match self {
+            ArgKind::Bool => String::new(),
+            ArgKind::Str | ArgKind::Number => format!(" {}", value),
+            ArgKind::OptStr => format!(" [{}]", value),
+            ArgKind::StrArray | ArgKind::NumberArray => format!(" {}...", value),
+        }
+    }
+
+    pub(crate) fn new_arg(self) -> ArgValue {
+        match self {
+            ArgKind::Bool => ArgValue::Bool(false),
+            ArgKind::Str => ArgValue::Str(None),
+            ArgKind::OptStr => ArgValue::OptStr {
+                implicit: String::new(),
+                presence: Presence::Absent,
+            },
+            ArgKind::Number => ArgValue::Number(None),
+            ArgKind::StrArray => ArgValue::StrArray(vec![]),
+            ArgKind::NumberArray => ArgValue::NumberArray(vec![]),
+        }
+    }
+}
+
+/// How sensitive an argument's value is. On top of [`Classification::Public`]
+/// values, `Debug` output shows internal ones, while recorded invocations
+/// only show public ones; secret values are printed as `***` either way.
+/// Reading a value directly, e.g. with [`ParsedArgs::get`], is unaffected.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Classification {
+    Public,
+    Internal,
+    Secret,
+}
+
+pub(crate) const MASK: &str = "***";
+
+/// What repeating an argument does to the values given so far.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Accumulation {
+    /// Lists grow with every occurrence, e.g. `-i a -i b` gives `a,b`.
+    /// Scalars keep the last value.
+    Append,
+    /// Every occurrence starts over, so only the last one counts.
+    Replace,
+    /// Only the first occurrence counts, and later ones are ignored.
+    Keep,
+    /// A second occurrence fails with [`ParseErr::TooManyOccurrences`].
+    Error,
+    /// Strings join every occurrence with their [`Schema::separator`], a
+    /// space unless set otherwise, e.g. `-m fix -m it` gives `fix it`.
+    /// Only strings concatenate: parsing with it set for another type
+    /// fails with [`ParseErr::InvalidSchema`].
+    Concat,
+}
+
+/// What the path a string argument names must be on disk, see
+/// [`SchemaBuilder::path_check`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PathCheck {
+    /// Something exists at the path.
+    Exists,
+    /// The path is an existing file.
+    File,
+    /// The path is an existing directory.
+    Dir,
+    /// The directory the path is in exists, as for a file about to be
+    /// written.
+    ParentExists,
+    /// Like [`PathCheck::ParentExists`], creating the directory and those
+    /// above it when they are missing.
+    CreateParent,
+}
+
+/// What a bool argument that wasn't given reads as.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AbsentBool {
+    /// `false`, the same as when given as `false`.
+    False,
+    /// No value, so only a flag given as `false` reads as `false`, and
+    /// [`Args::get`] tells a flag left out from one turned off.
+    Unset,
+}
+
+type ValidatorFn = dyn Fn(&str) -> Result<(), String> + Send + Sync;
+
+/// A check run against every value given for an argument.
+#[derive(Clone)]
+pub(crate) struct Validator(pub(crate) Arc<ValidatorFn>);
+
+impl fmt::Debug for Validator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Validator")
+    }
+}
+
+type TransformFn = dyn Fn(&str) -> String + Send + Sync;
+
+/// A mapping applied to every value of an argument once it is valid.
+#[derive(Clone)]
+pub(crate) struct Transform(pub(crate) Arc<TransformFn>);
+
+impl fmt::Debug for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Transform")
+    }
+}
+
+/// One argument declared by a [`Schema`], as listed by [`Schema::args`].
+#[derive(Clone, Debug)]
+pub struct ArgSpec {
+    pub(crate) name: String,
+    pub(crate) kind: ArgKind,
+    pub(crate) required: bool,
+    pub(crate) positional: bool,
+    pub(crate) max_occurrences: Option<usize>,
+    pub(crate) accumulation: Accumulation,
+    pub(crate) delimiter: Option<char>,
+    /// What the values of a scalar string are joined with.
+    pub(crate) separator: Option<String>,
+    pub(crate) classification: Classification,
+    pub(crate) absent: AbsentBool,
+    pub(crate) implicit: Option<String>,
+    pub(crate) default: Option<String>,
+    /// Defaults taking over from `default` while another argument has a
+    /// given value: (that argument, its value, the default).
+    pub(crate) defaults_if: Vec<(String, String, String)>,
+    pub(crate) description: Option<String>,
+    pub(crate) validators: Vec<Validator>,
+    pub(crate) transforms: Vec<Transform>,
+    /// The marker and type name of a type registered with
+    /// [`types::register`](crate::types::register).
+    pub(crate) custom: Option<(String, String)>,
+    pub(crate) metavar: Option<String>,
+    pub(crate) choices: Vec<String>,
+    /// Whether values match `choices` regardless of case, and are stored
+    /// spelled as the choice.
+    pub(crate) fold_choices: bool,
+    pub(crate) hint: Option<ValueHint>,
+    pub(crate) path_check: Option<PathCheck>,
+    pub(crate) glob: Option<NoMatches>,
+    pub(crate) env: Option<String>,
+}
+
+impl ArgSpec {
+    pub(crate) fn new(name: &str, kind: ArgKind) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            required: false,
+            positional: false,
+            max_occurrences: match kind {
+                ArgKind::StrArray | ArgKind::NumberArray => None,
+                _ => Some(1),
+            },
+            accumulation: Accumulation::Append,
+            delimiter: None,
+            separator: None,
+            classification: Classification::Public,
+            absent: AbsentBool::False,
+            implicit: None,
+            default: None,
+            defaults_if: vec![],
+            description: None,
+            validators: vec![],
+            transforms: vec![],
+            custom: None,
+            metavar: None,
+            choices: vec![],
+            fold_choices: false,
+            hint: None,
+            path_check: None,
+            glob: None,
+            env: None,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> ArgKind {
+        self.kind
+    }
+
+    /// Whether parsing fails with [`ParseErr::MissingArg`] when no value is
+    /// given or defaulted.
+    pub fn is_required(&self) -> bool {
+        self.required
+    }
+
+    /// Whether the argument is bound by position instead of by a flag.
+    pub fn is_positional(&self) -> bool {
+        self.positional
+    }
+
+    /// How many times the argument may be given, or `None` for no limit.
+    pub fn max_occurrences(&self) -> Option<usize> {
+        self.max_occurrences
+    }
+
+    pub fn accumulation(&self) -> Accumulation {
+        self.accumulation
+    }
+
+    /// The character that splits a single value into several list
+    /// elements, if any.
+    pub fn delimiter(&self) -> Option<char> {
+        self.delimiter
+    }
+
+    /// What several values of a string are joined with, if not a space.
+    pub fn separator(&self) -> Option<&str> {
+        self.separator.as_deref()
+    }
+
+    pub fn classification(&self) -> Classification {
+        self.classification
+    }
+
+    pub fn absent_bool(&self) -> AbsentBool {
+        self.absent
+    }
+
+    /// The value an optional-value argument takes when given bare.
+    pub fn implicit_value(&self) -> Option<&str> {
+        self.implicit.as_deref()
+    }
+
+    /// How the value follows the flag in usage and help, e.g. ` <number>`.
+    pub(crate) fn value_metavar(&self) -> String {
+        let value = match (&self.metavar, &self.custom) {
+            (Some(metavar), _) => metavar.clone(),
+            (None, Some((_, type_name))) => format!("<{}>", type_name),
+            (None, None) => self.kind.value_name().to_string(),
+        };
+        self.kind.metavar(&value)
+    }
+
+    pub(crate) fn new_arg(&self) -> ArgValue {
+        match (self.kind, &self.implicit) {
+            (ArgKind::OptStr, Some(implicit)) => ArgValue::OptStr {
+                implicit: implicit.clone(),
+                presence: Presence::Absent,
+            },
+            (ArgKind::Bool, _) if self.absent == AbsentBool::Unset => ArgValue::OptBool(None),
+            (kind, _) => kind.new_arg(),
+        }
+    }
+
+    pub fn default_value(&self) -> Option<&str> {
+        self.default.as_deref()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+
+    /// The name standing for the value in usage, such as `PORT`, if one
+    /// was set rather than the one of the type.
+    pub fn metavar(&self) -> Option<&str> {
+        self.metavar.as_deref()
+    }
+
+    /// The only values the argument accepts, if they are limited.
+    pub fn choices(&self) -> &[String] {
+        &self.choices
+    }
+
+    /// What kind of value shell completion should offer.
+    pub fn hint(&self) -> Option<ValueHint> {
+        self.hint
+    }
+
+    /// What the path the argument names must be on disk.
+    pub fn path_check(&self) -> Option<PathCheck> {
+        self.path_check
+    }
+
+    /// What a pattern matching no path gives, if patterns in the
+    /// argument's values are expanded.
+    pub fn glob(&self) -> Option<NoMatches> {
+        self.glob
+    }
+
+    /// The environment variable the argument falls back to.
+    pub fn env_var(&self) -> Option<&str> {
+        self.env.as_deref()
+    }
+}
+
+fn token_to_spec(token: &str) -> Result<ArgSpec, ParseErr> {
+    let (positional, variadic) = match token.strip_suffix("...") {
+        Some(positional) => (positional, true),
+        None => (token, false),
+    };
+    if let Some(name) = positional.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+        if name.is_empty() {
+            return Err(ParseErr::InvalidSchema);
+        }
+        let kind = if variadic { ArgKind::StrArray } else { ArgKind::Str };
+        let mut spec = ArgSpec::new(name, kind);
+        spec.positional = true;
+        spec.required = !variadic;
+        return Ok(spec);
+    }
+    let name_len = match token.chars().next() {
+        Some(c) => c.len_utf8(),
+        None => return Err(ParseErr::InvalidSchema),
+    };
+    let (name, marker) = token.split_at(name_len);
+    match ArgKind::from_marker(marker) {
+        Ok(kind) => Ok(ArgSpec::new(name, kind)),
+        Err(err) => crate::types::spec(name, marker).ok_or(err),
+    }
+}
+
+/// Whether a variadic positional argument, which takes every remaining
+/// word, is followed by another positional one that could never be bound.
+fn variadic_not_last(specs: &[ArgSpec]) -> bool {
+    let mut positionals = specs.iter().filter(|spec| spec.positional).rev().skip(1);
+    positionals.any(|spec| spec.kind == ArgKind::StrArray)
+}
+
+/// Fails with [`ParseErr::DuplicateArg`] on the first name declared twice,
+/// as [`Schema::merge`] does.
+fn check_duplicates(specs: &[ArgSpec]) -> Result<(), ParseErr> {
+    for (i, spec) in specs.iter().enumerate() {
+        if specs[..i].iter().any(|other| other.name == spec.name) {
+            return Err(ParseErr::DuplicateArg(spec.name.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// `value` as a single word of input: double-quoted if it is empty, looks
+/// like a flag or contains whitespace, quotes or backslashes.
+pub(crate) fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.starts_with('-')
+        && !value.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'' || c == '\\');
+    if plain {
+        return value.to_string();
+    }
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// How `name` is written on the command line: `-p` or `--port`.
+pub(crate) fn flag(name: &str) -> String {
+    let dashes = if name.chars().count() > 1 { "--" } else { "-" };
+    format!("{}{}", dashes, name)
+}
+
+/// Drops the last `excess` values of `token`, which were read as
+/// positional arguments instead, from its values and its span in `input`.
+fn trim_token(token: Option<&mut RawToken>, excess: usize, input: &str) {
+    let token = match token {
+        Some(token) if excess > 0 => token,
+        _ => return,
+    };
+    let words: Vec<Range<usize>> =
+        crate::split_words(&input[token.span.clone()]).map(|word| word.span).collect();
+    let kept = words.len().saturating_sub(excess).max(1);
+    token.span.end = token.span.start + words[kept - 1].end;
+    token.values.truncate(token.values.len().saturating_sub(excess));
+    token.text = input[token.span.clone()].to_string();
+}
+
+/// Where `value` is within `span` of `input`, or the whole span when it
+/// can't be found there.
+fn value_in(input: &str, span: Range<usize>, value: &str) -> Range<usize> {
+    match input.get(span.clone()).and_then(|text| text.rfind(value)) {
+        Some(at) => span.start + at..span.start + at + value.len(),
+        None => span,
+    }
+}
+
+/// Cuts `line` at a `#` that starts a word. A `#` right after an argument
+/// name is the number marker, not a comment.
+fn strip_comment(line: &str) -> &str {
+    let mut prev = None;
+    for (i, c) in line.char_indices() {
+        if c == '#' && prev.is_none_or(|p: char| p == ',' || p.is_whitespace()) {
+            return &line[..i];
+        }
+        prev = Some(c);
+    }
+    line
+}
+
+/// The argument definitions together with the cross-argument constraints
+/// that are checked once the whole input has been consumed.
+#[derive(Clone, Debug)]
+pub struct Schema {
+    pub(crate) specs: Vec<ArgSpec>,
+    pub(crate) constraints: Vec<Constraint>,
+    pub(crate) inversions: Vec<(String, String)>,
+    pub(crate) hidden: Vec<String>,
+    pub(crate) deprecations: Vec<(String, Option<String>)>,
+    pub(crate) case_insensitive: bool,
+    pub(crate) prefixes: String,
+    pub(crate) response_files: bool,
+    pub(crate) expand_env: bool,
+    pub(crate) expand_tilde: bool,
+    pub(crate) shell_words: bool,
+    pub(crate) capture_rest: bool,
+    pub(crate) collect_unknown: bool,
+    pub(crate) abbreviations: bool,
+    pub(crate) single_dash: bool,
+    /// Named bundles of values, chosen by the argument `preset_arg`.
+    pub(crate) presets: Vec<(String, Vec<(String, String)>)>,
+    pub(crate) preset_arg: Option<String>,
+    pub(crate) help_program: Option<String>,
+    pub(crate) version: Option<String>,
+    pub(crate) prompt: bool,
+    pub(crate) limits: Limits,
+    symbols: Symbols,
+    checks: Vec<Check>,
+    pub(crate) messages: Option<Messages>,
+    warning_sink: Option<SinkHandle>,
+}
+
+type MessagesFn = dyn Fn(&ParseErr) -> Option<String> + Send + Sync;
+
+/// The wording of errors, see [`Schema::error_messages`].
+#[derive(Clone)]
+pub(crate) struct Messages(pub(crate) Arc<MessagesFn>);
+
+impl fmt::Debug for Messages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Messages")
+    }
+}
+
+type CheckFn = dyn Fn(&ParsedArgs) -> Result<(), ParseErr> + Send + Sync;
+
+/// A check run against the whole result, see [`Schema::validate_with`].
+#[derive(Clone)]
+struct Check(Arc<CheckFn>);
+
+impl fmt::Debug for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Check")
+    }
+}
+
+#[derive(Clone)]
+struct SinkHandle(Arc<dyn WarningSink>);
+
+impl fmt::Debug for SinkHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WarningSink")
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Constraint {
+    Requires(String, String),
+    ConflictsWith(String, String),
+    /// At least one of the arguments must be given, or with `exactly`
+    /// exactly one.
+    OneOf(Vec<String>, bool),
+    /// The first argument must have a value once the second has the value
+    /// given, checked on the finished result.
+    RequiredIf(String, String, String),
+}
+
+impl Schema {
+    /// Compiles a definition such as `l,p#,d*,<input>`, where `<input>` is a
+    /// required positional argument taking a string. A last positional
+    /// argument written as `<files>...` takes every remaining word, if any,
+    /// as a list of strings, gathered from anywhere in the input the way GNU
+    /// getopt permutes arguments, so `a.rs -l b.rs` binds both files. Entries
+    /// may also be separated by whitespace or newlines, trailing commas are
+    /// allowed and a `#` at the start of a word begins a comment running to
+    /// the end of the line:
+    ///
+    /// ```text
+    /// l,        # verbose logging
+    /// p#        # port
+    /// d*
+    /// ```
+    pub fn new(definition: &str) -> Result<Self, ParseErr> {
+        let specs: Vec<ArgSpec> = definition
+            .lines()
+            .map(strip_comment)
+            .flat_map(|line| line.split(|c: char| c == ',' || c.is_whitespace()))
+            .filter(|token| !token.is_empty())
+            .map(token_to_spec)
+            .collect::<Result<_, _>>()?;
+        if specs.is_empty() || variadic_not_last(&specs) {
+            return Err(ParseErr::InvalidSchema);
+        }
+        check_duplicates(&specs)?;
+        Ok(Self::from_specs(specs))
+    }
+
+    pub fn builder() -> SchemaBuilder {
+        SchemaBuilder {
+            specs: vec![],
+            misplaced: false,
+        }
+    }
+
+    pub(crate) fn from_specs(specs: Vec<ArgSpec>) -> Self {
+        Self {
+            symbols: Symbols::new(specs.iter().map(|spec| spec.name.as_str())),
+            specs,
+            constraints: vec![],
+            inversions: vec![],
+            hidden: vec![],
+            deprecations: vec![],
+            case_insensitive: false,
+            prefixes: String::new(),
+            response_files: false,
+            expand_env: false,
+            expand_tilde: false,
+            shell_words: false,
+            capture_rest: false,
+            collect_unknown: false,
+            abbreviations: false,
+            single_dash: false,
+            presets: vec![],
+            preset_arg: None,
+            help_program: None,
+            version: None,
+            prompt: false,
+            limits: Limits::default(),
+            checks: vec![],
+            messages: None,
+            warning_sink: None,
+        }
+    }
+
+    /// Combines two schemas, e.g. shared logging flags with a tool's own.
+    /// Arguments, constraints and switches of `other` come after this
+    /// schema's, and a name declared by both, whether as an argument or an
+    /// inverted switch, fails with [`ParseErr::DuplicateArg`] rather than
+    /// one silently shadowing the other. The merged schema is case
+    /// insensitive if either was.
+    pub fn merge(mut self, other: Schema) -> Result<Self, ParseErr> {
+        self.include(&other)?;
+        Ok(self)
+    }
+
+    pub(crate) fn include(&mut self, other: &Schema) -> Result<(), ParseErr> {
+        let names = |schema: &Schema| -> Vec<String> {
+            let specs = schema.specs.iter().map(|spec| spec.name.clone());
+            specs.chain(schema.inversions.iter().map(|(switch, _)| switch.clone())).collect()
+        };
+        let ours = names(self);
+        if let Some(name) = names(other).into_iter().find(|name| ours.contains(name)) {
+            return Err(ParseErr::DuplicateArg(name));
+        }
+        let mut specs = self.specs.clone();
+        specs.extend(other.specs.iter().cloned());
+        if variadic_not_last(&specs) {
+            return Err(ParseErr::InvalidSchema);
+        }
+        self.symbols = Symbols::new(specs.iter().map(|spec| spec.name.as_str()));
+        self.specs = specs;
+        self.constraints.extend(other.constraints.iter().cloned());
+        self.inversions.extend(other.inversions.iter().cloned());
+        self.hidden.extend(other.hidden.iter().cloned());
+        self.deprecations.extend(other.deprecations.iter().cloned());
+        self.case_insensitive |= other.case_insensitive;
+        self.response_files |= other.response_files;
+        self.expand_env |= other.expand_env;
+        self.expand_tilde |= other.expand_tilde;
+        self.shell_words |= other.shell_words;
+        self.capture_rest |= other.capture_rest;
+        self.collect_unknown |= other.collect_unknown;
+        self.abbreviations |= other.abbreviations;
+        self.single_dash |= other.single_dash;
+        for (name, values) in &other.presets {
+            if !self.presets.iter().any(|(preset, _)| preset == name) {
+                self.presets.push((name.clone(), values.clone()));
+            }
+        }
+        if self.preset_arg.is_none() {
+            self.preset_arg = other.preset_arg.clone();
+        }
+        self.prompt |= other.prompt;
+        self.limits = self.limits.or(other.limits);
+        self.checks.extend(other.checks.iter().cloned());
+        if self.help_program.is_none() {
+            self.help_program = other.help_program.clone();
+        }
+        if self.version.is_none() {
+            self.version = other.version.clone();
+        }
+        for prefix in other.prefixes.chars() {
+            if !self.prefixes.contains(prefix) {
+                self.prefixes.push(prefix);
+            }
+        }
+        if self.warning_sink.is_none() {
+            self.warning_sink = other.warning_sink.clone();
+        }
+        if self.messages.is_none() {
+            self.messages = other.messages.clone();
+        }
+        Ok(())
+    }
+
+    /// Every declared argument, in declaration order.
+    pub fn args(&self) -> impl Iterator<Item = &ArgSpec> {
+        self.specs.iter()
+    }
+
+    pub(crate) fn spec(&self, name: &str) -> Option<&ArgSpec> {
+        self.symbols.id(name).map(|id| &self.specs[id])
+    }
+
+    /// Each declared argument with its interned name.
+    pub(crate) fn interned(&self) -> impl Iterator<Item = (&Symbol, &ArgSpec)> {
+        (0..self.specs.len()).map(move |id| (self.symbols.name(id), &self.specs[id]))
+    }
+
+    /// The interned `name`, shared with every result's maps.
+    pub(crate) fn symbol(&self, name: &str) -> Symbol {
+        self.symbols.intern(name)
+    }
+
+    /// The argument given on the command line as `name`, leaving out
+    /// positional ones.
+    pub(crate) fn flag_spec(&self, name: &str) -> Option<&ArgSpec> {
+        self.spec(name).filter(|spec| !spec.positional)
+    }
+
+    pub(crate) fn has_positionals(&self) -> bool {
+        self.specs.iter().any(|spec| spec.positional)
+    }
+
+    /// Splits the words following a flag into the ones it takes and the
+    /// ones left for positional arguments, see [`Schema::split_taken`]. All
+    /// words go to the flag when there are no positional arguments, unless
+    /// the rest is captured.
+    pub(crate) fn split_excess(
+        &self,
+        kind: ArgKind,
+        values: Vec<String>,
+    ) -> (Vec<String>, Vec<String>) {
+        if !self.has_positionals() && !self.capture_rest {
+            return (values, vec![]);
+        }
+        self.split_taken(kind, values)
+    }
+
+    /// Splits the words following a flag into the ones it takes and the
+    /// rest: bools take only an explicit `true` or `false`, scalars a single
+    /// word and lists all of them.
+    pub(crate) fn split_taken(
+        &self,
+        kind: ArgKind,
+        mut values: Vec<String>,
+    ) -> (Vec<String>, Vec<String>) {
+        match kind {
+            ArgKind::Bool => {
+                let explicit = values.len() == 1
+                    && ["true", "false"].iter().any(|b| values[0].eq_ignore_ascii_case(b));
+                if explicit {
+                    (values, vec![])
+                } else {
+                    (vec![], values)
+                }
+            }
+            ArgKind::StrArray | ArgKind::NumberArray => (values, vec![]),
+            _ => {
+                let excess = values.split_off(values.len().min(1));
+                (values, excess)
+            }
+        }
+    }
+
+    /// Assigns `words`, then `trailing` words after a `--`, to the
+    /// positional arguments in declaration order. Words that none of them
+    /// take fail with [`ParseErr::UnexpectedValue`]; trailing words are
+    /// allowed to remain. A captured rest isn't bound at all.
+    pub(crate) fn bind_positionals(
+        &self,
+        words: Vec<String>,
+        trailing: &[String],
+    ) -> Result<Vec<(String, Vec<String>)>, ParseErr> {
+        let given = words.len();
+        let mut queue = words;
+        if !self.capture_rest {
+            queue.extend(trailing.iter().cloned());
+        }
+        let mut next = 0;
+        let mut bound = vec![];
+        for spec in self.specs.iter().filter(|spec| spec.positional) {
+            let end = match spec.kind {
+                ArgKind::StrArray | ArgKind::NumberArray => queue.len(),
+                _ => queue.len().min(next + 1),
+            };
+            if end > next {
+                bound.push((spec.name.clone(), queue[next..end].to_vec()));
+                next = end;
+            }
+        }
+        match queue.get(next) {
+            Some(word) if next < given => Err(ParseErr::UnexpectedValue(word.clone())),
+            _ => Ok(bound),
+        }
+    }
+
+    pub fn description(&self, arg: &str) -> Option<&str> {
+        self.spec(arg)?.description.as_deref()
+    }
+
+    pub fn default_value(&self, arg: &str) -> Option<&str> {
+        self.spec(arg)?.default.as_deref()
+    }
+
+    /// Whenever `arg` is given, `required` must be given as well.
+    pub fn requires(mut self, arg: &str, required: &str) -> Self {
+        self.constraints
+            .push(Constraint::Requires(arg.to_string(), required.to_string()));
+        self
+    }
+
+    /// `arg` and `other` must not be given together.
+    pub fn conflicts_with(mut self, arg: &str, other: &str) -> Self {
+        self.constraints
+            .push(Constraint::ConflictsWith(arg.to_string(), other.to_string()));
+        self
+    }
+
+    /// Requires `arg` whenever `other` ends up with `value`, given on the
+    /// command line or not, e.g. `required_if("k", "tls", "true")` for a key
+    /// once TLS is on, set by `--tls` or by its environment variable.
+    /// Unlike [`Schema::requires`] it is checked on the finished result, and
+    /// fails with [`ParseErr::RequiredIf`].
+    pub fn required_if(mut self, arg: &str, other: &str, value: &str) -> Self {
+        let (arg, other, value) = (arg.to_string(), other.to_string(), value.to_string());
+        self.constraints.push(Constraint::RequiredIf(arg, other, value));
+        self
+    }
+
+    /// Exactly one of `args` must be given, e.g. one of `-f` and `-u` for
+    /// where to read from. Giving none fails with [`ParseErr::MissingOneOf`]
+    /// and giving two with [`ParseErr::ConflictingArgs`].
+    pub fn one_of(mut self, args: &[&str]) -> Self {
+        let args = args.iter().map(ToString::to_string).collect();
+        self.constraints.push(Constraint::OneOf(args, true));
+        self
+    }
+
+    /// At least one of `args` must be given, failing with
+    /// [`ParseErr::MissingOneOf`] otherwise.
+    pub fn any_of(mut self, args: &[&str]) -> Self {
+        let args = args.iter().map(ToString::to_string).collect();
+        self.constraints.push(Constraint::OneOf(args, false));
+        self
+    }
+
+    /// Runs `check` on every result once defaults are applied, for rules
+    /// spanning several arguments such as "`--start` must be before
+    /// `--end`". The first error it returns fails the parse.
+    pub fn validate_with<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&ParsedArgs) -> Result<(), ParseErr> + Send + Sync + 'static,
+    {
+        self.checks.push(Check(Arc::new(check)));
+        self
+    }
+
+    /// Adds `switch` as a flag that turns the boolean `arg` off, e.g.
+    /// `--disable-cache` for `cache`. `arg` then defaults to `true`.
+    ///
+    /// Without a switch, any bool can still be turned off as `--no-cache`,
+    /// or `-l-` for a short name, overriding earlier occurrences.
+    pub fn inverted(mut self, switch: &str, arg: &str) -> Self {
+        self.inversions.push((switch.to_string(), arg.to_string()));
+        self
+    }
+
+    /// Lets `arg` be given at most `max` times, or any number of times for
+    /// `None`. Scalars default to once and lists to no limit; going over
+    /// fails with [`ParseErr::TooManyOccurrences`].
+    pub fn max_occurrences(mut self, arg: &str, max: Option<usize>) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.max_occurrences = max;
+        }
+        self
+    }
+
+    /// Sets what repeating `arg` does. Arguments use
+    /// [`Accumulation::Append`] unless set otherwise. Any policy but
+    /// [`Accumulation::Error`] lifts the limit of one occurrence for a
+    /// scalar, as [`ParserBuilder::duplicates`](crate::ParserBuilder::duplicates)
+    /// does; a [`Schema::max_occurrences`] set afterwards still applies.
+    pub fn accumulate(mut self, arg: &str, accumulation: Accumulation) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.accumulation = accumulation;
+            if accumulation != Accumulation::Error {
+                spec.max_occurrences = None;
+            }
+        }
+        self
+    }
+
+    /// Splits each value of the list `arg` at `delimiter`, so `-p 8080,9090`
+    /// gives the same list as `-p 8080 -p 9090` or `-p 8080 9090`. Values
+    /// from other sources, such as defaults, are split too.
+    pub fn delimiter(mut self, arg: &str, delimiter: char) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.delimiter = Some(delimiter);
+        }
+        self
+    }
+
+    /// Joins the values of the string `arg` with `separator` rather than a
+    /// space, both those of one occurrence, as in `-m fix the bug`, and
+    /// those of every occurrence under [`Accumulation::Concat`].
+    pub fn separator(mut self, arg: &str, separator: &str) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.separator = Some(separator.to_string());
+        }
+        self
+    }
+
+    /// Defaults `arg` to `default` while `other` has `value`, instead of to
+    /// its plain default, e.g. `default_if("port", "tls", "true", "443")`
+    /// for a port of 80 otherwise. The first of several that applies wins.
+    /// Arguments with such defaults are filled in after all others, so
+    /// `other` is compared with its final value, and the value taken is
+    /// reported as [`Source::Default`].
+    pub fn default_if(mut self, arg: &str, other: &str, value: &str, default: &str) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            let rule = (other.to_string(), value.to_string(), default.to_string());
+            spec.defaults_if.push(rule);
+        }
+        self
+    }
+
+    /// Falls back to the environment variable `var` when `arg` isn't given,
+    /// before its default, e.g. `APP_PORT` for `p`. [`ParsedArgs::source`]
+    /// tells which one the value came from.
+    pub fn env(mut self, arg: &str, var: &str) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.env = Some(var.to_string());
+        }
+        self
+    }
+
+    /// Tags `arg` with how sensitive its value is. Arguments are
+    /// [`Classification::Public`] unless classified otherwise.
+    pub fn classify(mut self, arg: &str, classification: Classification) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.classification = classification;
+        }
+        self
+    }
+
+    /// Sets what the bool `arg` reads as when it isn't given. Bools read as
+    /// `false` unless set otherwise.
+    pub fn absent_bool(mut self, arg: &str, absent: AbsentBool) -> Self {
+        if let Some(spec) = self.specs.iter_mut().find(|spec| spec.name == arg) {
+            spec.absent = absent;
+        }
+        self
+    }
+
+    /// Keeps `arg` out of the generated usage text. It is still parsed.
+    pub fn hidden(mut self, arg: &str) -> Self {
+        self.hidden.push(arg.to_string());
+        self
+    }
+
+    /// Marks `arg` as deprecated. It is still parsed, but using it adds a
+    /// [`ParseWarning::Deprecated`] naming the `replacement`, if any.
+    pub fn deprecated(mut self, arg: &str, replacement: Option<&str>) -> Self {
+        self.deprecations
+            .push((arg.to_string(), replacement.map(ToString::to_string)));
+        self
+    }
+
+    /// Matches flags regardless of case, so `-P 8080` sets `p#`. An exact
+    /// match still wins when the schema has both `p` and `P`.
+    pub fn case_insensitive(mut self) -> Self {
+        self.case_insensitive = true;
+        self
+    }
+
+    /// Reads a long flag given as the start of a single declared name as
+    /// that flag, as GNU tools do: `--verb` for `--verbose`. A start shared
+    /// by several fails with [`ParseErr::AmbiguousArg`], and a name that is
+    /// declared itself always wins, so `--port` stays `port` next to
+    /// `portal`. Short flags and clusters are never read as abbreviations.
+    pub fn abbreviations(mut self) -> Self {
+        self.abbreviations = true;
+        self
+    }
+
+    /// Writes every flag with a single dash, as `find` and `java` do:
+    /// `-name foo -type f`. A word with one dash then always names a single
+    /// flag, so short flags can't be clustered as in `-lrt` or take their
+    /// value attached as in `-p8080`. `--name` still works, and usage, help
+    /// and the canonical command line spell the flags with one dash.
+    pub fn single_dash(mut self) -> Self {
+        self.single_dash = true;
+        self
+    }
+
+    /// How `name` is written on the command line with this schema: `-p` or
+    /// `--port`, or `-port` with [`Schema::single_dash`].
+    pub(crate) fn dashed(&self, name: &str) -> String {
+        match self.single_dash {
+            true => format!("-{}", name),
+            false => flag(name),
+        }
+    }
+
+    /// Also accepts flags introduced by any character of `prefixes`, such
+    /// as `/p 8080 /l` or `/port 8080` with `"/"`, for tools that should
+    /// feel native on Windows. `-` always works. A word such as `/var/logs`
+    /// that doesn't name a flag is still read as a value.
+    pub fn flag_prefixes(mut self, prefixes: &str) -> Self {
+        self.prefixes = prefixes.to_string();
+        self
+    }
+
+    /// Splits the input the way a POSIX shell would, so a line copied from
+    /// a terminal reads the same: on top of the default quoting, `\$` and
+    /// `` \` `` are escapes within double quotes, a backslash before a
+    /// newline joins the lines, and a quote left open fails with
+    /// [`ParseErr::UnterminatedQuote`] instead of running to the end.
+    pub fn shell_words(mut self) -> Self {
+        self.shell_words = true;
+        self
+    }
+
+    /// Ends the flags at the first word the schema has no place for instead
+    /// of failing: an unknown flag, or a word that neither the flag before
+    /// it nor a positional argument takes. That word and everything after
+    /// it are left as they are in [`ParsedArgs::trailing`], for wrappers
+    /// that forward them to another program, as are the words after a
+    /// `--`. None of them are bound to positional arguments.
+    pub fn capture_rest(mut self) -> Self {
+        self.capture_rest = true;
+        self
+    }
+
+    /// Sets a flag the schema doesn't declare aside instead of failing with
+    /// [`ParseErr::UnknownArg`], and goes on parsing. The flag, spelled as
+    /// given, and the values after it are kept in order in
+    /// [`ParsedArgs::unknown`], for wrappers that pass them through.
+    pub fn collect_unknown(mut self) -> Self {
+        self.collect_unknown = true;
+        self
+    }
+
+    /// Stops parsing at `-h` or `--help` with [`ParseErr::HelpRequested`],
+    /// carrying the [`Schema::help`] screen for `program`, instead of
+    /// failing on them as unknown, so that `main` can print it and exit.
+    /// Arguments the schema itself declares as `h` or `help` keep their
+    /// meaning.
+    pub fn help_flags(mut self, program: &str) -> Self {
+        self.help_program = Some(program.to_string());
+        self
+    }
+
+    /// Stops parsing at `-V` or `--version` with
+    /// [`ParseErr::VersionRequested`], carrying `program` followed by
+    /// `version`, e.g. `app 1.2.0`. As with [`Schema::help_flags`],
+    /// arguments the schema declares as `V` or `version` keep their
+    /// meaning.
+    pub fn version_flags(mut self, program: &str, version: &str) -> Self {
+        self.version = Some(format!("{} {}", program, version));
+        self
+    }
+
+    /// Declares the preset `name`, a bundle of `(argument, value)` pairs
+    /// applied when the argument of [`Schema::preset_arg`] names it, as
+    /// cargo applies a profile. A name declared again replaces the bundle.
+    /// Fails with [`ParseErr::InvalidSchema`] if an argument isn't in the
+    /// schema.
+    pub fn preset(mut self, name: &str, values: &[(&str, &str)]) -> Result<Self, ParseErr> {
+        if values.iter().any(|(arg, _)| self.spec(arg).is_none()) {
+            return Err(ParseErr::InvalidSchema);
+        }
+        let values = values.iter().map(|(arg, value)| (arg.to_string(), value.to_string()));
+        self.presets.retain(|(preset, _)| preset != name);
+        self.presets.push((name.to_string(), values.collect()));
+        Ok(self)
+    }
+
+    /// Chooses a [`Schema::preset`] by the value of the string `arg`, e.g.
+    /// `--profile fast`. The name is read from every source first, the
+    /// command line, environment variables, `.env` and config files of a
+    /// [`Resolver`](crate::Resolver), and the defaults of `arg`. The
+    /// preset's values then fill in the arguments still unset beneath all
+    /// of those sources but above the defaults, and are reported as
+    /// [`Source::Preset`]. A name without a preset fails with
+    /// [`ParseErr::UnknownPreset`], and an `arg` that isn't a string in the
+    /// schema with [`ParseErr::InvalidSchema`].
+    pub fn preset_arg(mut self, arg: &str) -> Result<Self, ParseErr> {
+        match self.spec(arg) {
+            Some(spec) if spec.kind.is_string() => {}
+            _ => return Err(ParseErr::InvalidSchema),
+        }
+        self.preset_arg = Some(arg.to_string());
+        Ok(self)
+    }
+
+    /// The names of the declared presets, in order.
+    pub fn presets(&self) -> impl Iterator<Item = &str> {
+        self.presets.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Fills in the values of the preset chosen, see [`Schema::preset_arg`].
+    pub(crate) fn apply_preset(&self, parsed: &mut ParsedArgs) -> Result<(), ParseErr> {
+        let arg = match &self.preset_arg {
+            Some(arg) => arg,
+            None => return Ok(()),
+        };
+        let chosen = parsed.get_str(arg).or_else(|| self.spec(arg)?.default.clone());
+        let name = match chosen {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let preset = self.presets.iter().find(|(preset, _)| *preset == name);
+        let (_, values) = preset.ok_or(ParseErr::UnknownPreset(name))?;
+        for (arg, value) in values {
+            self.fill(parsed, arg, value.clone(), Source::Preset)?;
+        }
+        Ok(())
+    }
+
+    /// Fails with [`ParseErr::HelpRequested`] or
+    /// [`ParseErr::VersionRequested`] if `input` asks for help or the
+    /// version, whichever comes first.
+    pub(crate) fn check_builtin_flags(&self, input: Input) -> Result<(), ParseErr> {
+        if self.help_program.is_none() && self.version.is_none() {
+            return Ok(());
+        }
+        let builtin = |name: &str| {
+            let taken = self.spec(name).is_some()
+                || self.inversions.iter().any(|(switch, _)| switch == name);
+            match (&self.help_program, &self.version) {
+                _ if taken => None,
+                (Some(program), _) if name == "h" || name == "help" => {
+                    Some(ParseErr::HelpRequested(self.help(program)))
+                }
+                (_, Some(version)) if name == "V" || name == "version" => {
+                    Some(ParseErr::VersionRequested(version.clone()))
+                }
+                _ => None,
+            }
+        };
+        let mut tokens = TokensIterator::for_input(input, self);
+        match tokens.find_map(|token| builtin(&self.canonical_name(token.name.into_owned()))) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails on a quote left open in shell words mode, once `tokens` has
+    /// read the word opening it.
+    pub(crate) fn check_quotes(&self, tokens: &TokensIterator) -> Result<(), ParseErr> {
+        match tokens.open_quote() {
+            Some(quote) if self.shell_words => Err(ParseErr::UnterminatedQuote(quote)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reports every warning raised while parsing to `sink`, in addition to
+    /// returning it from [`ParsedArgs::warnings`].
+    pub fn warning_sink(mut self, sink: Arc<dyn WarningSink>) -> Self {
+        self.warning_sink = Some(SinkHandle(sink));
+        self
+    }
+
+    /// Words errors the application's way, e.g. in another language, for
+    /// [`Schema::explain`] and [`Schema::render_error`]. Errors for which
+    /// `messages` returns `None` keep the built-in wording; the
+    /// [`ParseErr`] accessors give the arguments and values to mention.
+    pub fn error_messages<F>(mut self, messages: F) -> Self
+    where
+        F: Fn(&ParseErr) -> Option<String> + Send + Sync + 'static,
+    {
+        self.messages = Some(Messages(Arc::new(messages)));
+        self
+    }
+
+    /// The schema's spelling of the flag `given` on the command line.
+    pub(crate) fn canonical_name(&self, given: String) -> String {
+        if !self.case_insensitive || self.flag_names().any(|name| *name == given) {
+            return given;
+        }
+        self.flag_names()
+            .find(|name| name.to_lowercase() == given.to_lowercase())
+            .cloned()
+            .unwrap_or(given)
+    }
+
+    /// The declared flags and inverted switches.
+    fn flag_names(&self) -> impl Iterator<Item = &String> {
+        let flags = self.specs.iter().filter(|spec| !spec.positional);
+        let specs = flags.map(|spec| &spec.name);
+        specs.chain(self.inversions.iter().map(|(switch, _)| switch))
+    }
+
+    /// The long flags whose names `given` is the start of, when
+    /// [`Schema::abbreviations`] is set and `given` isn't a name itself.
+    pub(crate) fn expansions(&self, given: &str) -> Vec<String> {
+        if !self.abbreviations || given.is_empty() || self.is_name(given) {
+            return vec![];
+        }
+        let fold = |name: &str| match self.case_insensitive {
+            true => name.to_lowercase(),
+            false => name.to_string(),
+        };
+        let given = fold(given);
+        self.flag_names()
+            .filter(|name| name.chars().count() > 1 && fold(name).starts_with(&given))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether `given` names an argument or inverted switch, or negates a
+    /// bool.
+    pub(crate) fn is_name(&self, given: &str) -> bool {
+        self.is_declared(given) || self.negated(given).is_some()
+    }
+
+    fn is_declared(&self, given: &str) -> bool {
+        let name = self.canonical_name(given.to_string());
+        self.flag_spec(&name).is_some() || self.inversions.iter().any(|(switch, _)| *switch == name)
+    }
+
+    /// The bool that `given` turns off when it is written as `no-verbose`
+    /// for `verbose`, or `l-` for `l`, and isn't declared itself.
+    pub(crate) fn negated(&self, given: &str) -> Option<String> {
+        if self.is_declared(given) {
+            return None;
+        }
+        let short = given.strip_suffix('-').filter(|name| name.chars().count() == 1);
+        let name = self.canonical_name(given.strip_prefix("no-").or(short)?.to_string());
+        let spec = self.flag_spec(&name).filter(|spec| spec.kind == ArgKind::Bool)?;
+        Some(spec.name.clone())
+    }
+
+    /// Reads a short flag that isn't a name of its own, nor is the part
+    /// before an `=`, as a cluster: any number of bools or inverted
+    /// switches, optionally ending
+    /// with an argument that takes a value, which may be attached. So `lrt`
+    /// is `l`, `r` and `t`, and `lp8080` is `l` then `p` with `8080`.
+    pub(crate) fn split_short(&self, flag: &str) -> Option<Vec<(String, Option<String>)>> {
+        let before_equals = flag.split('=').next().unwrap_or(flag);
+        if self.is_name(flag) || self.is_name(before_equals) {
+            return None;
+        }
+        let mut cluster = vec![];
+        for (i, c) in flag.char_indices() {
+            let name = c.to_string();
+            let takes_value = match self.flag_spec(&self.canonical_name(name.clone())) {
+                Some(spec) => spec.kind != ArgKind::Bool,
+                None if self.is_name(&name) => false,
+                None => return None,
+            };
+            if !takes_value {
+                cluster.push((name, None));
+                continue;
+            }
+            let rest = &flag[i + c.len_utf8()..];
+            let rest = rest.strip_prefix('=').unwrap_or(rest);
+            cluster.push((name, Some(rest.to_string()).filter(|rest| !rest.is_empty())));
+            break;
+        }
+        Some(cluster).filter(|cluster| !cluster.is_empty())
+    }
+
+    /// Reads the flags of `input` the way [`Schema::parse`] does, with
+    /// clusters, attached values, negative numbers, alternative prefixes and
+    /// positional arguments as declared, but without checking names or
+    /// values, e.g. for highlighting or completion. Response files aren't
+    /// expanded.
+    pub fn tokens<'a>(&'a self, input: &'a str) -> TokensIterator<'a> {
+        TokensIterator::for_schema(input, self)
+    }
+
+    /// Where in `input` the word behind `err` is, as a byte range to
+    /// underline: the value when it can be found, as for
+    /// [`ParseErr::NumberFormatErr`], otherwise the whole flag with its
+    /// values. `None` for errors that aren't about a word of the input, such
+    /// as [`ParseErr::MissingArg`].
+    pub fn error_span(&self, input: &str, err: &ParseErr) -> Option<Range<usize>> {
+        let (name, value, skip) = match err {
+            ParseErr::UnknownArg { arg: name, .. }
+            | ParseErr::AmbiguousArg { given: name, .. }
+            | ParseErr::UnexpectedValue(name)
+            | ParseErr::ValidationFailed { arg: name, .. }
+            | ParseErr::MissingRequiredArg { arg: name, .. }
+            | ParseErr::ConflictingArgs { other: name, .. } => (Some(name), None, 0),
+            ParseErr::TooManyOccurrences { arg: name, max } => (Some(name), None, *max),
+            ParseErr::InvalidValue { arg: name, value }
+            | ParseErr::PathNotFound { arg: name, path: value }
+            | ParseErr::NotAFile { arg: name, path: value }
+            | ParseErr::NotADirectory { arg: name, path: value }
+            | ParseErr::ParentNotFound { arg: name, path: value }
+            | ParseErr::NoMatches { arg: name, pattern: value } => (Some(name), Some(value), 0),
+            ParseErr::NumberFormatErr { arg, value, span: None } => (Some(arg), Some(value), 0),
+            ParseErr::NumberFormatErr { value, span: Some(span), .. } => {
+                return Some(value_in(input, span.clone(), value));
+            }
+            _ => return None,
+        };
+        let named = |token: &Token| {
+            name.is_none_or(|name| self.canonical_name(token.name.to_string()) == *name)
+        };
+        let token = self
+            .tokens(input)
+            .filter(named)
+            .filter(|token| value.is_none_or(|value| token.values.iter().any(|v| v == value)))
+            .nth(skip)?;
+        Some(match value {
+            Some(value) => value_in(input, token.span, value),
+            None => token.span,
+        })
+    }
+
+    /// A report of `err` for the terminal: the message of
+    /// [`Schema::explain`], and the line of `input` it is about with the
+    /// word underlined, when [`Schema::error_span`] finds one:
+    ///
+    /// ```text
+    /// error: '8o' is not a number
+    ///   | -d logs -p 8o
+    ///   |            ^^
+    /// ```
+    pub fn render_error(&self, input: &str, err: &ParseErr) -> String {
+        let mut report = format!("error: {}", self.explain(err));
+        if let Some(span) = self.error_span(input, err) {
+            let line_start = input[..span.start].rfind('\n').map_or(0, |i| i + 1);
+            let line_end = input[span.start..].find('\n').map_or(input.len(), |i| span.start + i);
+            let column = input[line_start..span.start].chars().count();
+            let width = input[span.start..span.end.min(line_end)].chars().count().max(1);
+            report.push_str(&format!("\n  | {}", &input[line_start..line_end]));
+            report.push_str(&format!("\n  | {}{}", " ".repeat(column), "^".repeat(width)));
+        }
+        report
+    }
+
+    /// `input` with every flag spelled as in the schema and the whitespace
+    /// between words normalized, e.g. `-P   8080` becomes `-p 8080` when
+    /// [`Schema::case_insensitive`] is set. Response files are expanded, and
+    /// values are quoted where needed
+    /// to read back the same, and those of arguments that aren't
+    /// [`Classification::Public`] are masked.
+    pub(crate) fn canonical_command_line(&self, input: &str) -> String {
+        let expanded = self.expand_response_files(Input::typed(input)).ok().flatten();
+        let input = expanded.as_deref().unwrap_or(input);
+        let mut tokens = TokensIterator::for_schema(input, self);
+        let mut words = vec![];
+        while let Some(token) = tokens.next() {
+            words.extend(tokens.positionals.drain(..).map(|word| quote(&word)));
+            let name = self.canonical_name(token.name.into_owned());
+            match self.negated(&name) {
+                Some(negated) => words.push(self.dashed(&format!("no-{}", negated))),
+                None => words.push(self.dashed(&name)),
+            }
+            match self.spec(&name).map(|spec| spec.classification) {
+                Some(Classification::Public) | None => {
+                    words.extend(token.values.iter().map(|value| quote(value)))
+                }
+                Some(_) => words.extend(token.values.iter().map(|_| MASK.to_string())),
+            }
+        }
+        words.extend(tokens.positionals.drain(..).map(|word| quote(&word)));
+        if !tokens.trailing.is_empty() {
+            words.push("--".to_string());
+            words.extend(tokens.trailing.iter().map(|word| quote(word)));
+        }
+        words.join(" ")
+    }
+
+    /// The definition [`Schema::new`] compiles back to the same arguments,
+    /// e.g. `l,p#,d*,<input>` for a schema built with the same names and
+    /// types, or merged from several. Only names and types are written;
+    /// settings such as defaults and constraints have no place in it, see
+    /// `Schema::to_bytes` with the `wire` feature for a complete form.
+    /// Fails with
+    /// [`ParseErr::InvalidSchema`] if an argument can't be written, as a
+    /// flag whose name isn't a single character can't.
+    pub fn to_schema_string(&self) -> Result<String, ParseErr> {
+        let entries = self.specs.iter().map(|spec| {
+            let marker = spec.custom.as_ref().map_or(spec.kind.marker(), |(marker, _)| marker);
+            let written = match (spec.positional, spec.kind) {
+                (true, ArgKind::Str) if spec.required => format!("<{}>", spec.name),
+                (true, ArgKind::StrArray) if !spec.required => format!("<{}>...", spec.name),
+                (true, _) => return Err(ParseErr::InvalidSchema),
+                (false, _) => format!("{}{}", spec.name, marker),
+            };
+            let expected = token_to_spec(&written).ok();
+            let same = expected.filter(|expected| {
+                (&expected.name, expected.kind, expected.positional)
+                    == (&spec.name, spec.kind, spec.positional)
+            });
+            let plain = !written.contains(|c: char| c == ',' || c.is_whitespace())
+                && !written.starts_with('#');
+            match same {
+                Some(_) if plain => Ok(written),
+                _ => Err(ParseErr::InvalidSchema),
+            }
+        });
+        Ok(entries.collect::<Result<Vec<_>, _>>()?.join(","))
+    }
+
+    /// A short, stable hash of everything that decides how input is read:
+    /// the argument names and types, inverted switches and case folding.
+    pub fn fingerprint(&self) -> String {
+        let mut definition: Vec<String> = self
+            .specs
+            .iter()
+            .map(|spec| {
+                let marker = spec.custom.as_ref().map_or(spec.kind.marker(), |(marker, _)| marker);
+                if spec.positional {
+                    format!("<{}>{}", spec.name, marker)
+                } else {
+                    format!("{}{}", spec.name, marker)
+                }
+            })
+            .collect();
+        definition.extend(self.inversions.iter().map(|(switch, arg)| format!("{}!{}", switch, arg)));
+        if self.case_insensitive {
+            definition.push("~".to_string());
+        }
+        if !self.prefixes.is_empty() {
+            definition.push(format!("^{}", self.prefixes));
+        }
+        if self.response_files {
+            definition.push("@".to_string());
+        }
+        if self.expand_env {
+            definition.push("$".to_string());
+        }
+        if self.expand_tilde {
+            definition.push("~~".to_string());
+        }
+        if self.shell_words {
+            definition.push("sh".to_string());
+        }
+        if self.capture_rest {
+            definition.push("...".to_string());
+        }
+        if self.collect_unknown {
+            definition.push("?".to_string());
+        }
+        if self.abbreviations {
+            definition.push("--*".to_string());
+        }
+        if self.single_dash {
+            definition.push("-".to_string());
+        }
+        if self.help_program.is_some() {
+            definition.push("-h".to_string());
+        }
+        if self.version.is_some() {
+            definition.push("-V".to_string());
+        }
+        // 64-bit FNV-1a
+        let hash = definition.join(",").bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        format!("{:016x}", hash)
+    }
+
+    /// One-line synopsis of the visible arguments, e.g.
+    /// `usage: myapp [-l] [-p <number>] [-d <string>] <input>`. Required
+    /// arguments aren't bracketed, and positional ones come last.
+    pub fn usage(&self, program: &str) -> String {
+        let entries = self
+            .specs
+            .iter()
+            .filter(|spec| !spec.positional)
+            .map(|spec| (spec.name.as_str(), spec.value_metavar(), spec.required));
+        let switches = self
+            .inversions
+            .iter()
+            .map(|(switch, _)| (switch.as_str(), String::new(), false));
+        let mut usage = format!("usage: {}", program);
+        for (name, metavar, required) in entries.chain(switches) {
+            if self.hidden.iter().any(|hidden| hidden == name) {
+                continue;
+            }
+            if required {
+                usage.push_str(&format!(" {}{}", self.dashed(name), metavar));
+            } else {
+                usage.push_str(&format!(" [{}{}]", self.dashed(name), metavar));
+            }
+        }
+        for spec in self.specs.iter().filter(|spec| spec.positional) {
+            let dots = if spec.kind == ArgKind::StrArray { "..." } else { "" };
+            if spec.required {
+                usage.push_str(&format!(" <{}>{}", spec.name, dots));
+            } else {
+                usage.push_str(&format!(" [<{}>{}]", spec.name, dots));
+            }
+        }
+        usage
+    }
+
+    pub fn parse(&self, input: &str) -> Result<ParsedArgs, ParseErr> {
+        self.parse_words(Input::typed(input))
+    }
+
+    /// [`Schema::parse`] and [`Schema::parse_from`], for input typed or
+    /// split.
+    fn parse_words(&self, input: Input) -> Result<ParsedArgs, ParseErr> {
+        let mut parsed = self.parse_input(input)?;
+        self.apply_defaults(&mut parsed)?;
+        self.check_parsed(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Like [`Schema::parse`], with `defaults` computed by the application,
+    /// e.g. from a config file, for the arguments missing from `input`.
+    /// They take precedence over the defaults declared in the schema, and
+    /// fail with [`ParseErr::UnknownArg`] for names outside it and
+    /// [`ParseErr::InvalidValue`] for a value of the wrong kind.
+    pub fn parse_with_defaults(
+        &self,
+        input: &str,
+        defaults: &HashMap<&str, ArgValue>,
+    ) -> Result<ParsedArgs, ParseErr> {
+        let mut parsed = self.parse_input(Input::typed(input))?;
+        for (name, value) in defaults {
+            let spec = self.spec(name).ok_or_else(|| ParseErr::unknown_arg(name))?;
+            if spec.kind != value.kind() {
+                let value = value.get().unwrap_or_default();
+                return Err(ParseErr::InvalidValue { arg: name.to_string(), value });
+            }
+        }
+        for (name, _) in self.interned() {
+            let value = match defaults.get(&**name) {
+                Some(value) if !parsed.sources.contains_key(name) => value,
+                _ => continue,
+            };
+            if let Some(words) = value.given_words() {
+                self.check_values(name, &words)?;
+            }
+            match (parsed.args.get_mut(name).unwrap(), value) {
+                (ArgValue::OptStr { presence, .. }, ArgValue::OptStr { presence: given, .. }) => {
+                    *presence = given.clone()
+                }
+                (arg, value) => *arg = value.clone(),
+            }
+            parsed.sources.insert(name.clone(), Source::Default);
+        }
+        self.apply_defaults(&mut parsed)?;
+        self.check_parsed(&parsed)?;
+        Ok(parsed)
+    }
+
+    /// Like [`Schema::parse`], but reports every error in `input` at once,
+    /// see [`validate`](crate::validate).
+    pub fn parse_all(&self, input: &str) -> Result<ParsedArgs, Vec<ParseErr>> {
+        crate::validate(self, input)?;
+        self.parse(input).map_err(|err| vec![err])
+    }
+
+    /// Parses `input` into an application's own type, see [`FromArgs`].
+    pub fn parse_into<T: FromArgs>(&self, input: &str) -> Result<T, ParseErr> {
+        self.parse(input)?.extract()
+    }
+
+    /// Parses words that have already been split, such as those of
+    /// `std::env::args().skip(1)`, each as a word of its own: spaces and
+    /// quotes within one are kept as they are, rather than splitting it.
+    pub fn parse_from<I, S>(&self, args: I) -> Result<ParsedArgs, ParseErr>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let words: Vec<String> = args.into_iter().map(|arg| arg.as_ref().to_string()).collect();
+        let text = crate::join_words(&words);
+        self.parse_words(Input::split(&text, &words))
+    }
+
+    /// Parses the flags at the start of `input`, up to the first word that
+    /// is neither a flag nor taken by one, and returns them along with the
+    /// rest of the input as typed, e.g. for a subcommand to parse in
+    /// `-v build --release`. A `--` also ends the flags, and isn't part of
+    /// the rest. Positional arguments are left to the rest too.
+    pub fn parse_partial(&self, input: &str) -> Result<(ParsedArgs, String), ParseErr> {
+        let (mut parsed, rest) = self.read(Input::typed(input), true)?;
+        self.emit_warnings(&parsed.warnings);
+        self.apply_defaults(&mut parsed)?;
+        self.check_parsed(&parsed)?;
+        Ok((parsed, rest))
+    }
+
+    /// Parses as much of `input` as it can instead of giving up at the first
+    /// error, for interactive tools and completion that want to know what
+    /// was understood. Each flag that fails to parse is left out, with its
+    /// values, and its error reported; errors that can't be traced to a
+    /// flag, such as a quote left open, leave nothing parsed but the
+    /// defaults. The errors come in the order they were found, and are
+    /// empty exactly when [`Schema::parse`] succeeds.
+    pub fn parse_recovering(&self, input: &str) -> (ParsedArgs, Vec<ParseErr>) {
+        let mut input = input.to_string();
+        let mut errors = vec![];
+        let mut parsed = loop {
+            let err = match self.read_input(Input::typed(&input)) {
+                Ok(parsed) => break parsed,
+                Err(err) => err,
+            };
+            let span = self.error_span(&input, &err).and_then(|at| {
+                let mut spans = self.tokens(&input).map(|token| token.span);
+                spans.find(|span| span.start <= at.start && at.end <= span.end)
+            });
+            errors.push(err);
+            match span {
+                Some(span) if !span.is_empty() => input.replace_range(span, ""),
+                _ => break self.unparsed(),
+            }
+        };
+        self.emit_warnings(&parsed.warnings);
+        if let Err(err) = self.apply_defaults(&mut parsed) {
+            errors.push(err);
+        }
+        if let Err(err) = self.check_parsed(&parsed) {
+            errors.push(err);
+        }
+        (parsed, errors)
+    }
+
+    /// Parses `input` without falling back to the schema defaults, so that
+    /// other layers get a chance to fill in absent arguments first.
+    pub(crate) fn parse_input(&self, input: Input) -> Result<ParsedArgs, ParseErr> {
+        let parsed = self.read_input(input)?;
+        self.emit_warnings(&parsed.warnings);
+        Ok(parsed)
+    }
+
+    pub(crate) fn emit_warnings(&self, warnings: &[ParseWarning]) {
+        if let Some(SinkHandle(sink)) = &self.warning_sink {
+            for warning in warnings {
+                sink.emit(self, warning);
+            }
+        }
+    }
+
+    /// [`Schema::parse_input`] without reporting warnings to the sink.
+    pub(crate) fn read_input(&self, input: Input) -> Result<ParsedArgs, ParseErr> {
+        self.read(input, false).map(|(parsed, _)| parsed)
+    }
+
+    /// Reads `input`, or with `partial` only the flags at its start,
+    /// returning what's left.
+    fn read(&self, input: Input, partial: bool) -> Result<(ParsedArgs, String), ParseErr> {
+        self.check_input_len(input.text.len())?;
+        let expanded = self.expand_response_files(input)?;
+        let input = expanded.as_deref().map_or(input, Input::typed);
+        self.check_input_len(input.text.len())?;
+        self.check_builtin_flags(input)?;
+        let mut args: NameMap<ArgValue> = self
+            .interned()
+            .map(|(name, spec)| (name.clone(), spec.new_arg()))
+            .collect();
+        for (_, arg) in &self.inversions {
+            match (self.spec(arg), args.get_mut(arg.as_str())) {
+                (Some(spec), Some(value)) if spec.kind == ArgKind::Bool => {
+                    value.set(arg, vec!["true".to_string()])?
+                }
+                _ => return Err(ParseErr::InvalidSchema),
+            }
+        }
+        let mut concatenated = self.specs.iter().filter(|spec| {
+            spec.accumulation == Accumulation::Concat
+        });
+        if concatenated.any(|spec| !spec.kind.is_string()) {
+            return Err(ParseErr::InvalidSchema);
+        }
+        let mut present = Vec::with_capacity(self.specs.len());
+        let mut occurrences: NameMap<usize> = name_map(self.specs.len());
+        let mut warnings = vec![];
+        let mut normalizations = vec![];
+        let mut words = vec![];
+        let mut given_values: NameMap<Vec<String>> = name_map(self.specs.len());
+        let mut unknown = vec![];
+        let mut raw = vec![];
+        let mut tokens = TokensIterator::for_input(input, self).within_limits();
+        let input = input.text;
+        if partial {
+            tokens = tokens.stopping_at_words();
+        }
+        while let Some(token) = tokens.next() {
+            self.check_quotes(&tokens)?;
+            words.append(&mut tokens.positionals);
+            let given = token.name.into_owned();
+            let taken: Vec<String> = token.values.into_iter().map(Cow::into_owned).collect();
+            raw.push(RawToken {
+                name: given.clone(),
+                values: taken.clone(),
+                text: input[token.span.clone()].to_string(),
+                span: token.span.clone(),
+            });
+            let name = self.canonical_name(given.clone());
+            if name != given {
+                normalizations.push(Normalization::CaseFolded(given, name.clone()));
+            }
+            for (given, name) in tokens.take_abbreviated() {
+                normalizations.push(Normalization::Abbreviated(given, name));
+            }
+            let rewritten = tokens.take_rewritten().into_iter();
+            normalizations.extend(rewritten.map(|(given, read)| Normalization::Unquoted(given, read)));
+            self.warn_if_deprecated(&name, &mut warnings);
+            let negated = self.negated(&name);
+            let inverted = self.inversions.iter().find(|(switch, _)| *switch == name);
+            let inverted = inverted.map(|(_, arg)| arg).or(negated.as_ref());
+            if let Some(arg) = inverted {
+                let (values, mut excess) = self.split_excess(ArgKind::Bool, taken);
+                trim_token(raw.last_mut(), excess.len(), input);
+                words.append(&mut excess);
+                let enabled = values.is_empty() || values.join("").to_lowercase() == "true";
+                args.get_mut(arg.as_str()).unwrap().set(arg, vec![(!enabled).to_string()])?;
+                given_values.entry(self.symbol(arg)).or_default();
+                present.push(arg.to_string());
+            } else if let Some(spec) = self.flag_spec(&name) {
+                let symbol = self.symbol(&name);
+                let (values, mut excess) = self.split_excess(spec.kind, taken);
+                trim_token(raw.last_mut(), excess.len(), input);
+                words.append(&mut excess);
+                let count = occurrences.entry(symbol.clone()).or_insert(0);
+                *count += 1;
+                if let Some(max) = spec.max_occurrences.filter(|max| *count > *max) {
+                    return Err(ParseErr::TooManyOccurrences { arg: name, max });
+                }
+                match spec.accumulation {
+                    Accumulation::Error if *count > 1 => {
+                        return Err(ParseErr::TooManyOccurrences { arg: name, max: 1 });
+                    }
+                    Accumulation::Replace => {
+                        if *count > 1 {
+                            warnings.push(ParseWarning::Overridden(name.clone()));
+                        }
+                        args.insert(symbol.clone(), spec.new_arg());
+                        given_values.remove(&symbol);
+                    }
+                    Accumulation::Keep if *count > 1 => {
+                        warnings.push(ParseWarning::Ignored(name.clone()));
+                        present.push(name);
+                        continue;
+                    }
+                    Accumulation::Append if *count > 1 && spec.kind.is_scalar() => {
+                        warnings.push(ParseWarning::Overridden(name.clone()));
+                    }
+                    _ => {}
+                }
+                let previous = match spec.accumulation {
+                    Accumulation::Concat if *count > 1 => args[&symbol].get(),
+                    _ => None,
+                };
+                let values = self.expand_values(&name, values)?;
+                let values = self.expand_globs(&name, self.split_delimited(&name, values))?;
+                self.check_values(&name, &values)?;
+                self.check_paths(&name, &values)?;
+                let so_far = given_values.entry(symbol.clone()).or_default();
+                so_far.extend(values.iter().cloned());
+                self.check_value_count(&name, so_far.len())?;
+                let values = self.transformed(&name, values);
+                let values = self.joined(&name, previous, values);
+                let (arg, span) = (args.get_mut(&symbol).unwrap(), token.span);
+                arg.set(&name, values).map_err(|err| err.at(span))?;
+                present.push(name);
+            } else {
+                let candidates = self.expansions(&name);
+                if candidates.len() > 1 {
+                    return Err(ParseErr::AmbiguousArg { given: name, candidates });
+                }
+                if !self.collect_unknown {
+                    let token = raw.last().unwrap();
+                    return Err(self.unknown_arg(name, &token.name, &token.text));
+                }
+                unknown.push(flag(&name));
+                unknown.extend(taken);
+            }
+        }
+        if let Some(err) = tokens.exceeded.take() {
+            return Err(err);
+        }
+        self.check_quotes(&tokens)?;
+        words.append(&mut tokens.positionals);
+        for (name, values) in self.bind_positionals(words, &tokens.trailing)? {
+            let values = self.split_delimited(&name, self.expand_values(&name, values)?);
+            let values = self.expand_globs(&name, values)?;
+            self.check_values(&name, &values)?;
+            self.check_paths(&name, &values)?;
+            let symbol = self.symbol(&name);
+            given_values.entry(symbol.clone()).or_default().extend(values.iter().cloned());
+            let values = self.transformed(&name, values);
+            args.get_mut(&symbol).unwrap().set(&name, values)?;
+            present.push(name);
+        }
+        self.check_constraints(&present)?;
+        let mut occurrences = name_map(self.specs.len());
+        for name in &present {
+            *occurrences.entry(self.symbol(name)).or_insert(0) += 1;
+        }
+        let sources = present
+            .iter()
+            .map(|name| (self.symbol(name), Source::CommandLine))
+            .collect();
+        let parsed = ParsedArgs {
+            args,
+            order: self.interned().map(|(name, _)| name.clone()).collect(),
+            positionals: self.positional_names(),
+            kinds: self.kinds(),
+            classifications: self.classifications(),
+            warnings,
+            normalizations,
+            sources,
+            occurrences,
+            given: given_values,
+            trailing: tokens.trailing,
+            unknown,
+            tokens: raw,
+            input: input.to_string(),
+            os_words: vec![],
+        };
+        Ok((parsed, tokens.remainder.join(" ")))
+    }
+
+    /// A result with no argument given.
+    fn unparsed(&self) -> ParsedArgs {
+        let mut args: NameMap<ArgValue> = self
+            .interned()
+            .map(|(name, spec)| (name.clone(), spec.new_arg()))
+            .collect();
+        for (_, arg) in &self.inversions {
+            if let Some(value) = args.get_mut(arg.as_str()) {
+                let _ = value.set(arg, vec!["true".to_string()]);
+            }
+        }
+        ParsedArgs {
+            args,
+            order: self.interned().map(|(name, _)| name.clone()).collect(),
+            positionals: self.positional_names(),
+            kinds: self.kinds(),
+            classifications: self.classifications(),
+            warnings: vec![],
+            normalizations: vec![],
+            sources: NameMap::default(),
+            occurrences: NameMap::default(),
+            given: NameMap::default(),
+            trailing: vec![],
+            unknown: vec![],
+            tokens: vec![],
+            input: String::new(),
+            os_words: vec![],
+        }
+    }
+
+    pub(crate) fn classifications(&self) -> NameMap<Classification> {
+        self.interned()
+            .map(|(name, spec)| (name.clone(), spec.classification))
+            .collect()
+    }
+
+    pub(crate) fn positional_names(&self) -> Vec<String> {
+        let positionals = self.specs.iter().filter(|spec| spec.positional);
+        positionals.map(|spec| spec.name.clone()).collect()
+    }
+
+    pub(crate) fn kinds(&self) -> NameMap<ArgKind> {
+        self.interned()
+            .map(|(name, spec)| (name.clone(), spec.kind))
+            .collect()
+    }
+
+    /// Fills in the arguments still unset from their environment variables,
+    /// then from the chosen preset, then from their defaults, those set
+    /// with [`Schema::default_if`] last, then by asking if the schema
+    /// [prompts](Schema::prompt_missing).
+    pub(crate) fn apply_defaults(&self, parsed: &mut ParsedArgs) -> Result<(), ParseErr> {
+        self.apply_env(parsed)?;
+        self.apply_preset(parsed)?;
+        let (plain, conditional): (Vec<&ArgSpec>, Vec<&ArgSpec>) =
+            self.specs.iter().partition(|spec| spec.defaults_if.is_empty());
+        for spec in plain.into_iter().chain(conditional) {
+            let applies = |(other, value, _): &&(String, String, String)| {
+                parsed.get_str(other).is_some_and(|set| set == *value)
+            };
+            let default = match spec.defaults_if.iter().find(applies) {
+                Some((_, _, default)) => Some(default),
+                None => spec.default.as_ref(),
+            };
+            if let Some(default) = default {
+                self.fill(parsed, &spec.name, default.clone(), Source::Default)?;
+            }
+        }
+        self.prompt_if_terminal(parsed);
+        Ok(())
+    }
+
+    pub(crate) fn apply_env(&self, parsed: &mut ParsedArgs) -> Result<(), ParseErr> {
+        for spec in &self.specs {
+            let value = spec.env.as_ref().and_then(|var| std::env::var(var).ok());
+            if let Some(value) = value {
+                self.fill(parsed, &spec.name, value, Source::Env)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets `name` to `value` from `source` unless something already did.
+    pub(crate) fn fill(
+        &self,
+        parsed: &mut ParsedArgs,
+        name: &str,
+        value: String,
+        source: Source,
+    ) -> Result<(), ParseErr> {
+        self.fill_values(parsed, name, vec![value], source)
+    }
+
+    /// [`Schema::fill`] with any number of values, for lists.
+    pub(crate) fn fill_values(
+        &self,
+        parsed: &mut ParsedArgs,
+        name: &str,
+        values: Vec<String>,
+        source: Source,
+    ) -> Result<(), ParseErr> {
+        if parsed.sources.contains_key(name) {
+            return Ok(());
+        }
+        let arg = parsed
+            .args
+            .get_mut(name)
+            .ok_or_else(|| ParseErr::unknown_arg(name))?;
+        let values = self.split_delimited(name, values);
+        self.check_values(name, &values)?;
+        self.check_paths(name, &values)?;
+        let values = self.joined(name, None, self.transformed(name, values));
+        arg.set(name, values)?;
+        parsed.sources.insert(self.symbol(name), source);
+        Ok(())
+    }
+
+    /// `values` with each split at the delimiter of the list `name`, if it
+    /// has one.
+    pub(crate) fn split_delimited(&self, name: &str, values: Vec<String>) -> Vec<String> {
+        let spec = self.spec(name).filter(|spec| {
+            matches!(spec.kind, ArgKind::StrArray | ArgKind::NumberArray)
+        });
+        match spec.and_then(|spec| spec.delimiter) {
+            Some(delimiter) => values
+                .iter()
+                .flat_map(|value| value.split(delimiter).map(ToString::to_string))
+                .collect(),
+            None => values,
+        }
+    }
+
+    pub(crate) fn check_values(&self, name: &str, values: &[String]) -> Result<(), ParseErr> {
+        let choices = self.spec(name).map(|spec| &spec.choices[..]).unwrap_or(&[]);
+        let fold = self.spec(name).is_some_and(|spec| spec.fold_choices);
+        let allowed = |value: &String| match fold {
+            true => choices.iter().any(|choice| choice.to_lowercase() == value.to_lowercase()),
+            false => choices.contains(value),
+        };
+        if !choices.is_empty() && !values.iter().all(allowed) {
+            let message = format!("expected one of: {}", choices.join(", "));
+            return Err(ParseErr::ValidationFailed { arg: name.to_string(), message });
+        }
+        let validators = self.spec(name).map(|spec| &spec.validators[..]).unwrap_or(&[]);
+        for validator in validators {
+            for value in values {
+                (validator.0)(value)
+                    .map_err(|message| ParseErr::ValidationFailed {
+                        arg: name.to_string(),
+                        message,
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails if a value of `name` doesn't pass its [`PathCheck`], and
+    /// creates the missing directories of [`PathCheck::CreateParent`]. A
+    /// bare `-`, standing for standard input, passes any check.
+    fn check_paths(&self, name: &str, values: &[String]) -> Result<(), ParseErr> {
+        let check = match self.spec(name).and_then(|spec| spec.path_check) {
+            Some(check) => check,
+            None => return Ok(()),
+        };
+        for value in values.iter().filter(|value| *value != crate::STDIN) {
+            let path = Path::new(value);
+            let parent = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+            let (arg, value) = (name.to_string(), value.clone());
+            match check {
+                PathCheck::Exists | PathCheck::File | PathCheck::Dir if !path.exists() => {
+                    return Err(ParseErr::PathNotFound { arg, path: value });
+                }
+                PathCheck::File if !path.is_file() => {
+                    return Err(ParseErr::NotAFile { arg, path: value });
+                }
+                PathCheck::Dir if !path.is_dir() => {
+                    return Err(ParseErr::NotADirectory { arg, path: value });
+                }
+                PathCheck::ParentExists if !parent.is_none_or(Path::is_dir) => {
+                    return Err(ParseErr::ParentNotFound { arg, path: value });
+                }
+                PathCheck::CreateParent => {
+                    if let Some(dir) = parent {
+                        std::fs::create_dir_all(dir).map_err(|err| {
+                            let dir = dir.display().to_string();
+                            ParseErr::CreateDirFailed { arg, dir, reason: err.to_string() }
+                        })?;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// `values` mapped by each transform of `name` in turn, see
+    /// [`SchemaBuilder::transform`].
+    fn transformed(&self, name: &str, mut values: Vec<String>) -> Vec<String> {
+        let spec = match self.spec(name) {
+            Some(spec) => spec,
+            None => return values,
+        };
+        if spec.fold_choices {
+            for value in &mut values {
+                let choice = spec.choices.iter().find(|c| c.to_lowercase() == value.to_lowercase());
+                if let Some(choice) = choice {
+                    value.clone_from(choice);
+                }
+            }
+        }
+        for transform in &spec.transforms {
+            values = values.iter().map(|value| (transform.0)(value)).collect();
+        }
+        values
+    }
+
+    /// `values` of a string `name` as the single value they are joined to
+    /// with its separator, after `so_far` if it is being concatenated to.
+    fn joined(&self, name: &str, so_far: Option<String>, values: Vec<String>) -> Vec<String> {
+        let spec = match self.spec(name).filter(|spec| spec.kind.is_string()) {
+            Some(spec) if !values.is_empty() => spec,
+            _ => return values,
+        };
+        let separator = spec.separator.as_deref().unwrap_or(" ");
+        vec![so_far.into_iter().chain(values).collect::<Vec<_>>().join(separator)]
+    }
+
+    fn warn_if_deprecated(&self, name: &str, warnings: &mut Vec<ParseWarning>) {
+        let deprecation = self.deprecations.iter().find(|(arg, _)| arg == name);
+        if let Some((arg, replacement)) = deprecation {
+            let warning = ParseWarning::Deprecated(arg.clone(), replacement.clone());
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    /// Fails if a required argument is still missing, also one required by
+    /// [`Schema::required_if`], or a check added with
+    /// [`Schema::validate_with`] rejects the result.
+    pub(crate) fn check_parsed(&self, parsed: &ParsedArgs) -> Result<(), ParseErr> {
+        let missing = self
+            .specs
+            .iter()
+            .find(|spec| spec.required && !parsed.sources.contains_key(spec.name.as_str()));
+        if let Some(spec) = missing {
+            return Err(ParseErr::MissingArg(spec.name.clone()));
+        }
+        for constraint in &self.constraints {
+            if let Constraint::RequiredIf(arg, other, value) = constraint {
+                let set = parsed.get_str(other).is_some_and(|set| set == *value);
+                if set && !parsed.sources.contains_key(arg.as_str()) {
+                    let err = ParseErr::RequiredIf {
+                        arg: arg.clone(),
+                        other: other.clone(),
+                        value: value.clone(),
+                    };
+                    return Err(err);
+                }
+            }
+        }
+        self.checks.iter().try_for_each(|check| (check.0)(parsed))
+    }
+
+    fn check_constraints(&self, present: &[String]) -> Result<(), ParseErr> {
+        match self.constraint_errors(present).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Every constraint broken by the arguments in `present`, in
+    /// declaration order.
+    pub(crate) fn constraint_errors(&self, present: &[String]) -> Vec<ParseErr> {
+        let is_present = |name: &str| present.iter().any(|p| p == name);
+        let mut errors = vec![];
+        for constraint in &self.constraints {
+            let (arg, other) = match constraint {
+                Constraint::Requires(arg, other)
+                | Constraint::ConflictsWith(arg, other)
+                | Constraint::RequiredIf(arg, other, _) => (arg, other),
+                Constraint::OneOf(args, exactly) => {
+                    if args.is_empty() || args.iter().any(|arg| self.spec(arg).is_none()) {
+                        errors.push(ParseErr::InvalidSchema);
+                        continue;
+                    }
+                    let mut given = args.iter().filter(|arg| is_present(arg));
+                    match (given.next(), given.next()) {
+                        (None, _) => errors.push(ParseErr::MissingOneOf(args.clone())),
+                        (Some(arg), Some(other)) if *exactly => {
+                            errors.push(ParseErr::ConflictingArgs {
+                                arg: arg.clone(),
+                                other: other.clone(),
+                            })
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+            };
+            if self.spec(arg).is_none() || self.spec(other).is_none() {
+                errors.push(ParseErr::InvalidSchema);
+                continue;
+            }
+            match constraint {
+                Constraint::Requires(..) if is_present(arg) && !is_present(other) => {
+                    errors.push(ParseErr::MissingRequiredArg {
+                        arg: arg.clone(),
+                        required: other.clone(),
+                    });
+                }
+                Constraint::ConflictsWith(..) if is_present(arg) && is_present(other) => {
+                    errors.push(ParseErr::ConflictingArgs {
+                        arg: arg.clone(),
+                        other: other.clone(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        errors
+    }
+}
+
+/// Why a definition isn't a valid schema: the error of parsing one with
+/// [`str::parse`] or `TryFrom<&str>`, a type of its own so it can't be
+/// mistaken for an input that failed to parse. [`Schema::new`] returns the
+/// [`ParseErr`] inside, so schema and input errors can share one `?`.
+#[derive(PartialEq, Debug)]
+pub struct SchemaError(ParseErr);
+
+impl SchemaError {
+    /// The [`ParseErr`] describing what is wrong with the definition.
+    pub fn parse_err(&self) -> &ParseErr {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for SchemaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl From<SchemaError> for ParseErr {
+    fn from(err: SchemaError) -> Self {
+        err.0
+    }
+}
+
+impl std::str::FromStr for Schema {
+    type Err = SchemaError;
+
+    fn from_str(definition: &str) -> Result<Self, SchemaError> {
+        Schema::new(definition).map_err(SchemaError)
+    }
+}
+
+impl std::convert::TryFrom<&str> for Schema {
+    type Error = SchemaError;
+
+    fn try_from(definition: &str) -> Result<Self, SchemaError> {
+        Schema::new(definition).map_err(SchemaError)
+    }
+}
+
+/// Builds a [`Schema`] in code instead of from the string DSL:
+///
+/// ```
+/// let schema = args::Schema::builder()
+///     .bool("l")
+///     .string("d")
+///     .description("log directory")
+///     .number("p")
+///     .default("8080")
+///     .build()
+///     .unwrap();
+/// assert_eq!(schema.usage("app"), "usage: app [-l] [-d <string>] [-p <number>]");
+/// ```
+///
+/// [`default`](SchemaBuilder::default), [`description`](SchemaBuilder::description)
+/// and [`validator`](SchemaBuilder::validator) apply to the argument added last.
+pub struct SchemaBuilder {
+    specs: Vec<ArgSpec>,
+    misplaced: bool,
+}
+
+impl SchemaBuilder {
+    fn arg(mut self, name: &str, kind: ArgKind) -> Self {
+        self.specs.push(ArgSpec::new(name, kind));
+        self
+    }
+
+    fn last(mut self, update: impl FnOnce(&mut ArgSpec)) -> Self {
+        match self.specs.last_mut() {
+            Some(spec) => update(spec),
+            None => self.misplaced = true,
+        }
+        self
+    }
+
+    pub fn bool(self, name: &str) -> Self {
+        self.arg(name, ArgKind::Bool)
+    }
+
+    pub fn string(self, name: &str) -> Self {
+        self.arg(name, ArgKind::Str)
+    }
+
+    /// A string that may also be given without a value; see
+    /// [`SchemaBuilder::implicit_value`].
+    pub fn optional_string(self, name: &str) -> Self {
+        self.arg(name, ArgKind::OptStr)
+    }
+
+    /// A required string bound by position rather than by a flag, after
+    /// any positional arguments added before it.
+    pub fn positional(mut self, name: &str) -> Self {
+        let mut spec = ArgSpec::new(name, ArgKind::Str);
+        spec.positional = true;
+        spec.required = true;
+        self.specs.push(spec);
+        self
+    }
+
+    /// A list of strings taking every word after the other positional
+    /// arguments. It must be the last positional argument added.
+    pub fn variadic(mut self, name: &str) -> Self {
+        let mut spec = ArgSpec::new(name, ArgKind::StrArray);
+        spec.positional = true;
+        self.specs.push(spec);
+        self
+    }
+
+    pub fn number(self, name: &str) -> Self {
+        self.arg(name, ArgKind::Number)
+    }
+
+    pub fn string_list(self, name: &str) -> Self {
+        self.arg(name, ArgKind::StrArray)
+    }
+
+    pub fn number_list(self, name: &str) -> Self {
+        self.arg(name, ArgKind::NumberArray)
+    }
+
+    pub fn default(self, value: &str) -> Self {
+        self.last(|spec| spec.default = Some(value.to_string()))
+    }
+
+    /// Defaults the last argument to `default` while `other` has `value`,
+    /// see [`Schema::default_if`].
+    pub fn default_if(self, other: &str, value: &str, default: &str) -> Self {
+        let rule = (other.to_string(), value.to_string(), default.to_string());
+        self.last(|spec| spec.defaults_if.push(rule))
+    }
+
+    pub fn description(self, text: &str) -> Self {
+        self.last(|spec| spec.description = Some(text.to_string()))
+    }
+
+    /// The value the last argument, an [`SchemaBuilder::optional_string`],
+    /// takes when given without one. Without it that value is empty.
+    pub fn implicit_value(self, value: &str) -> Self {
+        self.last(|spec| spec.implicit = Some(value.to_string()))
+    }
+
+    /// The name standing for the last argument's value in usage, e.g.
+    /// `PORT` for `[-p PORT]`, instead of its type.
+    pub fn metavar(self, name: &str) -> Self {
+        self.last(|spec| spec.metavar = Some(name.to_string()))
+    }
+
+    /// Limits the last argument to `values`, failing with
+    /// [`ParseErr::ValidationFailed`] on any other.
+    pub fn choices(self, values: &[&str]) -> Self {
+        self.last(|spec| spec.choices = values.iter().map(ToString::to_string).collect())
+    }
+
+    /// Limits the last argument to the variants of `T`, matched regardless
+    /// of case and stored spelled as declared, so that
+    /// [`ParsedArgs::get_as`] reads it back as a `T`.
+    pub fn choices_of<T: crate::ArgEnum>(self) -> Self {
+        self.choices(T::VARIANTS).last(|spec| spec.fold_choices = true)
+    }
+
+    /// What shell completion offers as the last argument's value, see
+    /// [`Schema::completion_script`].
+    pub fn hint(self, hint: ValueHint) -> Self {
+        self.last(|spec| spec.hint = Some(hint))
+    }
+
+    /// Requires the path the last argument, a string or list of strings,
+    /// names to pass `check` on disk, e.g. [`PathCheck::File`] for an input
+    /// file. Checks run once the value is read from the command line, the
+    /// environment or a config file, not on defaults, and fail with
+    /// [`ParseErr::PathNotFound`], [`ParseErr::NotAFile`],
+    /// [`ParseErr::NotADirectory`], [`ParseErr::ParentNotFound`] or
+    /// [`ParseErr::CreateDirFailed`].
+    pub fn path_check(self, check: PathCheck) -> Self {
+        self.last(|spec| spec.path_check = Some(check))
+    }
+
+    /// The environment variable the last argument falls back to, see
+    /// [`Schema::env`].
+    pub fn env(self, var: &str) -> Self {
+        self.last(|spec| spec.env = Some(var.to_string()))
+    }
+
+    /// Makes the last argument mandatory; a default also satisfies it.
+    pub fn required(self) -> Self {
+        self.last(|spec| spec.required = true)
+    }
+
+    /// Rejects values for which `validator` returns an error message, with
+    /// [`ParseErr::ValidationFailed`].
+    pub fn validator<F>(self, validator: F) -> Self
+    where
+        F: Fn(&str) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.last(|spec| spec.validators.push(Validator(Arc::new(validator))))
+    }
+
+    /// Maps every value of the last argument with `transform` once it has
+    /// passed validation, before it is stored, e.g. to lowercase it or to
+    /// strip a URL's trailing slash. Transforms added one after another run
+    /// in that order; values from the environment, config files and
+    /// defaults go through them too.
+    pub fn transform<F>(self, transform: F) -> Self
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        self.last(|spec| spec.transforms.push(Transform(Arc::new(transform))))
+    }
+
+    /// Hands the builder to `f`, so that shared sets of arguments can be
+    /// written as plain functions: `builder.with(logging_args)`.
+    pub fn with(self, f: impl FnOnce(Self) -> Self) -> Self {
+        f(self)
+    }
+
+    /// Fails with [`ParseErr::InvalidSchema`] on empty names, or when an
+    /// attribute was set before any argument was added or on an argument it
+    /// doesn't apply to, with [`ParseErr::DuplicateArg`] on a name declared
+    /// twice, and with the usual value errors when a default doesn't fit
+    /// its argument.
+    pub fn build(self) -> Result<Schema, ParseErr> {
+        if self.misplaced || self.specs.is_empty() || variadic_not_last(&self.specs) {
+            return Err(ParseErr::InvalidSchema);
+        }
+        check_duplicates(&self.specs)?;
+        for spec in &self.specs {
+            let invalid_name = spec.name.is_empty()
+                || spec.name.starts_with('-')
+                || spec.name.chars().any(char::is_whitespace);
+            let misplaced_implicit = spec.implicit.is_some() && spec.kind != ArgKind::OptStr;
+            let takes_value = spec.kind != ArgKind::Bool;
+            let misplaced_metavar = spec.metavar.is_some() && (!takes_value || spec.positional);
+            let misplaced_value_attr =
+                (!spec.choices.is_empty() || spec.hint.is_some()) && !takes_value;
+            let misplaced_path_check = spec.path_check.is_some()
+                && !matches!(spec.kind, ArgKind::Str | ArgKind::OptStr | ArgKind::StrArray);
+            if invalid_name
+                || misplaced_implicit
+                || misplaced_metavar
+                || misplaced_value_attr
+                || misplaced_path_check
+            {
+                return Err(ParseErr::InvalidSchema);
+            }
+        }
+        let schema = Schema::from_specs(self.specs);
+        for spec in &schema.specs {
+            let conditional = spec.defaults_if.iter().map(|(_, _, default)| default);
+            for default in spec.default.iter().chain(conditional) {
+                schema.check_values(&spec.name, std::slice::from_ref(default))?;
+                spec.new_arg().set(&spec.name, vec![default.clone()])?;
+            }
+            if spec.defaults_if.iter().any(|(other, _, _)| schema.spec(other).is_none()) {
+                return Err(ParseErr::InvalidSchema);
+            }
+        }
+        Ok(schema)
+    }
+}
+
+/// Builds a [`Schema`] from `name: type` pairs, where the type is one of
+/// the [`SchemaBuilder`] methods (`bool`, `string`, `number`,
+/// `string_list`, `number_list`) and is checked at compile time. Names can
+/// be identifiers or string literals, and a literal default may follow:
+///
+/// ```
+/// let schema = args::schema! { l: bool, d: string, p: number = 8080, "log-file": string };
+/// assert_eq!(
+///     schema.usage("app"),
+///     "usage: app [-l] [-d <string>] [-p <number>] [--log-file <string>]"
+/// );
+/// ```
+///
+/// Panics if a name is repeated or a default doesn't fit its type.
+#[macro_export]
+macro_rules! schema {
+    ($($name:tt : $kind:ident $(= $default:literal)?),* $(,)?) => {
+        $crate::Schema::builder()
+            $(
+                .$kind($crate::schema!(@name $name))
+                $(.default(&$default.to_string()))?
+            )*
+            .build()
+            .expect("invalid schema! definition")
+    };
+    (@name $name:ident) => {
+        stringify!($name)
+    };
+    (@name $name:literal) => {
+        $name
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assert_parse_err, assert_parses};
+
+    #[test]
+    fn multi_line_schema_with_comments() {
+        let schema = Schema::new(
+            "
+            # general
+            l,        # verbose logging
+            p# d*     # port and directory
+            s[*],
+            ",
+        )
+        .unwrap();
+        assert_eq!(
+            schema.usage("app"),
+            "usage: app [-l] [-p <number>] [-d <string>] [-s <string>...]"
+        );
+    }
+
+    #[test]
+    fn case_insensitive_flags() {
+        let schema = Schema::new("p#,l").unwrap();
+        assert_eq!(
+            schema.parse("-P 8080").unwrap_err(),
+            ParseErr::UnknownArg {
+                arg: "P".into(),
+                typed: "-P".into(),
+                suggestion: Some("-p".into()),
+            }
+        );
+        let args = schema.case_insensitive().parse("-P 8080 -L").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+    }
+
+    #[test]
+    fn case_insensitive_prefers_exact_match() {
+        let schema = Schema::new("p#,P*").unwrap().case_insensitive();
+        let args = schema.parse("-P upper -p 1").unwrap();
+        assert_eq!(args.get("P").unwrap().get().unwrap(), "upper");
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 1);
+    }
+
+    #[test]
+    fn case_insensitive_switches() {
+        let schema = Schema::new("c")
+            .unwrap()
+            .inverted("disable-cache", "c")
+            .case_insensitive();
+        let args = schema.parse("--Disable-Cache").unwrap();
+        assert!(!args.get("c").unwrap().as_bool().unwrap());
+    }
+
+    #[test]
+    fn single_dash_long_flags() {
+        let schema = Schema::builder()
+            .string("name")
+            .string("type")
+            .bool("x")
+            .bool("v")
+            .build()
+            .unwrap()
+            .single_dash();
+        let args = schema.parse("-name '*.rs' -type=f --x").unwrap();
+        assert_eq!(args.get_str("name"), Some("*.rs".to_string()));
+        assert_eq!(args.get_str("type"), Some("f".to_string()));
+        assert_eq!(args.get_bool("x"), Some(true));
+        assert_eq!(schema.parse("-xv").unwrap_err(), ParseErr::UnknownArg {
+            arg: "xv".into(),
+            typed: "-xv".into(),
+            suggestion: Some("-v".into()),
+        });
+        assert_eq!(schema.usage("find"), "usage: find [-name <string>] [-type <string>] [-x] [-v]");
+        assert_eq!(schema.canonical_command_line("--name a  -v"), "-name a -v");
+    }
+
+    #[test]
+    fn abbreviated_long_flags() {
+        let schema = Schema::builder()
+            .bool("verbose")
+            .bool("version")
+            .number("port")
+            .string("portal")
+            .bool("v")
+            .build()
+            .unwrap()
+            .abbreviations();
+        let args = schema.parse("--verb --port 80 --porta=a").unwrap();
+        assert_eq!(args.get_bool("verbose"), Some(true));
+        assert_eq!(args.get_number("port"), Some(80));
+        assert_eq!(args.get_str("portal"), Some("a".to_string()));
+        assert_eq!(
+            args.normalizations()[..2],
+            [
+                Normalization::Abbreviated("verb".to_string(), "verbose".to_string()),
+                Normalization::Abbreviated("porta".to_string(), "portal".to_string()),
+            ]
+        );
+        let err = schema.parse("--ver").unwrap_err();
+        let candidates = vec!["verbose".to_string(), "version".to_string()];
+        assert_eq!(err, ParseErr::AmbiguousArg { given: "ver".to_string(), candidates });
+        assert_eq!(err.to_string(), "'--ver' is ambiguous: --verbose, --version");
+        assert_eq!(schema.parse("-v").unwrap().get_bool("version"), Some(false));
+        let schema = Schema::builder().bool("verbose").build().unwrap();
+        let err = schema.parse("--verb").unwrap_err();
+        assert_eq!(err, ParseErr::UnknownArg {
+            arg: "verb".into(),
+            typed: "--verb".into(),
+            suggestion: None,
+        });
+    }
+
+    #[test]
+    fn optional_value_args() {
+        let schema = Schema::builder()
+            .optional_string("color")
+            .implicit_value("auto")
+            .default("never")
+            .bool("l")
+            .build()
+            .unwrap();
+        assert_eq!(schema.usage("app"), "usage: app [--color [<string>]] [-l]");
+        let bare = schema.parse("--color -l").unwrap();
+        assert_eq!(bare.get("color").unwrap().presence(), Presence::Bare);
+        assert_eq!(bare.get("color").unwrap().get().unwrap(), "auto");
+        let given = schema.parse("--color always").unwrap();
+        assert_eq!(
+            given.get("color").unwrap().presence(),
+            Presence::Value("always".to_string())
+        );
+        let absent = schema.parse("-l").unwrap();
+        assert_eq!(absent.get("color").unwrap().get().unwrap(), "never");
+        let dsl = Schema::new("c*?").unwrap().parse("-c").unwrap();
+        assert_eq!(dsl.get("c").unwrap().get().unwrap(), "");
+        assert_eq!(
+            Schema::builder().string("d").implicit_value("x").build().unwrap_err(),
+            ParseErr::InvalidSchema
+        );
+    }
+
+    #[test]
+    fn occurrence_limits() {
+        let schema = Schema::new("p#,s[*],v").unwrap().max_occurrences("v", Some(3));
+        assert_eq!(
+            schema.parse("-p 1 -p 2").unwrap_err(),
+            ParseErr::TooManyOccurrences { arg: "p".to_string(), max: 1 }
+        );
+        assert!(schema.parse("-s a -s b -s c -v -v -v").is_ok());
+        assert_eq!(
+            schema.parse("-v -v -v -v").unwrap_err(),
+            ParseErr::TooManyOccurrences { arg: "v".to_string(), max: 3 }
+        );
+        let unlimited = schema.max_occurrences("p", None);
+        let args = unlimited.parse("-p 1 -p 2").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 2);
+    }
+
+    #[test]
+    fn merging_schemas() {
+        let logging = Schema::new("v,q").unwrap().conflicts_with("v", "q");
+        let schema = Schema::new("p#").unwrap().merge(logging).unwrap();
+        assert_eq!(schema.usage("app"), "usage: app [-p <number>] [-v] [-q]");
+        assert_eq!(
+            schema.parse("-v -q").unwrap_err(),
+            ParseErr::ConflictingArgs { arg: "v".to_string(), other: "q".to_string() }
+        );
+        let clash = Schema::new("p*").unwrap();
+        assert_eq!(
+            Schema::new("p#").unwrap().merge(clash).unwrap_err(),
+            ParseErr::DuplicateArg("p".to_string())
+        );
+        let switch = Schema::new("c").unwrap().inverted("p", "c");
+        assert_eq!(
+            Schema::new("p#").unwrap().merge(switch).unwrap_err(),
+            ParseErr::DuplicateArg("p".to_string())
+        );
+    }
+
+    #[test]
+    fn reports_case_folding() {
+        let schema = Schema::new("p#,l").unwrap().case_insensitive();
+        let args = schema.parse("-P 8080 -l").unwrap();
+        assert_eq!(
+            args.normalizations(),
+            &[Normalization::CaseFolded("P".to_string(), "p".to_string())]
+        );
+        assert_eq!(args.normalizations()[0].to_string(), "-P was read as -p");
+        assert!(schema.parse("-p 1").unwrap().normalizations().is_empty());
+    }
+
+    #[test]
+    fn keeps_the_tokens_read() {
+        let schema = Schema::new("p#,d*,l,v,<file>").unwrap().case_insensitive();
+        let args = schema.parse("-P 8080 a.txt -lv -d 'my logs'").unwrap();
+        let tokens: Vec<_> = args
+            .tokens()
+            .iter()
+            .map(|token| (token.name.as_str(), token.values.clone(), token.text.as_str()))
+            .collect();
+        assert_eq!(
+            tokens,
+            vec![
+                ("P", vec!["8080".to_string()], "-P 8080"),
+                ("l", vec![], "-lv"),
+                ("v", vec![], "-lv"),
+                ("d", vec!["my logs".to_string()], "-d 'my logs'"),
+            ]
+        );
+        assert_eq!(args.tokens()[3].span, 18..30);
+        assert!(schema.parse("a.txt").unwrap().tokens().is_empty());
+    }
+
+    #[test]
+    fn equals_sign_values() {
+        let schema = Schema::builder().number("port").bool("l").number("p").build().unwrap();
+        let args = schema.parse("--port=8080 -p=1 -l").unwrap();
+        assert_eq!(args.get("port").unwrap().as_number().unwrap(), 8080);
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 1);
+        let optional = Schema::builder().optional_string("color").build().unwrap();
+        let args = optional.parse("--color=never").unwrap();
+        assert_eq!(args.get("color").unwrap().presence(), Presence::Value("never".to_string()));
+    }
+
+    #[test]
+    fn attached_short_values() {
+        let schema = Schema::new("p#,d*,l,n[#]").unwrap();
+        assert_parses!(schema, "-p8080 -dfoo=bar -l -n1 2", { p: 8080, d: "foo=bar", n: [1, 2] });
+        assert_parse_err!(schema, "-lx", ParseErr::UnknownArg { arg, .. } if arg == "lx");
+        assert_parse_err!(schema, "--p8080", ParseErr::UnknownArg { arg, .. } if arg == "p8080");
+    }
+
+    #[test]
+    fn combined_short_bools() {
+        let schema = Schema::new("l,r,t,p#").unwrap();
+        assert_parses!(schema, "-lrt", { l: true, r: true, t: true });
+        assert_parses!(schema, "-rp 8080", { r: true, p: 8080 });
+        assert_parses!(schema, "-lp8080 -t", { p: 8080 });
+        assert_parse_err!(schema, "-lx", ParseErr::UnknownArg { arg, .. } if arg == "lx");
+        assert_parse_err!(
+            schema,
+            "-ll",
+            ParseErr::TooManyOccurrences { arg, max: 1 } if arg == "l"
+        );
+    }
+
+    #[test]
+    fn attached_values_never_shadow_names() {
+        let schema = Schema::builder().string("d").bool("dry").build().unwrap();
+        let args = schema.parse("-dry").unwrap();
+        assert!(args.get("dry").unwrap().as_bool().unwrap());
+        assert!(args.get("d").unwrap().get().is_none());
+        let replayed = Schema::new("p#").unwrap().canonical_command_line("-p8080");
+        assert_eq!(replayed, "-p 8080");
+    }
+
+    #[test]
+    fn trailing_words_after_separator() {
+        let schema = Schema::new("l,s[*]").unwrap();
+        let args = schema.parse("-s a -- -l 'b c' --").unwrap();
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a"]);
+        assert!(!args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(args.trailing(), &["-l", "b c", "--"]);
+        assert!(schema.parse("-l").unwrap().trailing().is_empty());
+        assert_eq!(schema.canonical_command_line("-l -- -x 'y z'"), "-l -- \"-x\" \"y z\"");
+    }
+
+    #[test]
+    fn quoted_values() {
+        let schema = Schema::new("d*,s[*]").unwrap();
+        let args = schema.parse(r#"-d "/var/my logs" -s 'a b' c"#).unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/my logs");
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a b", "c"]);
+        assert_eq!(
+            args.normalizations(),
+            &[
+                Normalization::Unquoted("\"/var/my logs\"".to_string(), "/var/my logs".to_string()),
+                Normalization::Unquoted("'a b'".to_string(), "a b".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn fingerprint_depends_on_definition_only() {
+        let schema = Schema::new("l,p#").unwrap();
+        assert_eq!(schema.fingerprint(), Schema::new("l\np#  # port").unwrap().fingerprint());
+        assert_eq!(schema.fingerprint(), Schema::new("l,p#").unwrap().hidden("l").fingerprint());
+        assert_ne!(schema.fingerprint(), Schema::new("l,p*").unwrap().fingerprint());
+        assert_ne!(schema.fingerprint(), Schema::new("p#,l").unwrap().fingerprint());
+        assert_eq!(schema.fingerprint().len(), 16);
+    }
+
+    #[test]
+    fn builder_creates_typed_args() {
+        let schema = Schema::builder()
+            .bool("l")
+            .string("d")
+            .number("p")
+            .string_list("s")
+            .number_list("n")
+            .build()
+            .unwrap();
+        let args = schema.parse("-l -d /var/logs -p 8080 -s a b -n 1 2").unwrap();
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/logs");
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a", "b"]);
+        assert_eq!(args.get("n").unwrap().as_num_array(), vec![1, 2]);
+    }
+
+    #[test]
+    fn builder_supports_long_names() {
+        let schema = Schema::builder().number("port").bool("v").build().unwrap();
+        assert_eq!(schema.usage("app"), "usage: app [--port <number>] [-v]");
+        let args = schema.parse("--port 8080").unwrap();
+        assert_eq!(args.get("port").unwrap().as_number().unwrap(), 8080);
+    }
+
+    #[test]
+    fn builder_defaults_and_descriptions() {
+        let schema = Schema::builder()
+            .number("p")
+            .default("8080")
+            .description("port")
+            .build()
+            .unwrap();
+        assert_eq!(schema.description("p"), Some("port"));
+        let args = schema.parse("").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert_eq!(args.source("p"), Some(Source::Default));
+    }
+
+    #[test]
+    fn builder_validators() {
+        let schema = Schema::builder()
+            .number("p")
+            .validator(|v| match v.parse::<isize>() {
+                Ok(port) if port <= 1024 => Err("port must be > 1024".to_string()),
+                _ => Ok(()),
+            })
+            .build()
+            .unwrap();
+        assert!(schema.parse("-p 8080").is_ok());
+        assert_eq!(
+            schema.parse("-p 80").unwrap_err(),
+            ParseErr::ValidationFailed {
+                arg: "p".to_string(),
+                message: "port must be > 1024".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn conditional_defaults() {
+        let schema = Schema::builder()
+            .number("port")
+            .default("80")
+            .default_if("tls", "true", "443")
+            .bool("tls")
+            .string("m")
+            .build()
+            .unwrap()
+            .default_if("m", "port", "443", "secure");
+        let args = schema.parse("--tls").unwrap();
+        assert_eq!(args.get_number("port"), Some(443));
+        assert_eq!(args.source("port"), Some(Source::Default));
+        assert_eq!(args.get_str("m"), Some("secure".to_string()));
+        let args = schema.parse("").unwrap();
+        assert_eq!((args.get_number("port"), args.get_str("m")), (Some(80), None));
+        assert_eq!(schema.parse("--tls --port 8443").unwrap().get_number("port"), Some(8443));
+        assert!(schema.help("app").contains("[default: 80; default: 443 when --tls is set]"));
+        let unknown = Schema::builder().number("p").default_if("tls", "true", "443").build();
+        assert_eq!(unknown.unwrap_err(), ParseErr::InvalidSchema);
+        let misfit = Schema::builder().bool("l").number("p").default_if("l", "true", "x").build();
+        assert!(misfit.is_err());
+    }
+
+    #[test]
+    fn path_checks() {
+        let dir = std::env::temp_dir().join(format!("args-paths-{}", std::process::id()));
+        let file = dir.join("in.txt");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&file, "").unwrap();
+        let schema = Schema::builder()
+            .string("in")
+            .path_check(PathCheck::File)
+            .string("root")
+            .path_check(PathCheck::Dir)
+            .string("out")
+            .path_check(PathCheck::ParentExists)
+            .string_list("log")
+            .path_check(PathCheck::CreateParent)
+            .build()
+            .unwrap();
+        let (dir, file) = (dir.display().to_string(), file.display().to_string());
+        let input = format!("--in '{}' --root '{}' --out '{}/out.txt' --log -", file, dir, dir);
+        assert!(schema.parse(&input).is_ok());
+        let err = |arg: &str, value: &str| {
+            schema.parse(&format!("--{} '{}'", arg, value)).unwrap_err()
+        };
+        let missing = format!("{}/missing/out.txt", dir);
+        assert_eq!(err("in", &dir), ParseErr::NotAFile {
+            arg: "in".to_string(),
+            path: dir.clone(),
+        });
+        assert_eq!(err("root", &file), ParseErr::NotADirectory {
+            arg: "root".to_string(),
+            path: file.clone(),
+        });
+        assert_eq!(err("out", &missing), ParseErr::ParentNotFound {
+            arg: "out".to_string(),
+            path: missing,
+        });
+        let gone = format!("{}/gone", dir);
+        assert_eq!(err("in", &gone), ParseErr::PathNotFound {
+            arg: "in".to_string(),
+            path: gone.clone(),
+        });
+        assert_eq!(err("in", &gone).to_string(), format!("--in: '{}' does not exist", gone));
+        assert!(schema.parse(&format!("--log '{}/logs/a/x.log'", dir)).is_ok());
+        assert!(Path::new(&format!("{}/logs/a", dir)).is_dir());
+        let misplaced = Schema::builder().number("p").path_check(PathCheck::Exists).build();
+        assert_eq!(misplaced.unwrap_err(), ParseErr::InvalidSchema);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn builder_transforms() {
+        let schema = Schema::builder()
+            .string("url")
+            .validator(|v| match v.starts_with("https://") {
+                true => Ok(()),
+                false => Err("must use https".to_string()),
+            })
+            .transform(|v| v.trim_end_matches('/').to_string())
+            .transform(str::to_lowercase)
+            .string_list("tags")
+            .default("A,B")
+            .transform(str::to_lowercase)
+            .build()
+            .unwrap();
+        let args = schema.parse("--url https://Example.com/ --tags X Y").unwrap();
+        assert_eq!(args.get_str("url"), Some("https://example.com".to_string()));
+        assert_eq!(args.get("tags").unwrap().as_str_array(), vec!["x", "y"]);
+        assert_eq!(args.values_of("url"), Some(&["https://Example.com/".to_string()][..]));
+        let args = schema.parse("").unwrap();
+        assert_eq!(args.get("tags").unwrap().as_str_array(), vec!["a", "b"]);
+        assert!(schema.parse("--url http://example.com").is_err());
+    }
+
+    #[test]
+    fn validators_check_every_value() {
+        let schema = Schema::builder()
+            .string_list("f")
+            .validator(|v| match v {
+                v if v.contains("..") => Err("path must not contain ..".to_string()),
+                _ => Ok(()),
+            })
+            .validator(|v| match v {
+                "" => Err("path must not be empty".to_string()),
+                _ => Ok(()),
+            })
+            .build()
+            .unwrap();
+        assert!(schema.parse("-f a/b c").is_ok());
+        assert_eq!(
+            schema.parse("-f a ../etc").unwrap_err(),
+            ParseErr::ValidationFailed {
+                arg: "f".to_string(),
+                message: "path must not contain ..".to_string(),
+            }
+        );
+        assert_eq!(
+            schema.parse("-f a ''").unwrap_err(),
+            ParseErr::ValidationFailed {
+                arg: "f".to_string(),
+                message: "path must not be empty".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn builder_rejects_invalid_definitions() {
+        assert_eq!(Schema::builder().build().unwrap_err(), ParseErr::InvalidSchema);
+        assert_eq!(
+            Schema::builder().default("1").number("p").build().unwrap_err(),
+            ParseErr::InvalidSchema
+        );
+        assert_eq!(
+            Schema::builder().bool("l").string("l").build().unwrap_err(),
+            ParseErr::DuplicateArg("l".to_string())
+        );
+        assert_eq!(
+            Schema::builder().number("p").default("http").build().unwrap_err(),
+            ParseErr::NumberFormatErr { arg: "p".into(), value: "http".into(), span: None }
+        );
+    }
+
+    #[test]
+    fn builders_compose() {
+        fn logging(builder: SchemaBuilder) -> SchemaBuilder {
+            builder.bool("v").string("log-file")
+        }
+        let schema = Schema::builder().number("p").with(logging).build().unwrap();
+        assert_eq!(
+            schema.usage("app"),
+            "usage: app [-p <number>] [-v] [--log-file <string>]"
+        );
+    }
+
+    #[test]
+    fn lists_declared_args() {
+        let schema = Schema::builder()
+            .bool("l")
+            .number("p")
+            .required()
+            .default("8080")
+            .string("d")
+            .description("log directory")
+            .build()
+            .unwrap();
+        let listed: Vec<_> = schema
+            .args()
+            .map(|spec| (spec.name(), spec.kind(), spec.is_required(), spec.default_value()))
+            .collect();
+        assert_eq!(
+            listed,
+            vec![
+                ("l", ArgKind::Bool, false, None),
+                ("p", ArgKind::Number, true, Some("8080")),
+                ("d", ArgKind::Str, false, None),
+            ]
+        );
+        assert_eq!(schema.args().nth(2).unwrap().description(), Some("log directory"));
+    }
+
+    #[test]
+    fn required_args() {
+        let schema = Schema::builder().bool("l").string("d").required().build().unwrap();
+        assert_eq!(schema.usage("app"), "usage: app [-l] -d <string>");
+        assert_eq!(schema.parse("-l").unwrap_err(), ParseErr::MissingArg("d".to_string()));
+        assert!(schema.parse("-d /tmp").is_ok());
+    }
+
+    #[test]
+    fn env_fallback_per_arg() {
+        std::env::set_var("SCHEMA_TEST_PORT", "9090");
+        std::env::set_var("SCHEMA_TEST_DIR", "/env");
+        let schema = Schema::builder()
+            .number("p")
+            .env("SCHEMA_TEST_PORT")
+            .default("8080")
+            .string("d")
+            .default("/tmp")
+            .build()
+            .unwrap()
+            .env("d", "SCHEMA_TEST_DIR");
+        let args = schema.parse("").unwrap();
+        assert_eq!(args.get_number("p"), Some(9090));
+        assert_eq!(args.source("p"), Some(Source::Env));
+        assert_eq!(args.get_str("d"), Some("/env".to_string()));
+        let args = schema.parse("-p 1").unwrap();
+        assert_eq!(args.get_number("p"), Some(1));
+        assert_eq!(args.source("p"), Some(Source::CommandLine));
+        std::env::remove_var("SCHEMA_TEST_DIR");
+        let args = schema.parse("").unwrap();
+        assert_eq!(args.source("d"), Some(Source::Default));
+        assert_eq!(schema.spec("p").unwrap().env_var(), Some("SCHEMA_TEST_PORT"));
+        std::env::set_var("SCHEMA_TEST_PORT", "x");
+        let err = ParseErr::NumberFormatErr { arg: "p".into(), value: "x".into(), span: None };
+        assert_eq!(schema.parse("").unwrap_err(), err);
+    }
+
+    #[test]
+    fn metavars_in_usage() {
+        let schema = Schema::builder()
+            .bool("l")
+            .number("p")
+            .metavar("PORT")
+            .string("d")
+            .metavar("DIR")
+            .required()
+            .string_list("s")
+            .metavar("HOST")
+            .optional_string("c")
+            .metavar("WHEN")
+            .number("n")
+            .build()
+            .unwrap();
+        assert_eq!(
+            schema.usage("app"),
+            "usage: app [-l] [-p PORT] -d DIR [-s HOST...] [-c [WHEN]] [-n <number>]"
+        );
+        assert_eq!(schema.spec("p").unwrap().metavar(), Some("PORT"));
+        let misplaced = Schema::builder().bool("l").metavar("ON").build();
+        assert_eq!(misplaced.unwrap_err(), ParseErr::InvalidSchema);
+    }
+
+    #[test]
+    fn schema_macro() {
+        let schema = schema! { l: bool, d: string, p: number = 8080, s: string_list, };
+        let args = schema.parse("-l -s a b").unwrap();
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a", "b"]);
+        assert!(args.get("d").unwrap().get().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid schema! definition")]
+    fn schema_macro_rejects_duplicates() {
+        schema! { l: bool, l: string };
+    }
+
+    #[test]
+    fn trailing_and_repeated_separators_are_ignored() {
+        let schema = Schema::new("l,,p#,").unwrap();
+        assert_eq!(schema.usage("app"), "usage: app [-l] [-p <number>]");
+    }
+
+    #[test]
+    fn schema_of_only_comments_is_invalid() {
+        assert_eq!(Schema::new("# nothing here\n,").unwrap_err(), ParseErr::InvalidSchema);
+    }
+
+    #[test]
+    fn invalid_marker_is_still_reported() {
+        assert_eq!(
+            Schema::new("l\np!  # port").unwrap_err(),
+            ParseErr::UnsupportedArgType("!".to_string())
+        );
+    }
+
+    #[test]
+    fn positionals_are_bound_in_order() {
+        let schema = Schema::new("l,<input>,<output>").unwrap();
+        assert_parses!(schema, "in.txt -l out.txt", {
+            l: true,
+            input: "in.txt",
+            output: "out.txt",
+        });
+        assert_eq!(schema.usage("cp"), "usage: cp [-l] <input> <output>");
+    }
+
+    #[test]
+    fn positionals_after_values() {
+        let schema = Schema::builder().number("p").positional("input").build().unwrap();
+        let args = schema.parse("-p 8080 in.txt").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert_eq!(args.get("input").unwrap().get().unwrap(), "in.txt");
+        let args = schema.parse("-- -in.txt").unwrap();
+        assert_eq!(args.get("input").unwrap().get().unwrap(), "-in.txt");
+    }
+
+    #[test]
+    fn missing_and_extra_positionals() {
+        let schema = Schema::new("l,<input>").unwrap();
+        assert_parse_err!(schema, "-l", ParseErr::MissingArg(name) if name == "input");
+        assert_parse_err!(schema, "a b", ParseErr::UnexpectedValue(value) if value == "b");
+        assert_parse_err!(schema, "-input a", ParseErr::UnknownArg { arg, .. } if arg == "input");
+        assert_eq!(Schema::new("<>").unwrap_err(), ParseErr::InvalidSchema);
+    }
+
+    #[test]
+    fn variadic_positional_takes_the_rest() {
+        let schema = Schema::new("i,<pattern>,<files>...").unwrap();
+        assert_parses!(schema, "-i todo a.rs b.rs c.rs", {
+            i: true,
+            pattern: "todo",
+            files: ["a.rs", "b.rs", "c.rs"],
+        });
+        let args = assert_parses!(schema, "todo", { i: false, pattern: "todo" });
+        assert_eq!(args.source("files"), None);
+        assert_eq!(schema.usage("grep"), "usage: grep [-i] <pattern> [<files>...]");
+    }
+
+    #[test]
+    fn variadic_positional_must_come_last() {
+        assert_eq!(Schema::new("<files>...,<out>").unwrap_err(), ParseErr::InvalidSchema);
+        let built = Schema::builder().variadic("files").positional("out").build();
+        assert_eq!(built.unwrap_err(), ParseErr::InvalidSchema);
+        let files = Schema::builder().variadic("files").build().unwrap();
+        let out = Schema::new("<out>").unwrap();
+        assert_eq!(files.merge(out).unwrap_err(), ParseErr::InvalidSchema);
+    }
+
+    #[test]
+    fn alternative_flag_prefixes() {
+        let schema = Schema::new("l,p#,d*").unwrap().flag_prefixes("/");
+        let args = schema.parse("/p 8080 /l -d /var/logs").unwrap();
+        assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "/var/logs");
+        assert!(schema.matcher().is_valid("/p 8080 /l -d /var/logs"));
+        let long = Schema::builder().number("port").build().unwrap().flag_prefixes("/");
+        assert_eq!(long.parse("/port=8080").unwrap().get("port").unwrap().as_number(), Some(8080));
+        let plain = Schema::new("l").unwrap();
+        assert_eq!(plain.parse("/l").unwrap_err(), ParseErr::UnknownArg {
+            arg: "/l".into(),
+            typed: "/l".into(),
+            suggestion: Some("-l".into()),
+        });
+    }
+
+    #[test]
+    fn negated_bools() {
+        let schema = Schema::new("l,v,p#").unwrap().inverted("q", "v");
+        let args = schema.parse("-l --no-l").unwrap();
+        assert!(!args.get("l").unwrap().as_bool().unwrap());
+        let args = schema.parse("-l-").unwrap();
+        assert!(!args.get("l").unwrap().as_bool().unwrap());
+        let args = schema.parse("-q --no-v").unwrap();
+        assert!(!args.get("v").unwrap().as_bool().unwrap());
+        assert_eq!(schema.parse("--no-p").unwrap_err(), ParseErr::UnknownArg {
+            arg: "no-p".into(),
+            typed: "--no-p".into(),
+            suggestion: None,
+        });
+        assert_eq!(schema.canonical_command_line("-l- -p 1"), "--no-l -p 1");
+        let declared = Schema::new("l").unwrap().merge(Schema::builder().bool("no-l").build().unwrap());
+        let args = declared.unwrap().parse("-l --no-l").unwrap();
+        assert!(args.get("no-l").unwrap().as_bool().unwrap());
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+    }
+
+    #[test]
+    fn accumulation_policies() {
+        let schema = Schema::new("i[*],e[*],x[*]")
+            .unwrap()
+            .accumulate("e", Accumulation::Replace)
+            .accumulate("x", Accumulation::Error);
+        let args = schema.parse("-i a -i b c -e a -e b c -x a b").unwrap();
+        assert_eq!(args.get("i").unwrap().as_str_array(), vec!["a", "b", "c"]);
+        assert_eq!(args.get("e").unwrap().as_str_array(), vec!["b", "c"]);
+        assert_eq!(
+            schema.parse("-x a -x b").unwrap_err(),
+            ParseErr::TooManyOccurrences { arg: "x".to_string(), max: 1 }
+        );
+        assert_eq!(schema.args().nth(1).unwrap().accumulation(), Accumulation::Replace);
+        let scalars = Schema::new("p#,d*")
+            .unwrap()
+            .accumulate("p", Accumulation::Keep)
+            .accumulate("d", Accumulation::Error);
+        assert_eq!(scalars.parse("-p 1 -p 2").unwrap().get_number("p"), Some(1));
+        assert_eq!(scalars.spec("p").unwrap().max_occurrences(), None);
+        assert_eq!(
+            scalars.parse("-d a -d b").unwrap_err(),
+            ParseErr::TooManyOccurrences { arg: "d".to_string(), max: 1 }
+        );
+    }
+
+    #[test]
+    fn presets_fill_in_beneath_the_command_line() {
+        let schema = Schema::builder()
+            .string("profile")
+            .default("dev")
+            .number("j")
+            .default("1")
+            .bool("lto")
+            .string("o")
+            .build()
+            .unwrap()
+            .preset("dev", &[("o", "0")])
+            .unwrap()
+            .preset("release", &[("j", "8"), ("lto", "true"), ("o", "3")])
+            .unwrap()
+            .env("profile", "ARGS_TEST_PROFILE")
+            .env("j", "ARGS_TEST_PRESET_J")
+            .preset_arg("profile")
+            .unwrap();
+        let args = schema.parse("--profile release -o 2").unwrap();
+        assert_eq!((args.get_number("j"), args.get_bool("lto")), (Some(8), Some(true)));
+        assert_eq!(args.get_str("o"), Some("2".to_string()));
+        assert_eq!(args.source("j"), Some(Source::Preset));
+        assert_eq!(args.source("o"), Some(Source::CommandLine));
+        let args = schema.parse("").unwrap();
+        assert_eq!((args.get_str("o"), args.get_number("j")), (Some("0".to_string()), Some(1)));
+        assert_eq!(args.source("j"), Some(Source::Default));
+        let err = schema.parse("--profile fast").unwrap_err();
+        assert_eq!(err, ParseErr::UnknownPreset("fast".to_string()));
+        let notes = "[default: dev; env: ARGS_TEST_PROFILE; presets: dev, release]";
+        assert!(schema.help("app").contains(notes));
+        std::env::set_var("ARGS_TEST_PROFILE", "release");
+        std::env::set_var("ARGS_TEST_PRESET_J", "4");
+        let args = schema.parse("-o 2").unwrap();
+        assert_eq!((args.get_number("j"), args.get_bool("lto")), (Some(4), Some(true)));
+        assert_eq!(args.source("j"), Some(Source::Env));
+        assert_eq!(args.source("lto"), Some(Source::Preset));
+        std::env::remove_var("ARGS_TEST_PROFILE");
+        std::env::remove_var("ARGS_TEST_PRESET_J");
+        let built = Schema::new("p*,l").unwrap();
+        assert_eq!(built.clone().preset_arg("x").unwrap_err(), ParseErr::InvalidSchema);
+        assert_eq!(built.clone().preset_arg("l").unwrap_err(), ParseErr::InvalidSchema);
+        assert_eq!(built.preset("fast", &[("x", "1")]).unwrap_err(), ParseErr::InvalidSchema);
+    }
+
+    #[test]
+    fn renders_the_definition() {
+        let definition = "l,p#,d*,c*?,s[*],n[#],<input>,<files>...";
+        assert_eq!(Schema::new(definition).unwrap().to_schema_string().unwrap(), definition);
+        let built = Schema::builder().bool("v").positional("src").build().unwrap();
+        let merged = built.merge(Schema::new("o*,<rest>...").unwrap()).unwrap();
+        let rendered = merged.to_schema_string().unwrap();
+        assert_eq!(rendered, "v,<src>,o*,<rest>...");
+        assert_eq!(Schema::new(&rendered).unwrap().fingerprint(), merged.fingerprint());
+        let long = Schema::builder().bool("verbose").build().unwrap();
+        assert_eq!(long.to_schema_string().unwrap_err(), ParseErr::InvalidSchema);
+    }
+
+    #[test]
+    fn concatenating_strings() {
+        let schema = Schema::new("m*,t*,d*")
+            .unwrap()
+            .separator("t", "")
+            .accumulate("m", Accumulation::Concat)
+            .accumulate("t", Accumulation::Concat);
+        let args = schema.parse("-m fix the -m bug -t a -t b c -d x y").unwrap();
+        assert_eq!(args.get_str("m"), Some("fix the bug".to_string()));
+        assert_eq!(args.get_str("t"), Some("abc".to_string()));
+        assert_eq!(args.get_str("d"), Some("x y".to_string()));
+        assert_eq!(schema.spec("t").unwrap().separator(), Some(""));
+        let numbers = Schema::new("p#").unwrap().accumulate("p", Accumulation::Concat);
+        assert_eq!(numbers.parse("-p 1").unwrap_err(), ParseErr::InvalidSchema);
+        let args = Schema::new("d*").unwrap().separator("d", ", ").parse("-d a b").unwrap();
+        assert_eq!(args.get_str("d"), Some("a, b".to_string()));
+    }
+
+    #[test]
+    fn absent_bools_can_stay_unset() {
+        let schema = Schema::new("l,v").unwrap().absent_bool("l", AbsentBool::Unset);
+        let args = schema.parse("").unwrap();
+        assert_eq!(args.get("l").unwrap().get(), None);
+        assert_eq!(args.get_bool("v"), Some(false));
+        assert_eq!(args.try_get_bool("l"), Err(ParseErr::MissingValue("l".to_string())));
+        assert_eq!(schema.parse("-l false").unwrap().get_bool("l"), Some(false));
+        let args = schema.parse("-l").unwrap();
+        assert_eq!(args.value("l"), Some(&ArgValue::OptBool(Some(true))));
+        assert_eq!(schema.parse("-l false").unwrap().to_command_line(), "-l false");
+        assert_eq!(schema.args().next().unwrap().absent_bool(), AbsentBool::Unset);
+    }
+
+    #[test]
+    fn delimited_list_values() {
+        let schema = Schema::builder()
+            .number_list("p")
+            .default("1,2")
+            .string_list("s")
+            .build()
+            .unwrap()
+            .delimiter("p", ',')
+            .delimiter("s", ';');
+        assert_parses!(schema, "-p 8080,9090 -p 9100 -s a;b c", {
+            p: [8080, 9090, 9100],
+            s: ["a", "b", "c"],
+        });
+        assert_parses!(schema, "", { p: [1, 2] });
+    }
+
+    #[test]
+    fn shell_words_mode() {
+        let schema = Schema::new("d*,l").unwrap().shell_words();
+        let args = schema.parse(r#"-d "cost \$5 \`x\`" -l"#).unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "cost $5 `x`");
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        let args = schema.parse("-d a\\\nb -l").unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "ab");
+        assert_eq!(schema.parse("-d 'open").unwrap_err(), ParseErr::UnterminatedQuote('\''));
+        assert!(schema.matcher().is_valid("-d a\\\nb -l"));
+        let plain = Schema::new("d*").unwrap().parse(r#"-d "\$5" "#).unwrap();
+        assert_eq!(plain.get("d").unwrap().get().unwrap(), "\\$5");
+    }
+
+    #[test]
+    fn non_ascii_flag_names() {
+        let schema = Schema::new("é*,日,ü#").unwrap();
+        let args = schema.parse("-é value -日ü5").unwrap();
+        assert_eq!(args.get("é").unwrap().get().unwrap(), "value");
+        assert!(args.get("日").unwrap().as_bool().unwrap());
+        assert_eq!(args.get("ü").unwrap().as_number().unwrap(), 5);
+        assert_eq!(schema.usage("app"), "usage: app [-é <string>] [-日] [-ü <number>]");
+        assert_eq!(schema.parse("--日本").unwrap_err(), ParseErr::UnknownArg {
+            arg: "日本".into(),
+            typed: "--日本".into(),
+            suggestion: Some("-日".into()),
+        });
+        assert_eq!(
+            Schema::new("日本*").unwrap_err(),
+            ParseErr::UnsupportedArgType("本*".to_string())
+        );
+    }
+
+    #[test]
+    fn captures_the_rest() {
+        let schema = Schema::new("v,p#,d*").unwrap().capture_rest();
+        let args = schema.parse("-v -p 1 ssh -v host").unwrap();
+        assert!(args.get("v").unwrap().as_bool().unwrap());
+        assert_eq!(args.trailing(), &["ssh", "-v", "host"]);
+        let args = schema.parse("-d logs -x 'a b' -- -p").unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "logs");
+        assert_eq!(args.trailing(), &["-x", "a b", "--", "-p"]);
+        assert_eq!(schema.parse("-v -- -p 2").unwrap().trailing(), &["-p", "2"]);
+        assert_eq!(schema.parse("make").unwrap().trailing(), &["make"]);
+        assert!(schema.parse("-v").unwrap().trailing().is_empty());
+        assert_eq!(
+            Schema::new("v").unwrap().parse("-v -x").unwrap_err(),
+            ParseErr::UnknownArg {
+                arg: "x".into(),
+                typed: "-x".into(),
+                suggestion: Some("-v".into()),
+            }
+        );
+        assert!(schema.matcher().is_valid("-p x"));
+    }
+
+    #[test]
+    fn captured_rest_after_positionals() {
+        let schema = Schema::new("v,<cmd>").unwrap().capture_rest();
+        let args = schema.parse("-v run --fast now").unwrap();
+        assert_eq!(args.get("cmd").unwrap().get().unwrap(), "run");
+        assert_eq!(args.trailing(), &["--fast", "now"]);
+        let args = schema.parse("run now -v").unwrap();
+        assert_eq!(args.get("cmd").unwrap().get().unwrap(), "run");
+        assert_eq!(args.trailing(), &["now", "-v"]);
+        assert!(!args.get("v").unwrap().as_bool().unwrap());
+        assert_eq!(schema.parse("-v -- run").unwrap_err(), ParseErr::MissingArg("cmd".to_string()));
+        assert_eq!(crate::validate(&schema, "-v run -x"), Ok(()));
+    }
+
+    #[test]
+    fn collects_unknown_flags() {
+        let schema = Schema::new("v,p#,<cmd>").unwrap().collect_unknown();
+        let args = schema.parse("-v --color always -p 1 run -x").unwrap();
+        assert_eq!(args.get_number("p"), Some(1));
+        assert_eq!(args.get_str("cmd"), Some("run".to_string()));
+        assert_eq!(args.unknown(), &["--color", "always", "-x"]);
+        let args = schema.parse("--fast -v run").unwrap();
+        assert_eq!(args.get_str("cmd"), Some("run".to_string()));
+        assert_eq!(args.unknown(), &["--fast"]);
+        assert!(schema.parse("-v run").unwrap().unknown().is_empty());
+        assert_eq!(crate::validate(&schema, "-x -v run"), Ok(()));
+        assert!(schema.matcher().is_valid("-x -v run"));
+        assert_ne!(schema.fingerprint(), Schema::new("v,p#,<cmd>").unwrap().fingerprint());
+    }
+
+    #[test]
+    fn recovers_from_errors() {
+        let schema = Schema::new("l,p#,d*").unwrap();
+        let (args, errors) = schema.parse_recovering("-l -p x -d logs -q");
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(args.get_number("p"), None);
+        assert_eq!(args.get_str("d"), Some("logs".to_string()));
+        assert_eq!(
+            errors,
+            vec![
+                ParseErr::NumberFormatErr { arg: "p".into(), value: "x".into(), span: Some(3..7) },
+                ParseErr::UnknownArg {
+                    arg: "q".into(),
+                    typed: "-q".into(),
+                    suggestion: Some("-d".into()),
+                }
+            ]
+        );
+        let (args, errors) = schema.parse_recovering("-p 1");
+        assert_eq!(args, schema.parse("-p 1").unwrap());
+        assert!(errors.is_empty());
+        let schema = Schema::builder().bool("l").string("d").required().build().unwrap();
+        let schema = schema.shell_words();
+        let (args, errors) = schema.parse_recovering("-l -d 'logs");
+        assert!(!args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(
+            errors,
+            vec![ParseErr::UnterminatedQuote('\''), ParseErr::MissingArg("d".to_string())]
+        );
+    }
+
+    #[test]
+    fn partial_parse_stops_at_the_first_word() {
+        let schema = Schema::new("v,p#,d*,s[*]").unwrap();
+        let (args, rest) = schema.parse_partial("-v -p 1 build --release 'a b'").unwrap();
+        assert!(args.get("v").unwrap().as_bool().unwrap());
+        assert_eq!(args.get("p").unwrap().as_number(), Some(1));
+        assert_eq!(rest, "build --release 'a b'");
+        let (args, rest) = schema.parse_partial("-d logs test -- -x").unwrap();
+        assert_eq!(args.get("d").unwrap().get().unwrap(), "logs");
+        assert_eq!(rest, "test -- -x");
+        let (args, rest) = schema.parse_partial("-s a b -- -v").unwrap();
+        assert_eq!(args.get("s").unwrap().as_str_array(), vec!["a", "b"]);
+        assert_eq!(rest, "-v");
+        assert_eq!(schema.parse_partial("-v").unwrap().1, "");
+        assert_eq!(
+            schema.parse_partial("-x run").unwrap_err(),
+            ParseErr::UnknownArg {
+                arg: "x".into(),
+                typed: "-x".into(),
+                suggestion: Some("-d".into()),
+            }
+        );
+        assert_eq!(
+            schema.parse("-p 1 build").unwrap_err(),
+            ParseErr::NumberFormatErr { arg: "p".into(), value: "1build".into(), span: Some(0..10) }
+        );
+    }
+
+    #[test]
+    fn positionals_intermixed_with_flags() {
+        let schema = Schema::new("l,p#,<files>...").unwrap();
+        let input = "file1 -l file2 -p 8080 file3";
+        let args = schema.parse(input).unwrap();
+        assert!(args.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(args.get("p").unwrap().as_number(), Some(8080));
+        assert_eq!(args.get("files").unwrap().as_str_array(), vec!["file1", "file2", "file3"]);
+        assert_eq!(crate::validate(&schema, input), Ok(()));
+        assert!(schema.matcher().is_valid(input));
+        assert_eq!(schema.canonical_command_line(input), input);
+    }
+
+    #[test]
+    fn schema_is_shared_across_threads() {
+        fn shareable<T: Send + Sync>() {}
+        shareable::<Schema>();
+        shareable::<ParsedArgs>();
+        let schema = Arc::new(Schema::new("p#,l").unwrap());
+        let handles: Vec<_> = (0..4)
+            .map(|port| {
+                let schema = Arc::clone(&schema);
+                std::thread::spawn(move || schema.parse(&format!("-p {}", port)).unwrap())
+            })
+            .collect();
+        for (port, handle) in handles.into_iter().enumerate() {
+            let args = handle.join().unwrap();
+            assert_eq!(args.get("p").unwrap().as_number(), Some(port as isize));
+        }
+    }
+
+    #[test]
+    fn parse_with_computed_defaults() {
+        let schema = Schema::builder().number("p").default("80").string("d").bool("l").build();
+        let schema = schema.unwrap();
+        let mut defaults = HashMap::new();
+        defaults.insert("p", ArgValue::Number(Some(8080)));
+        defaults.insert("d", ArgValue::Str(Some("/var/logs".to_string())));
+        let args = schema.parse_with_defaults("-d /tmp", &defaults).unwrap();
+        assert_eq!(args.get_number("p"), Some(8080));
+        assert_eq!(args.source("p"), Some(Source::Default));
+        assert_eq!(args.get_str("d"), Some("/tmp".to_string()));
+        assert_eq!(args.source("d"), Some(Source::CommandLine));
+
+        defaults.insert("l", ArgValue::Number(Some(1)));
+        assert_eq!(
+            schema.parse_with_defaults("", &defaults).unwrap_err(),
+            ParseErr::InvalidValue { arg: "l".to_string(), value: "1".to_string() }
+        );
+        let unknown: HashMap<_, _> = vec![("x", ArgValue::Bool(true))].into_iter().collect();
+        assert_eq!(
+            schema.parse_with_defaults("", &unknown).unwrap_err(),
+            ParseErr::unknown_arg("x")
+        );
+    }
+
+    #[test]
+    fn validate_with_checks_the_whole_result() {
+        let schema = Schema::new("s#,e#,a,b")
+            .unwrap()
+            .validate_with(|args| match (args.get_number("s"), args.get_number("e")) {
+                (Some(start), Some(end)) if start >= end => Err(ParseErr::ValidationFailed {
+                    arg: "s".to_string(),
+                    message: "--start must be before --end".to_string(),
+                }),
+                _ => Ok(()),
+            })
+            .validate_with(|args| {
+                if args.is_present("a") == args.is_present("b") {
+                    return Err(ParseErr::ConflictingArgs {
+                        arg: "a".to_string(),
+                        other: "b".to_string(),
+                    });
+                }
+                Ok(())
+            });
+        assert!(schema.parse("-s 1 -e 2 -a").is_ok());
+        assert_eq!(
+            schema.parse("-s 2 -e 1 -a").unwrap_err(),
+            ParseErr::ValidationFailed {
+                arg: "s".to_string(),
+                message: "--start must be before --end".to_string(),
+            }
+        );
+        assert_eq!(
+            schema.parse("-a -b").unwrap_err(),
+            ParseErr::ConflictingArgs { arg: "a".to_string(), other: "b".to_string() }
+        );
+    }
+
+    #[test]
+    fn schema_from_str() {
+        use std::convert::TryFrom;
+        let schema: Schema = "d*,p#,l".parse().unwrap();
+        assert_eq!(schema.fingerprint(), Schema::new("d*,p#,l").unwrap().fingerprint());
+        assert!(Schema::try_from("d*,p#,l").is_ok());
+        let err = "d*,p!".parse::<Schema>().unwrap_err();
+        assert_eq!(err.parse_err(), &ParseErr::UnsupportedArgType("!".to_string()));
+        assert_eq!(err.to_string(), "unsupported argument type '!'");
+        let err = Schema::try_from("p#,p*").unwrap_err();
+        assert_eq!(ParseErr::from(err), ParseErr::DuplicateArg("p".to_string()));
+        assert_eq!(Schema::new("p#,p*").unwrap_err(), ParseErr::DuplicateArg("p".to_string()));
+    }
+
+    #[test]
+    fn error_spans() {
+        let schema = Schema::new("p#,d*,l,v").unwrap().conflicts_with("l", "v");
+        let span = |input: &str| schema.error_span(input, &schema.parse(input).unwrap_err());
+        assert_eq!(span("-d logs -p foo"), Some(11..14));
+        assert_eq!(span("-d logs -x"), Some(8..10));
+        assert_eq!(span("-p 1 -d a -p 2"), Some(10..14));
+        assert_eq!(span("-l -v"), Some(3..5));
+        assert_eq!(schema.error_span("", &ParseErr::MissingArg("d".to_string())), None);
+    }
+
+    #[test]
+    fn renders_errors_with_the_word_underlined() {
+        let schema = Schema::new("p#,d*").unwrap();
+        let input = "-d logs -p 8o";
+        let err = schema.parse(input).unwrap_err();
+        let report = "error: '8o' is not a number\n  | -d logs -p 8o\n  |            ^^";
+        assert_eq!(schema.render_error(input, &err), report);
+        let input = "-d logs\n-é";
+        let err = schema.parse(input).unwrap_err();
+        assert!(schema.render_error(input, &err).ends_with("\n  | -é\n  | ^^"));
+        let err = ParseErr::MissingArg("d".to_string());
+        assert_eq!(schema.render_error("", &err), "error: missing required argument -d");
+    }
+}
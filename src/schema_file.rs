@@ -0,0 +1,371 @@
+//! Loading a [`Schema`] from a declaration file instead of the inline DSL.
+//!
+//! Both formats describe the same thing: one entry per argument, keyed by
+//! its name, with a `type` (`bool`, `string`, `string?`, `number`,
+//! `string[]` or `number[]`) and an optional `default`, `description` and `metavar`. Only
+//! the subset of TOML and YAML needed for that is understood.
+//!
+//! ```toml
+//! [args.p]
+//! type = "number"
+//! default = 8080
+//! description = "port to listen on"
+//! ```
+//!
+//! TOML files may also declare [presets](Schema::preset) as `[presets.<name>]`
+//! tables of `argument = value` lines.
+//!
+//! ```yaml
+//! args:
+//!   p:
+//!     type: number
+//!     default: 8080
+//!     description: port to listen on
+//! ```
+
+use crate::schema::{ArgKind, ArgSpec};
+use crate::{ParseErr, Schema};
+
+struct Entry {
+    line: usize,
+    name: String,
+    fields: Vec<(usize, String, String)>,
+}
+
+fn invalid(line: usize, message: &str) -> ParseErr {
+    ParseErr::InvalidSchemaFile { line, message: message.to_string() }
+}
+
+fn build(entries: Vec<Entry>) -> Result<Schema, ParseErr> {
+    if entries.is_empty() {
+        return Err(ParseErr::InvalidSchema);
+    }
+    let mut specs: Vec<ArgSpec> = vec![];
+    for entry in entries {
+        if specs.iter().any(|spec| spec.name == entry.name) {
+            return Err(invalid(entry.line, "duplicate argument"));
+        }
+        let line = entry.line;
+        let mut kind = None;
+        let mut default = None;
+        let mut description = None;
+        let mut metavar = None;
+        for (line, key, value) in entry.fields {
+            match key.as_str() {
+                "type" => {
+                    kind = Some(ArgKind::from_name(&value).ok_or_else(|| {
+                        ParseErr::UnsupportedArgType(value.clone())
+                    })?)
+                }
+                "default" => default = Some(value),
+                "description" => description = Some(value),
+                "metavar" => metavar = Some(value),
+                _ => return Err(invalid(line, &format!("unknown key '{}'", key))),
+            }
+        }
+        let kind = kind.ok_or_else(|| invalid(line, "missing type"))?;
+        let mut spec = ArgSpec::new(&entry.name, kind);
+        spec.default = default;
+        spec.description = description;
+        spec.metavar = metavar;
+        specs.push(spec);
+    }
+    Ok(Schema::from_specs(specs))
+}
+
+/// Reads a quoted string starting at `text`, returning it and the rest of
+/// the line.
+pub(crate) fn quoted(text: &str, line: usize) -> Result<(String, &str), ParseErr> {
+    let mut chars = text.char_indices();
+    let quote = chars.next().map(|(_, c)| c).unwrap_or('"');
+    let mut value = String::new();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => match chars.next().map(|(_, c)| c) {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(c @ '"') | Some(c @ '\\') => value.push(c),
+                _ => return Err(invalid(line, "unsupported escape sequence")),
+            },
+            c if c == quote => return Ok((value, &text[i + 1..])),
+            c => value.push(c),
+        }
+    }
+    Err(invalid(line, "unterminated string"))
+}
+
+/// Fails unless nothing but a comment follows a value.
+pub(crate) fn expect_end(rest: &str, line: usize) -> Result<(), ParseErr> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(invalid(line, "unexpected trailing characters"))
+    }
+}
+
+impl Schema {
+    /// Builds a schema from a TOML document with one `[args.<name>]` table
+    /// per argument and one `[presets.<name>]` table per preset.
+    #[cfg(feature = "toml")]
+    pub fn from_toml(contents: &str) -> Result<Schema, ParseErr> {
+        let mut entries: Vec<Entry> = vec![];
+        let mut presets: Vec<Entry> = vec![];
+        let mut in_preset = false;
+        for (i, raw) in contents.lines().enumerate() {
+            let line = i + 1;
+            let text = raw.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+            if let Some(header) = text.strip_prefix('[') {
+                let (header, rest) = header
+                    .split_once(']')
+                    .ok_or_else(|| invalid(line, "unterminated table header"))?;
+                expect_end(rest, line)?;
+                let header = header.trim();
+                let preset = header.strip_prefix("presets.");
+                in_preset = preset.is_some();
+                let name = preset
+                    .or_else(|| header.strip_prefix("args."))
+                    .ok_or_else(|| invalid(line, "expected an [args.<name>] table"))?
+                    .trim();
+                let name = if name.starts_with('"') || name.starts_with('\'') {
+                    let (name, rest) = quoted(name, line)?;
+                    expect_end(rest, line)?;
+                    name
+                } else {
+                    name.to_string()
+                };
+                if name.is_empty() {
+                    return Err(invalid(line, "empty argument name"));
+                }
+                let entries = if in_preset { &mut presets } else { &mut entries };
+                entries.push(Entry {
+                    line,
+                    name,
+                    fields: vec![],
+                });
+                continue;
+            }
+            let (key, value) = text
+                .split_once('=')
+                .ok_or_else(|| invalid(line, "expected key = value"))?;
+            let value = value.trim();
+            let value = if value.starts_with('"') || value.starts_with('\'') {
+                let (value, rest) = quoted(value, line)?;
+                expect_end(rest, line)?;
+                value
+            } else {
+                let bare = value.split('#').next().unwrap_or("").trim();
+                let digits = bare.trim_start_matches(['+', '-']).replace('_', "");
+                if bare != "true"
+                    && bare != "false"
+                    && (digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()))
+                {
+                    return Err(invalid(line, "unsupported value"));
+                }
+                bare.trim_start_matches('+').replace('_', "")
+            };
+            let table = if in_preset { &mut presets } else { &mut entries };
+            let entry = table
+                .last_mut()
+                .ok_or_else(|| invalid(line, "key outside of an [args.<name>] table"))?;
+            entry.fields.push((line, key.trim().to_string(), value));
+        }
+        let mut schema = build(entries)?;
+        for preset in presets {
+            let values = preset.fields.iter().map(|(_, arg, value)| (arg.as_str(), value.as_str()));
+            let values: Vec<(&str, &str)> = values.collect();
+            schema = schema.preset(&preset.name, &values)?;
+        }
+        Ok(schema)
+    }
+
+    /// Builds a schema from a YAML document with a top-level `args` mapping
+    /// of argument names to their settings.
+    #[cfg(feature = "yaml")]
+    pub fn from_yaml(contents: &str) -> Result<Schema, ParseErr> {
+        let mut entries: Vec<Entry> = vec![];
+        let mut seen_args = false;
+        let mut name_indent = None;
+        for (i, raw) in contents.lines().enumerate() {
+            let line = i + 1;
+            let text = raw.trim_end();
+            let trimmed = text.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed == "---" {
+                continue;
+            }
+            let indent = text.len() - trimmed.len();
+            let (key, value) = trimmed
+                .split_once(':')
+                .ok_or_else(|| invalid(line, "expected key: value"))?;
+            let value = value.trim();
+            let value = if value.starts_with('"') || value.starts_with('\'') {
+                let (value, rest) = quoted(value, line)?;
+                expect_end(rest, line)?;
+                value
+            } else {
+                match value.find(" #") {
+                    Some(comment) => value[..comment].trim_end().to_string(),
+                    None if value.starts_with('#') => String::new(),
+                    None => value.to_string(),
+                }
+            };
+            let key = key.trim().to_string();
+            if indent == 0 {
+                if key != "args" || !value.is_empty() || seen_args {
+                    return Err(invalid(line, "expected a single top-level 'args' mapping"));
+                }
+                seen_args = true;
+            } else if !seen_args {
+                return Err(invalid(line, "expected a top-level 'args' mapping"));
+            } else if name_indent.is_none_or(|n| indent <= n) {
+                if name_indent.is_some_and(|n| indent != n) || !value.is_empty() {
+                    return Err(invalid(line, "expected an argument name"));
+                }
+                name_indent = Some(indent);
+                entries.push(Entry {
+                    line,
+                    name: key,
+                    fields: vec![],
+                });
+            } else {
+                let entry = entries.last_mut().unwrap();
+                entry.fields.push((line, key, value));
+            }
+        }
+        build(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "toml")]
+    mod toml {
+        use super::*;
+
+        #[test]
+        fn loads_names_types_defaults_and_descriptions() {
+            let schema = Schema::from_toml(
+                r#"
+                # server flags
+                [args.p]
+                type = "number"
+                default = 8080
+                description = "port to listen on"
+
+                [args.d]
+                type = 'string'
+                description = "log \"dir\""  # trailing comment
+
+                [args.l]
+                type = "bool"
+                "#,
+            )
+            .unwrap();
+            assert_eq!(schema.usage("app"), "usage: app [-p <number>] [-d <string>] [-l]");
+            assert_eq!(schema.default_value("p"), Some("8080"));
+            assert_eq!(schema.description("d"), Some("log \"dir\""));
+            let args = schema.parse("-l").unwrap();
+            assert_eq!(args.get("p").unwrap().as_number().unwrap(), 8080);
+        }
+
+        #[test]
+        fn reports_the_offending_line() {
+            let err = Schema::from_toml("[args.p]\ntype = \"number\"\nport = 1").unwrap_err();
+            assert_eq!(
+                err,
+                ParseErr::InvalidSchemaFile { line: 3, message: "unknown key 'port'".to_string() }
+            );
+            let err = Schema::from_toml("type = \"bool\"").unwrap_err();
+            assert_eq!(
+                err,
+                ParseErr::InvalidSchemaFile {
+                    line: 1,
+                    message: "key outside of an [args.<name>] table".to_string(),
+                }
+            );
+        }
+
+        #[test]
+        fn loads_presets() {
+            let schema = Schema::from_toml(
+                "[args.profile]\ntype = \"string\"\n[args.j]\ntype = \"number\"\n\
+                 [presets.fast]\nj = 8\n",
+            )
+            .unwrap()
+            .preset_arg("profile")
+            .unwrap();
+            assert_eq!(schema.parse("--profile fast").unwrap().get_number("j"), Some(8));
+            let unknown = Schema::from_toml("[args.j]\ntype = \"number\"\n[presets.fast]\nx = 8");
+            assert_eq!(unknown.unwrap_err(), ParseErr::InvalidSchema);
+        }
+
+        #[test]
+        fn loads_optional_strings() {
+            let schema = Schema::from_toml("[args.color]\ntype = \"string?\"").unwrap();
+            assert_eq!(schema.spec("color").unwrap().kind(), ArgKind::OptStr);
+            assert_eq!(schema.parse("--color").unwrap().get_str("color"), Some(String::new()));
+            let args = schema.parse("--color never").unwrap();
+            assert_eq!(args.get_str("color"), Some("never".to_string()));
+        }
+
+        #[test]
+        fn rejects_unknown_types() {
+            let err = Schema::from_toml("[args.p]\ntype = \"port\"").unwrap_err();
+            assert_eq!(err, ParseErr::UnsupportedArgType("port".to_string()));
+        }
+    }
+
+    #[cfg(feature = "yaml")]
+    mod yaml {
+        use super::*;
+
+        #[test]
+        fn loads_names_types_defaults_and_descriptions() {
+            let schema = Schema::from_yaml(
+                "
+---
+# server flags
+args:
+  p:
+    type: number
+    default: 8080 # the usual
+    description: port to listen on
+  s:
+    type: 'string[]'
+    description: \"servers, comma separated\"
+",
+            )
+            .unwrap();
+            assert_eq!(schema.usage("app"), "usage: app [-p <number>] [-s <string>...]");
+            assert_eq!(schema.default_value("p"), Some("8080"));
+            assert_eq!(schema.description("p"), Some("port to listen on"));
+            assert_eq!(schema.description("s"), Some("servers, comma separated"));
+        }
+
+        #[test]
+        fn requires_args_mapping() {
+            let err = Schema::from_yaml("flags:\n  p:\n    type: number").unwrap_err();
+            assert_eq!(
+                err,
+                ParseErr::InvalidSchemaFile {
+                    line: 1,
+                    message: "expected a single top-level 'args' mapping".to_string(),
+                }
+            );
+        }
+
+        #[test]
+        fn requires_a_type() {
+            let err = Schema::from_yaml("args:\n  p:\n    default: 1").unwrap_err();
+            assert_eq!(err, ParseErr::InvalidSchemaFile {
+                line: 2,
+                message: "missing type".to_string(),
+            });
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! "Did you mean" suggestions for flags the schema doesn't know.
+
+use crate::{schema::flag, ParseErr, Schema};
+
+/// A known flag that looks like what was typed, as ranked by
+/// [`Schema::suggest`]. Lower distances are closer matches.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Suggestion {
+    pub name: String,
+    pub distance: usize,
+}
+
+/// Edit distance where insertions, deletions, substitutions and swapping
+/// two adjacent characters each cost one.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+impl Schema {
+    /// Up to three known names close to `given`, closest first. Argument
+    /// names, inverted switches and deprecated names are all considered,
+    /// and a case-only difference counts as an exact match. Hidden names
+    /// are never suggested.
+    pub fn suggest(&self, given: &str) -> Vec<Suggestion> {
+        let given = given.trim_start_matches('-');
+        let flags = self.specs.iter().filter(|spec| !spec.positional);
+        let specs = flags.map(|spec| &spec.name);
+        let switches = self.inversions.iter().map(|(switch, _)| switch);
+        let deprecated = self.deprecations.iter().map(|(name, _)| name);
+        let mut names: Vec<&String> = specs.chain(switches).chain(deprecated).collect();
+        names.retain(|name| !self.hidden.contains(name));
+        names.sort();
+        names.dedup();
+        let limit = (given.chars().count() / 3).max(1);
+        let mut suggestions: Vec<Suggestion> = names
+            .into_iter()
+            .map(|name| Suggestion {
+                name: name.clone(),
+                distance: distance(&given.to_lowercase(), &name.to_lowercase()),
+            })
+            .filter(|suggestion| suggestion.distance <= limit && suggestion.name != given)
+            .collect();
+        suggestions.sort_by_key(|suggestion| {
+            let length_gap = suggestion.name.chars().count().abs_diff(given.chars().count());
+            (suggestion.distance, length_gap)
+        });
+        suggestions.truncate(3);
+        suggestions
+    }
+
+    /// The message of `err` with what the schema expected, e.g. `unknown
+    /// argument '-x'; did you mean -p?` or, with nothing close, `unknown
+    /// argument '-x'; expected one of: -d, -l`.
+    /// Messages set with [`Schema::error_messages`] are used as they are.
+    pub fn explain(&self, err: &ParseErr) -> String {
+        if let Some(message) = self.messages.as_ref().and_then(|messages| (messages.0)(err)) {
+            return message;
+        }
+        let (given, typed) = match err {
+            ParseErr::UnknownArg { arg, typed, .. } => (arg, typed),
+            _ => return err.to_string(),
+        };
+        let suggestions: Vec<String> =
+            self.suggest(given).iter().map(|suggestion| self.dashed(&suggestion.name)).collect();
+        let unknown = format!("unknown argument '{}'", typed);
+        if !suggestions.is_empty() {
+            return format!("{}; did you mean {}?", unknown, suggestions.join(" or "));
+        }
+        let flags = self.specs.iter().filter(|spec| !spec.positional).map(|spec| &spec.name);
+        let switches = self.inversions.iter().map(|(switch, _)| switch);
+        let known: Vec<String> = flags
+            .chain(switches)
+            .filter(|name| !self.hidden.contains(name))
+            .map(|name| self.dashed(name))
+            .collect();
+        format!("{}; expected one of: {}", unknown, known.join(", "))
+    }
+
+    /// [`ParseErr::UnknownArg`] for the flag `name`, spelled `given` in
+    /// `text`, with the closest known flag as its suggestion.
+    pub(crate) fn unknown_arg(&self, name: String, given: &str, text: &str) -> ParseErr {
+        let text = text.trim_start_matches(['"', '\'']);
+        let typed = match text.find(given) {
+            Some(at) => text[..at + given.len()].to_string(),
+            None => flag(given),
+        };
+        let closest = self.suggest(&name).into_iter().next();
+        let suggestion = closest.map(|suggestion| self.dashed(&suggestion.name));
+        ParseErr::UnknownArg { arg: name, typed, suggestion }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(suggestions: Vec<Suggestion>) -> Vec<String> {
+        suggestions.into_iter().map(|suggestion| suggestion.name).collect()
+    }
+
+    #[test]
+    fn ranks_transpositions_and_typos() {
+        let schema = Schema::builder()
+            .number("port")
+            .string("proxy")
+            .bool("verbose")
+            .bool("p")
+            .build()
+            .unwrap();
+        assert_eq!(names(schema.suggest("--prot")), vec!["port"]);
+        assert_eq!(
+            schema.suggest("--verbsoe"),
+            vec![Suggestion {
+                name: "verbose".to_string(),
+                distance: 1
+            }]
+        );
+        assert_eq!(names(schema.suggest("--VERBOSE")), vec!["verbose"]);
+        assert!(schema.suggest("--colour").is_empty());
+    }
+
+    #[test]
+    fn considers_switches_and_deprecated_names_but_not_hidden_ones() {
+        let schema = Schema::new("c,d*,x")
+            .unwrap()
+            .inverted("no-cache", "c")
+            .deprecated("x", Some("d"))
+            .hidden("d");
+        assert_eq!(names(schema.suggest("--no-cahce")), vec!["no-cache"]);
+        assert_eq!(names(schema.suggest("-y")), vec!["c", "x"]);
+    }
+
+    #[test]
+    fn explains_unknown_arguments() {
+        let schema = Schema::new("d*,l,p#").unwrap().hidden("p");
+        let err = schema.parse("--colour").unwrap_err();
+        assert_eq!(err.to_string(), "unknown argument '--colour'");
+        assert_eq!(schema.explain(&err), "unknown argument '--colour'; expected one of: -d, -l");
+        let schema = Schema::builder().number("port").bool("l").build().unwrap();
+        let err = schema.parse("--prot 1").unwrap_err();
+        assert_eq!(schema.explain(&err), "unknown argument '--prot'; did you mean --port?");
+        let err = schema.parse("--port x").unwrap_err();
+        assert_eq!(schema.explain(&err), "'x' is not a number");
+        let boxed: Box<dyn std::error::Error> = Box::new(err);
+        assert_eq!(boxed.to_string(), "'x' is not a number");
+    }
+
+    #[test]
+    fn localized_messages() {
+        let schema = Schema::new("p#,l").unwrap().error_messages(|err| match err {
+            ParseErr::UnknownArg { arg, .. } => Some(format!("argument inconnu « {} »", arg)),
+            _ => None,
+        });
+        let err = schema.parse("-x").unwrap_err();
+        assert_eq!(schema.explain(&err), "argument inconnu « x »");
+        let report = "error: argument inconnu « x »\n  | -x\n  | ^^";
+        assert_eq!(schema.render_error("-x", &err), report);
+        let err = schema.parse("-p x").unwrap_err();
+        assert_eq!(schema.explain(&err), "'x' is not a number");
+    }
+
+    #[test]
+    fn did_you_mean() {
+        let schema = Schema::builder().number("port").bool("l").build().unwrap();
+        let err = schema.parse("-prot 1").unwrap_err();
+        let expected = ParseErr::UnknownArg {
+            arg: "prot".into(),
+            typed: "-prot".into(),
+            suggestion: Some("--port".into()),
+        };
+        assert_eq!(err, expected);
+        assert_eq!(err.to_string(), "unknown argument '-prot'; did you mean --port?");
+        assert_eq!(schema.explain(&err), "unknown argument '-prot'; did you mean --port?");
+    }
+
+    #[test]
+    fn caps_at_three() {
+        let schema = Schema::new("a,b,c,e").unwrap();
+        assert_eq!(schema.suggest("-z").len(), 3);
+    }
+}
@@ -0,0 +1,59 @@
+//! The argument names of a schema, interned once when the schema is built.
+//! Looking up the argument a token names is then a single hash rather than
+//! a comparison with every declared name, and the maps of each result share
+//! the interned names instead of copying them.
+
+use crate::hash::{name_map, NameMap};
+use std::sync::Arc;
+
+/// An interned argument name; cloning one only bumps a count.
+pub(crate) type Symbol = Arc<str>;
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Symbols {
+    names: Vec<Symbol>,
+    ids: NameMap<usize>,
+}
+
+impl Symbols {
+    /// Interns `names`, numbering them in order. A name given twice keeps
+    /// its first number.
+    pub(crate) fn new<'a>(names: impl ExactSizeIterator<Item = &'a str>) -> Self {
+        let mut ids = name_map(names.len());
+        let names: Vec<Symbol> = names.map(Symbol::from).collect();
+        for (id, name) in names.iter().enumerate() {
+            ids.entry(name.clone()).or_insert(id);
+        }
+        Symbols { names, ids }
+    }
+
+    /// The number of `name`, its index among the declared arguments.
+    pub(crate) fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    /// The interned name numbered `id`.
+    pub(crate) fn name(&self, id: usize) -> &Symbol {
+        &self.names[id]
+    }
+
+    /// The interned `name`, or a new symbol if it isn't declared.
+    pub(crate) fn intern(&self, name: &str) -> Symbol {
+        self.id(name).map_or_else(|| name.into(), |id| self.name(id).clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_the_declared_names() {
+        let symbols = Symbols::new(["l", "port", "l"].iter().copied());
+        assert_eq!(symbols.id("port"), Some(1));
+        assert_eq!(symbols.id("l"), Some(0));
+        assert_eq!(symbols.id("d"), None);
+        assert!(Arc::ptr_eq(&symbols.intern("port"), symbols.name(1)));
+        assert_eq!(&*symbols.intern("d"), "d");
+    }
+}
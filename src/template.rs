@@ -0,0 +1,138 @@
+use crate::schema::ArgKind;
+use crate::{ParseErr, ParsedArgs};
+
+/// Fills the `{name}` placeholders in `template` with parsed values, e.g.
+/// `backup-{d}-{p}.tar.gz`.
+///
+/// Values are formatted according to their type: lists are joined with
+/// `-`, and numbers accept a zero-padded width such as `{p:05}`. Every
+/// character of a substituted value other than ASCII letters, digits, `.`,
+/// `_` and `-` is percent-encoded, so a value like `/var/logs` can't
+/// smuggle path separators or spaces into the result. Write `{{` and `}}`
+/// for literal braces.
+///
+/// Fails with [`ParseErr::UnknownArg`] for names outside the schema,
+/// [`ParseErr::MissingValue`] for arguments without a value and
+/// [`ParseErr::InvalidTemplate`] for malformed placeholders.
+pub fn render_template(args: &ParsedArgs, template: &str) -> Result<String, ParseErr> {
+    let mut out = String::new();
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.as_str().starts_with('{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.as_str().starts_with('}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let rest = chars.as_str();
+                let end = rest
+                    .find('}')
+                    .ok_or_else(|| ParseErr::InvalidTemplate(format!("unclosed '{{{}'", rest)))?;
+                out.push_str(&escape(&placeholder(args, &rest[..end])?));
+                chars = rest[end + 1..].chars();
+            }
+            '}' => return Err(ParseErr::InvalidTemplate("unmatched '}'".to_string())),
+            c => out.push(c),
+        }
+    }
+    Ok(out)
+}
+
+fn placeholder(args: &ParsedArgs, placeholder: &str) -> Result<String, ParseErr> {
+    let (name, format) = match placeholder.split_once(':') {
+        Some((name, format)) => (name, Some(format)),
+        None => (placeholder, None),
+    };
+    let arg = args
+        .get(name)
+        .ok_or_else(|| ParseErr::unknown_arg(name))?;
+    let value = arg
+        .get()
+        .ok_or_else(|| ParseErr::MissingValue(name.to_string()))?;
+    let kind = args.kinds[name];
+    match (kind, format) {
+        (ArgKind::StrArray, None) | (ArgKind::NumberArray, None) => Ok(value.replace(',', "-")),
+        (_, None) => Ok(value),
+        (ArgKind::Number, Some(width)) if width.starts_with('0') => match width.parse() {
+            Ok(width) => Ok(format!("{:0width$}", arg.as_number().unwrap_or(0), width = width)),
+            Err(_) => Err(ParseErr::InvalidTemplate(format!("bad width in '{{{}}}'", placeholder))),
+        },
+        (_, Some(_)) => Err(ParseErr::InvalidTemplate(format!(
+            "unsupported format in '{{{}}}'",
+            placeholder
+        ))),
+    }
+}
+
+fn escape(value: &str) -> String {
+    let mut out = String::new();
+    for byte in value.bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'.' | b'_' | b'-' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    #[test]
+    fn substitutes_values() {
+        let args = parse("d*,p#", "-d logs -p 8080").unwrap();
+        assert_eq!(
+            render_template(&args, "backup-{d}-{p}.tar.gz").unwrap(),
+            "backup-logs-8080.tar.gz"
+        );
+    }
+
+    #[test]
+    fn type_aware_formatting() {
+        let args = parse("p#,s[*],l", "-p 42 -s a b c").unwrap();
+        assert_eq!(render_template(&args, "{p:05}_{s}_{l}").unwrap(), "00042_a-b-c_false");
+    }
+
+    #[test]
+    fn values_are_percent_encoded() {
+        let args = parse("d*", "-d /var/my%logs").unwrap();
+        assert_eq!(render_template(&args, "out/{d}.tar").unwrap(), "out/%2Fvar%2Fmy%25logs.tar");
+    }
+
+    #[test]
+    fn literal_braces() {
+        let args = parse("p#", "-p 1").unwrap();
+        assert_eq!(render_template(&args, "{{p}}={p}").unwrap(), "{p}=1");
+    }
+
+    #[test]
+    fn errors() {
+        let args = parse("p#,d*", "-p 1").unwrap();
+        assert_eq!(
+            render_template(&args, "{x}").unwrap_err(),
+            ParseErr::unknown_arg("x")
+        );
+        assert_eq!(
+            render_template(&args, "{d}").unwrap_err(),
+            ParseErr::MissingValue("d".to_string())
+        );
+        assert_eq!(
+            render_template(&args, "{p").unwrap_err(),
+            ParseErr::InvalidTemplate("unclosed '{p'".to_string())
+        );
+        assert_eq!(
+            render_template(&args, "p}").unwrap_err(),
+            ParseErr::InvalidTemplate("unmatched '}'".to_string())
+        );
+        assert_eq!(
+            render_template(&args, "{p:x}").unwrap_err(),
+            ParseErr::InvalidTemplate("unsupported format in '{p:x}'".to_string())
+        );
+    }
+}
@@ -0,0 +1,263 @@
+//! Assertions for tests of a command line, with the `test-util` feature:
+//! [`assert_parses!`](crate::assert_parses) checks what an input parses to,
+//! and [`assert_parse_err!`](crate::assert_parse_err) which error it fails
+//! with. Failures name the input and list every argument that differs.
+
+use crate::schema::flag;
+use crate::{ParseErr, ParsedArgs};
+use std::fmt::Debug;
+
+/// A value [`assert_parses!`](crate::assert_parses) compares an argument
+/// with, read with the getter for its type: bools with
+/// [`ParsedArgs::get_bool`], integers with [`ParsedArgs::get_number`],
+/// strings with [`ParsedArgs::get_str`] and lists of strings or integers as
+/// lists.
+pub trait Expected: Debug {
+    /// How the value of `name` differs from `self`, if it does.
+    fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String>;
+}
+
+fn compare<T, U>(expected: &T, actual: Option<T>, shown: U) -> Option<String>
+where
+    T: Debug + PartialEq,
+    U: Debug,
+{
+    match actual.as_ref() == Some(expected) {
+        true => None,
+        false => Some(format!("expected {:?}, got {:?}", expected, shown)),
+    }
+}
+
+impl Expected for bool {
+    fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String> {
+        let actual = args.get_bool(name);
+        compare(self, actual, actual)
+    }
+}
+
+macro_rules! expected_integers {
+    ($($integer:ty),*) => {$(
+        impl Expected for $integer {
+            fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String> {
+                let actual = args.get_number(name);
+                compare(&(*self as i128), actual.map(|n| n as i128), actual)
+            }
+        }
+
+        impl ListItem for $integer {
+            fn text(&self) -> String {
+                self.to_string()
+            }
+        }
+    )*};
+}
+
+expected_integers!(i32, i64, isize, u16, u32, u64, usize);
+
+impl Expected for &str {
+    fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String> {
+        let actual = args.get_str(name);
+        compare(&self.to_string(), actual.clone(), actual)
+    }
+}
+
+impl Expected for String {
+    fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String> {
+        self.as_str().mismatch(args, name)
+    }
+}
+
+impl<T: ListItem> Expected for [T] {
+    fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String> {
+        let expected: Vec<String> = self.iter().map(ListItem::text).collect();
+        let actual = args.get_list(name);
+        compare(&expected, Some(actual.clone()), actual)
+    }
+}
+
+impl<T: ListItem, const N: usize> Expected for [T; N] {
+    fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String> {
+        self[..].mismatch(args, name)
+    }
+}
+
+impl<T: ListItem> Expected for Vec<T> {
+    fn mismatch(&self, args: &ParsedArgs, name: &str) -> Option<String> {
+        self[..].mismatch(args, name)
+    }
+}
+
+/// An element of an expected list, compared as it reads in
+/// [`ParsedArgs::get_list`].
+pub trait ListItem: Debug {
+    fn text(&self) -> String;
+}
+
+impl ListItem for &str {
+    fn text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl ListItem for String {
+    fn text(&self) -> String {
+        self.clone()
+    }
+}
+
+/// Panics unless `result`, what `input` parsed to, holds each expected
+/// value; returns the result for further checks. The expansion of
+/// [`assert_parses!`](crate::assert_parses).
+#[doc(hidden)]
+pub fn check_parses(
+    input: &str,
+    result: Result<ParsedArgs, ParseErr>,
+    expected: &[(&str, &dyn Expected)],
+) -> ParsedArgs {
+    let args = match result {
+        Ok(args) => args,
+        Err(err) => panic!("`{}` failed to parse: {} ({:?})", input, err, err),
+    };
+    let mismatches: Vec<String> = expected
+        .iter()
+        .filter_map(|(name, value)| {
+            let mismatch = match args.value(name) {
+                Some(_) => value.mismatch(&args, name)?,
+                None => "not in the schema".to_string(),
+            };
+            Some(format!("  {}: {}", flag(name), mismatch))
+        })
+        .collect();
+    if !mismatches.is_empty() {
+        panic!("`{}` parsed differently than expected:\n{}", input, mismatches.join("\n"));
+    }
+    args
+}
+
+/// Parses `input` with a [`Schema`](crate::Schema) and asserts the value of
+/// each argument listed, see [`Expected`] for the types they can be given
+/// with. Names are identifiers or string literals, as in
+/// [`schema!`](crate::schema), and arguments not listed aren't checked.
+/// Evaluates to the [`ParsedArgs`]:
+///
+/// ```
+/// # #[cfg(feature = "test-util")]
+/// # {
+/// use args::{assert_parses, schema};
+///
+/// let schema = schema! { l: bool, p: number, "log-file": string, i: string_list };
+/// let args = assert_parses!(schema, "-l -p 8080 --log-file out.log -i a b", {
+///     l: true,
+///     p: 8080,
+///     "log-file": "out.log",
+///     i: ["a", "b"],
+/// });
+/// assert_eq!(args.occurrences_of("l"), 1);
+/// # }
+/// ```
+///
+/// On failure the message lists every argument that differs, e.g.
+/// `` `-p 80` parsed differently than expected: -p: expected 8080, got
+/// Some(80)``.
+#[macro_export]
+macro_rules! assert_parses {
+    ($schema:expr, $input:expr, { $($name:tt : $value:expr),* $(,)? }) => {{
+        let input: &str = $input;
+        $crate::test_util::check_parses(
+            input,
+            $schema.parse(input),
+            &[$((
+                $crate::assert_parses!(@name $name),
+                &$value as &dyn $crate::test_util::Expected,
+            )),*],
+        )
+    }};
+    (@name $name:ident) => {
+        stringify!($name)
+    };
+    (@name $name:literal) => {
+        $name
+    };
+}
+
+/// Parses `input` with a [`Schema`](crate::Schema) and asserts that it
+/// fails with an error matching `pattern`, optionally with a guard.
+/// Evaluates to the [`ParseErr`]:
+///
+/// ```
+/// # #[cfg(feature = "test-util")]
+/// # {
+/// use args::{assert_parse_err, ParseErr, Schema};
+///
+/// let schema = Schema::new("p#").unwrap();
+/// assert_parse_err!(schema, "-x", ParseErr::UnknownArg { arg, .. } if arg == "x");
+/// let err = assert_parse_err!(schema, "-p 80a", ParseErr::NumberFormatErr { .. });
+/// assert_eq!(err.value(), Some("80a"));
+/// # }
+/// ```
+#[macro_export]
+macro_rules! assert_parse_err {
+    ($schema:expr, $input:expr, $pattern:pat $(if $guard:expr)? $(,)?) => {{
+        let input: &str = $input;
+        match $schema.parse(input) {
+            Err(err) if matches!(&err, $pattern $(if $guard)?) => err,
+            other => panic!(
+                "`{}` was expected to fail with {}, got {:?}",
+                input,
+                stringify!($pattern),
+                other
+            ),
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ParseErr, Schema};
+
+    fn schema() -> Schema {
+        crate::schema! { l: bool, p: number, d: string, i: string_list, n: number_list }
+    }
+
+    #[test]
+    fn asserts_parsed_values() {
+        let args = assert_parses!(schema(), "-l -p 8080 -d /tmp -i a b -n 1 2", {
+            l: true,
+            p: 8080,
+            d: "/tmp",
+            i: ["a", "b"],
+            n: vec![1, 2],
+        });
+        assert_parses!(schema(), "-n 3 4 -p 5", { n: [3_u64, 4], p: 5_u64 });
+        assert_parses!(schema(), "-n 6", { n: vec![6_usize] });
+        assert_eq!(args.occurrences_of("l"), 1);
+        assert_parses!(&schema(), "", { l: false, i: Vec::<&str>::new() });
+    }
+
+    #[test]
+    #[should_panic(expected = "  -p: expected 8080, got Some(80)\n  -d: expected \"a\", got None")]
+    fn lists_every_difference() {
+        assert_parses!(schema(), "-p 80 -l", { l: true, p: 8080, d: "a" });
+    }
+
+    #[test]
+    #[should_panic(expected = "  -x: not in the schema")]
+    fn reports_unknown_names() {
+        assert_parses!(schema(), "-l", { x: true });
+    }
+
+    #[test]
+    fn asserts_errors() {
+        let err = assert_parse_err!(schema(), "-x", ParseErr::UnknownArg { arg, .. } if arg == "x");
+        assert_eq!(err.arg(), Some("x"));
+        assert_parse_err!(schema(), "-p a", ParseErr::NumberFormatErr { .. });
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "`-l` was expected to fail with ParseErr::UnknownArg { .. }, got Ok("
+    )]
+    fn fails_on_success() {
+        assert_parse_err!(schema(), "-l", ParseErr::UnknownArg { .. });
+    }
+}
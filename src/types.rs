@@ -0,0 +1,122 @@
+//! Value types of an application's own, for schema markers beyond the
+//! built-in ones. An argument with a registered marker takes one value,
+//! which must parse as the registered type, and is read back with
+//! [`ParsedArgs::get_as`](crate::ParsedArgs::get_as):
+//!
+//! ```
+//! use args::{types, Schema};
+//! use std::str::FromStr;
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Color {
+//!     Red,
+//!     Green,
+//! }
+//!
+//! impl FromStr for Color {
+//!     type Err = ();
+//!     fn from_str(s: &str) -> Result<Self, ()> {
+//!         match s {
+//!             "red" => Ok(Color::Red),
+//!             "green" => Ok(Color::Green),
+//!             _ => Err(()),
+//!         }
+//!     }
+//! }
+//!
+//! types::register::<Color>("%", "color").unwrap();
+//! let schema = Schema::new("c%,l").unwrap();
+//! assert_eq!(schema.usage("app"), "usage: app [-c <color>] [-l]");
+//! let args = schema.parse("-c red").unwrap();
+//! assert_eq!(args.get_as::<Color>("c"), Ok(Color::Red));
+//! assert!(schema.parse("-c blue").is_err());
+//! ```
+
+use crate::schema::{ArgKind, ArgSpec, Validator};
+use crate::ParseErr;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// A registered marker: the name shown in usage and the check its values
+/// must pass.
+#[derive(Clone)]
+struct CustomType {
+    name: String,
+    validator: Validator,
+}
+
+fn types() -> &'static Mutex<HashMap<String, CustomType>> {
+    static TYPES: OnceLock<Mutex<HashMap<String, CustomType>>> = OnceLock::new();
+    TYPES.get_or_init(Default::default)
+}
+
+/// Makes `marker` declare arguments whose values parse as a `T`, shown as
+/// `<name>` in usage. Fails with [`ParseErr::DuplicateMarker`] if the
+/// marker is built in or already registered, and with
+/// [`ParseErr::InvalidSchema`] if it is empty or contains a `,`.
+pub fn register<T: FromStr>(marker: &str, name: &str) -> Result<(), ParseErr> {
+    if marker.is_empty() || marker.contains(',') || marker.contains(char::is_whitespace) {
+        return Err(ParseErr::InvalidSchema);
+    }
+    let mut types = types().lock().unwrap_or_else(|e| e.into_inner());
+    if ArgKind::from_marker(marker).is_ok() || types.contains_key(marker) {
+        return Err(ParseErr::DuplicateMarker(marker.to_string()));
+    }
+    let type_name = name.to_string();
+    let check = move |value: &str| match value.parse::<T>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("not a valid {}", type_name)),
+    };
+    let custom = CustomType {
+        name: name.to_string(),
+        validator: Validator(Arc::new(check)),
+    };
+    types.insert(marker.to_string(), custom);
+    Ok(())
+}
+
+/// The spec of an argument `name` declared with the registered `marker`.
+pub(crate) fn spec(name: &str, marker: &str) -> Option<ArgSpec> {
+    let types = types().lock().unwrap_or_else(|e| e.into_inner());
+    let custom = types.get(marker)?;
+    let mut spec = ArgSpec::new(name, ArgKind::Str);
+    spec.custom = Some((marker.to_string(), custom.name.clone()));
+    spec.validators.push(custom.validator.clone());
+    Some(spec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Schema;
+
+    #[test]
+    fn registered_markers_declare_checked_args() {
+        register::<u8>("^", "byte").unwrap();
+        let schema = Schema::new("b^,p#").unwrap();
+        assert_eq!(schema.usage("app"), "usage: app [-b <byte>] [-p <number>]");
+        assert_eq!(schema.parse("-b 200").unwrap().get_as::<u8>("b"), Ok(200));
+        assert_eq!(
+            schema.parse("-b 300").unwrap_err(),
+            ParseErr::ValidationFailed {
+                arg: "b".to_string(),
+                message: "not a valid byte".to_string(),
+            }
+        );
+        assert_ne!(schema.fingerprint(), Schema::new("b*,p#").unwrap().fingerprint());
+    }
+
+    #[test]
+    fn rejects_taken_and_malformed_markers() {
+        register::<u8>("~", "test").unwrap();
+        assert_eq!(register::<u8>("~", "test"), Err(ParseErr::DuplicateMarker("~".to_string())));
+        assert_eq!(register::<u8>("#", "test"), Err(ParseErr::DuplicateMarker("#".to_string())));
+        assert_eq!(register::<u8>("", "test"), Err(ParseErr::InvalidSchema));
+        assert_eq!(register::<u8>("a,b", "test"), Err(ParseErr::InvalidSchema));
+        assert_eq!(
+            Schema::new("x&").unwrap_err(),
+            ParseErr::UnsupportedArgType("&".to_string())
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Pass/fail checking of input with every problem reported at once.
+
+use crate::schema::{Accumulation, ArgKind};
+use crate::{Input, ParseErr, Schema, TokensIterator};
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// Runs `input` through the same checks as [`Schema::parse`] (names,
+/// values, validators, occurrence limits, constraints and required
+/// arguments) without building a result, and reports every error found
+/// instead of only the first.
+pub fn validate(schema: &Schema, input: &str) -> Result<(), Vec<ParseErr>> {
+    for (_, arg) in &schema.inversions {
+        if schema.spec(arg).is_none_or(|spec| spec.kind != ArgKind::Bool) {
+            return Err(vec![ParseErr::InvalidSchema]);
+        }
+    }
+    schema.check_input_len(input.len()).map_err(|err| vec![err])?;
+    let expanded = schema.expand_response_files(Input::typed(input)).map_err(|err| vec![err])?;
+    let input = expanded.as_deref().unwrap_or(input);
+    schema.check_input_len(input.len()).map_err(|err| vec![err])?;
+    schema.check_builtin_flags(Input::typed(input)).map_err(|err| vec![err])?;
+    let mut errors = vec![];
+    let mut present = vec![];
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    let mut words = vec![];
+    let mut value_counts: HashMap<String, usize> = HashMap::new();
+    let mut tokens = TokensIterator::for_schema(input, schema).within_limits();
+    while let Some(token) = tokens.next() {
+        schema.check_quotes(&tokens).map_err(|err| vec![err])?;
+        words.append(&mut tokens.positionals);
+        let (given, text) = (token.name.into_owned(), &input[token.span.clone()]);
+        let name = schema.canonical_name(given.clone());
+        let taken: Vec<String> = token.values.into_iter().map(Cow::into_owned).collect();
+        let negated = schema.negated(&name);
+        let inverted = schema.inversions.iter().find(|(switch, _)| *switch == name);
+        if let Some(arg) = inverted.map(|(_, arg)| arg).or(negated.as_ref()) {
+            words.append(&mut schema.split_excess(ArgKind::Bool, taken).1);
+            present.push(arg.clone());
+            continue;
+        }
+        let spec = match schema.flag_spec(&name) {
+            Some(spec) => spec,
+            None if schema.collect_unknown => continue,
+            None => {
+                errors.push(schema.unknown_arg(name, &given, text));
+                continue;
+            }
+        };
+        let count = occurrences.entry(name.clone()).or_insert(0);
+        *count += 1;
+        if let Some(max) = spec.max_occurrences.filter(|max| *count == max + 1) {
+            errors.push(ParseErr::TooManyOccurrences { arg: name.clone(), max });
+        } else if spec.accumulation == Accumulation::Error && *count == 2 {
+            errors.push(ParseErr::TooManyOccurrences { arg: name.clone(), max: 1 });
+        }
+        let (values, mut excess) = schema.split_excess(spec.kind, taken);
+        words.append(&mut excess);
+        let values = match schema.expand_values(&name, values) {
+            Ok(values) => schema.split_delimited(&name, values),
+            Err(err) => {
+                errors.push(err);
+                vec![]
+            }
+        };
+        if let Err(err) = schema.check_values(&name, &values) {
+            errors.push(err);
+        }
+        let count = value_counts.entry(name.clone()).or_insert(0);
+        *count += values.len();
+        match schema.check_value_count(&name, *count) {
+            Err(err) if !errors.contains(&err) => errors.push(err),
+            _ => {}
+        }
+        let value = values.join("");
+        if spec.kind == ArgKind::Number && value.parse::<isize>().is_err() {
+            let span = Some(token.span.clone());
+            errors.push(ParseErr::NumberFormatErr { arg: name.clone(), value, span });
+        }
+        present.push(name);
+    }
+    schema.check_quotes(&tokens).map_err(|err| vec![err])?;
+    errors.extend(tokens.exceeded.take());
+    words.append(&mut tokens.positionals);
+    match schema.bind_positionals(words, &tokens.trailing) {
+        Ok(bound) => {
+            for (name, values) in bound {
+                let values = match schema.expand_values(&name, values) {
+                    Ok(values) => schema.split_delimited(&name, values),
+                    Err(err) => {
+                        errors.push(err);
+                        vec![]
+                    }
+                };
+                if let Err(err) = schema.check_values(&name, &values) {
+                    errors.push(err);
+                }
+                present.push(name);
+            }
+        }
+        Err(err) => errors.push(err),
+    }
+    errors.extend(schema.constraint_errors(&present));
+    for spec in &schema.specs {
+        if spec.required && spec.default.is_none() && !present.contains(&spec.name) {
+            errors.push(ParseErr::MissingArg(spec.name.clone()));
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_every_error() {
+        let schema = Schema::builder()
+            .number("p")
+            .string("d")
+            .required()
+            .bool("l")
+            .build()
+            .unwrap()
+            .conflicts_with("l", "p");
+        assert_eq!(
+            validate(&schema, "-p x -p 1 -x -l"),
+            Err(vec![
+                ParseErr::NumberFormatErr { arg: "p".into(), value: "x".into(), span: Some(0..4) },
+                ParseErr::TooManyOccurrences { arg: "p".to_string(), max: 1 },
+                ParseErr::UnknownArg {
+                    arg: "x".into(),
+                    typed: "-x".into(),
+                    suggestion: Some("-d".into()),
+                },
+                ParseErr::ConflictingArgs { arg: "l".to_string(), other: "p".to_string() },
+                ParseErr::MissingArg("d".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_all_collects_errors_or_parses() {
+        let schema = Schema::new("p#,n#,l")
+            .unwrap()
+            .validate_with(|args| match args.get_number("p") {
+                Some(0) => Err(ParseErr::ValidationFailed {
+                    arg: "p".to_string(),
+                    message: "zero".to_string(),
+                }),
+                _ => Ok(()),
+            });
+        assert_eq!(
+            schema.parse_all("-p x -n y -z").unwrap_err(),
+            vec![
+                ParseErr::NumberFormatErr { arg: "p".into(), value: "x".into(), span: Some(0..4) },
+                ParseErr::NumberFormatErr { arg: "n".into(), value: "y".into(), span: Some(5..9) },
+                ParseErr::UnknownArg {
+                    arg: "z".into(),
+                    typed: "-z".into(),
+                    suggestion: Some("-l".into()),
+                },
+            ]
+        );
+        assert_eq!(
+            schema.parse_all("-p 0").unwrap_err(),
+            vec![ParseErr::ValidationFailed { arg: "p".to_string(), message: "zero".to_string() }]
+        );
+        assert_eq!(schema.parse_all("-p 8080 -l").unwrap().get_number("p"), Some(8080));
+    }
+
+    #[test]
+    fn agrees_with_parse_on_valid_input() {
+        let schema = Schema::new("l,p#,d*").unwrap().inverted("q", "l");
+        for input in ["", "-l -p 8080 -d /tmp", "-q"] {
+            assert_eq!(validate(&schema, input), Ok(()));
+            assert!(schema.parse(input).is_ok());
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{ParseWarning, Schema};
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Receives the warnings raised while parsing with a schema, see
+/// [`Schema::warning_sink`].
+pub trait WarningSink: Send + Sync {
+    fn emit(&self, schema: &Schema, warning: &ParseWarning);
+}
+
+/// Prints each warning to stderr as `warning: ...`.
+pub struct StderrSink;
+
+impl WarningSink for StderrSink {
+    fn emit(&self, _schema: &Schema, warning: &ParseWarning) {
+        eprintln!("warning: {}", warning);
+    }
+}
+
+/// Forwards each distinct warning only once, so that parsing many inputs
+/// in a batch doesn't repeat the same deprecation notice over and over.
+///
+/// By default a warning is repeated for every schema it comes from; with
+/// [`Deduplicated::across_schemas`] it is reported once no matter which
+/// schema raised it. Share one instance (e.g. in a `static` or an `Arc`) to
+/// deduplicate for the whole process.
+pub struct Deduplicated<S> {
+    inner: S,
+    per_schema: bool,
+    seen: Mutex<HashSet<(String, ParseWarning)>>,
+}
+
+impl<S: WarningSink> Deduplicated<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            per_schema: true,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn across_schemas(mut self) -> Self {
+        self.per_schema = false;
+        self
+    }
+}
+
+impl<S: WarningSink> WarningSink for Deduplicated<S> {
+    fn emit(&self, schema: &Schema, warning: &ParseWarning) {
+        let scope = if self.per_schema {
+            schema.fingerprint()
+        } else {
+            String::new()
+        };
+        let first = self
+            .seen
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert((scope, warning.clone()));
+        if first {
+            self.inner.emit(schema, warning);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<String>>);
+
+    impl WarningSink for Arc<Collect> {
+        fn emit(&self, _schema: &Schema, warning: &ParseWarning) {
+            self.0.lock().unwrap().push(warning.to_string());
+        }
+    }
+
+    #[test]
+    fn every_warning_reaches_a_plain_sink() {
+        let collected = Arc::new(Collect::default());
+        let schema = Schema::new("o*,d*")
+            .unwrap()
+            .deprecated("o", Some("d"))
+            .warning_sink(Arc::new(collected.clone()));
+        schema.parse("-o a").unwrap();
+        schema.parse("-o b").unwrap();
+        assert_eq!(
+            *collected.0.lock().unwrap(),
+            vec!["-o is deprecated, use -d instead"; 2]
+        );
+    }
+
+    #[test]
+    fn deduplicated_sink_reports_once_per_schema() {
+        let collected = Arc::new(Collect::default());
+        let sink: Arc<dyn WarningSink> = Arc::new(Deduplicated::new(collected.clone()));
+        let first = Schema::new("x")
+            .unwrap()
+            .deprecated("x", None)
+            .warning_sink(sink.clone());
+        let second = Schema::new("x,y")
+            .unwrap()
+            .deprecated("x", None)
+            .warning_sink(sink);
+        for _ in 0..3 {
+            first.parse("-x").unwrap();
+            second.parse("-x").unwrap();
+        }
+        assert_eq!(collected.0.lock().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn deduplicated_sink_across_schemas() {
+        let collected = Arc::new(Collect::default());
+        let sink: Arc<dyn WarningSink> =
+            Arc::new(Deduplicated::new(collected.clone()).across_schemas());
+        for definition in &["x", "x,y"] {
+            let schema = Schema::new(definition)
+                .unwrap()
+                .deprecated("x", None)
+                .warning_sink(sink.clone());
+            schema.parse("-x").unwrap();
+        }
+        assert_eq!(*collected.0.lock().unwrap(), vec!["-x is deprecated"]);
+    }
+
+    #[test]
+    fn warnings_are_still_returned_with_a_sink() {
+        let schema = Schema::new("x")
+            .unwrap()
+            .deprecated("x", None)
+            .warning_sink(Arc::new(Deduplicated::new(StderrSink)));
+        let args = schema.parse("-x").unwrap();
+        assert_eq!(args.warnings().len(), 1);
+    }
+}
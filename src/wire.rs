@@ -0,0 +1,781 @@
+//! A compact binary encoding of [`Schema`] and [`ParsedArgs`], so that one
+//! process can parse and validate arguments and hand the result to another
+//! over IPC.
+//!
+//! Every message starts with a two-byte tag and a version byte. Lengths and
+//! counts are LEB128 varints, strings are a length followed by UTF-8 bytes
+//! and optional values are prefixed with `0` (absent) or `1` (present).
+
+use crate::hash::NameMap;
+use crate::schema::{
+    AbsentBool, Accumulation, ArgKind, ArgSpec, Classification, Constraint, PathCheck,
+};
+use crate::{
+    Args, NoMatches, Normalization, ParseErr, ParseWarning, ParsedArgs, RawToken, Schema, Source,
+    ValueHint,
+};
+use std::collections::HashMap;
+
+const SCHEMA_TAG: &[u8; 2] = b"AS";
+const PARSED_ARGS_TAG: &[u8; 2] = b"AP";
+const VERSION: u8 = 1;
+
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn header(tag: &[u8; 2]) -> Self {
+        let mut writer = Writer::default();
+        writer.0.extend_from_slice(tag);
+        writer.0.push(VERSION);
+        writer
+    }
+
+    fn byte(&mut self, byte: u8) {
+        self.0.push(byte);
+    }
+
+    fn len(&mut self, mut len: usize) {
+        loop {
+            let byte = (len & 0x7f) as u8;
+            len >>= 7;
+            if len == 0 {
+                self.0.push(byte);
+                return;
+            }
+            self.0.push(byte | 0x80);
+        }
+    }
+
+    fn str(&mut self, s: &str) {
+        self.len(s.len());
+        self.0.extend_from_slice(s.as_bytes());
+    }
+
+    fn opt_str(&mut self, s: Option<&str>) {
+        match s {
+            Some(s) => {
+                self.byte(1);
+                self.str(s);
+            }
+            None => self.byte(0),
+        }
+    }
+
+    fn pairs(&mut self, pairs: &[(String, String)]) {
+        self.len(pairs.len());
+        for (a, b) in pairs {
+            self.str(a);
+            self.str(b);
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn header(bytes: &'a [u8], tag: &[u8; 2]) -> Result<Self, ParseErr> {
+        let mut reader = Reader(bytes);
+        if reader.take(2)? != tag || reader.byte()? != VERSION {
+            return Err(ParseErr::InvalidWireFormat);
+        }
+        Ok(reader)
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], ParseErr> {
+        if self.0.len() < n {
+            return Err(ParseErr::InvalidWireFormat);
+        }
+        let (taken, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, ParseErr> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn len(&mut self) -> Result<usize, ParseErr> {
+        let mut len = 0usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let byte = self.byte()?;
+            len |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(len);
+            }
+        }
+        Err(ParseErr::InvalidWireFormat)
+    }
+
+    fn str(&mut self) -> Result<String, ParseErr> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| ParseErr::InvalidWireFormat)
+    }
+
+    fn opt_str(&mut self) -> Result<Option<String>, ParseErr> {
+        match self.byte()? {
+            0 => Ok(None),
+            1 => Ok(Some(self.str()?)),
+            _ => Err(ParseErr::InvalidWireFormat),
+        }
+    }
+
+    fn pairs(&mut self) -> Result<Vec<(String, String)>, ParseErr> {
+        (0..self.len()?).map(|_| Ok((self.str()?, self.str()?))).collect()
+    }
+
+    fn finish(self) -> Result<(), ParseErr> {
+        if self.0.is_empty() {
+            Ok(())
+        } else {
+            Err(ParseErr::InvalidWireFormat)
+        }
+    }
+}
+
+fn kind_to_byte(kind: ArgKind) -> u8 {
+    match kind {
+        ArgKind::Bool => 0,
+        ArgKind::Str => 1,
+        ArgKind::Number => 2,
+        ArgKind::StrArray => 3,
+        ArgKind::NumberArray => 4,
+        ArgKind::OptStr => 5,
+    }
+}
+
+fn kind_from_byte(byte: u8) -> Result<ArgKind, ParseErr> {
+    match byte {
+        0 => Ok(ArgKind::Bool),
+        1 => Ok(ArgKind::Str),
+        2 => Ok(ArgKind::Number),
+        3 => Ok(ArgKind::StrArray),
+        4 => Ok(ArgKind::NumberArray),
+        5 => Ok(ArgKind::OptStr),
+        _ => Err(ParseErr::InvalidWireFormat),
+    }
+}
+
+fn source_to_byte(source: Option<Source>) -> u8 {
+    match source {
+        None => 0,
+        Some(Source::CommandLine) => 1,
+        Some(Source::Env) => 2,
+        Some(Source::DotEnv) => 3,
+        Some(Source::Default) => 4,
+        Some(Source::Config) => 5,
+        Some(Source::Prompt) => 6,
+        Some(Source::Preset) => 7,
+    }
+}
+
+fn source_from_byte(byte: u8) -> Result<Option<Source>, ParseErr> {
+    match byte {
+        0 => Ok(None),
+        1 => Ok(Some(Source::CommandLine)),
+        2 => Ok(Some(Source::Env)),
+        3 => Ok(Some(Source::DotEnv)),
+        4 => Ok(Some(Source::Default)),
+        5 => Ok(Some(Source::Config)),
+        6 => Ok(Some(Source::Prompt)),
+        7 => Ok(Some(Source::Preset)),
+        _ => Err(ParseErr::InvalidWireFormat),
+    }
+}
+
+impl Schema {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::header(SCHEMA_TAG);
+        w.len(self.specs.len());
+        for spec in &self.specs {
+            w.str(&spec.name);
+            w.byte(kind_to_byte(spec.kind));
+            w.byte(spec.required as u8);
+            w.byte(spec.positional as u8);
+            w.len(spec.max_occurrences.map_or(0, |max| max + 1));
+            w.byte(match spec.accumulation {
+                Accumulation::Append => 0,
+                Accumulation::Replace => 1,
+                Accumulation::Error => 2,
+                Accumulation::Keep => 3,
+                Accumulation::Concat => 4,
+            });
+            w.byte(match spec.classification {
+                Classification::Public => 0,
+                Classification::Internal => 1,
+                Classification::Secret => 2,
+            });
+            w.opt_str(spec.default.as_deref());
+            w.opt_str(spec.description.as_deref());
+            w.opt_str(spec.implicit.as_deref());
+            w.opt_str(spec.delimiter.map(String::from).as_deref());
+            w.opt_str(spec.separator.as_deref());
+            w.opt_str(spec.custom.as_ref().map(|(marker, _)| marker.as_str()));
+            w.opt_str(spec.metavar.as_deref());
+            w.len(spec.choices.len());
+            for choice in &spec.choices {
+                w.str(choice);
+            }
+            w.byte(spec.fold_choices as u8);
+            w.byte(match spec.hint {
+                None => 0,
+                Some(ValueHint::File) => 1,
+                Some(ValueHint::Dir) => 2,
+            });
+            w.byte(match spec.path_check {
+                None => 0,
+                Some(PathCheck::Exists) => 1,
+                Some(PathCheck::File) => 2,
+                Some(PathCheck::Dir) => 3,
+                Some(PathCheck::ParentExists) => 4,
+                Some(PathCheck::CreateParent) => 5,
+            });
+            w.byte(match spec.glob {
+                None => 0,
+                Some(NoMatches::Fail) => 1,
+                Some(NoMatches::Keep) => 2,
+                Some(NoMatches::Skip) => 3,
+            });
+            w.opt_str(spec.env.as_deref());
+            w.byte(match spec.absent {
+                AbsentBool::False => 0,
+                AbsentBool::Unset => 1,
+            });
+            w.len(spec.defaults_if.len());
+            for (other, value, default) in &spec.defaults_if {
+                w.str(other);
+                w.str(value);
+                w.str(default);
+            }
+        }
+        w.len(self.constraints.len());
+        for constraint in &self.constraints {
+            let (tag, arg, other) = match constraint {
+                Constraint::Requires(arg, other) => (0, arg, other),
+                Constraint::ConflictsWith(arg, other) => (1, arg, other),
+                Constraint::OneOf(args, exactly) => {
+                    w.byte(2);
+                    w.byte(*exactly as u8);
+                    w.len(args.len());
+                    for arg in args {
+                        w.str(arg);
+                    }
+                    continue;
+                }
+                Constraint::RequiredIf(arg, other, value) => {
+                    w.byte(3);
+                    w.str(arg);
+                    w.str(other);
+                    w.str(value);
+                    continue;
+                }
+            };
+            w.byte(tag);
+            w.str(arg);
+            w.str(other);
+        }
+        w.pairs(&self.inversions);
+        w.len(self.hidden.len());
+        for hidden in &self.hidden {
+            w.str(hidden);
+        }
+        w.len(self.deprecations.len());
+        for (arg, replacement) in &self.deprecations {
+            w.str(arg);
+            w.opt_str(replacement.as_deref());
+        }
+        w.byte(self.case_insensitive as u8);
+        w.str(&self.prefixes);
+        w.byte(self.response_files as u8);
+        w.byte(self.expand_env as u8);
+        w.byte(self.expand_tilde as u8);
+        w.byte(self.shell_words as u8);
+        w.byte(self.capture_rest as u8);
+        w.byte(self.collect_unknown as u8);
+        w.opt_str(self.help_program.as_deref());
+        w.opt_str(self.version.as_deref());
+        w.byte(self.prompt as u8);
+        let limits = &self.limits;
+        for limit in [
+            limits.max_input_len,
+            limits.max_tokens,
+            limits.max_values,
+            limits.max_response_file_depth,
+        ] {
+            w.len(limit.map_or(0, |limit| limit + 1));
+        }
+        w.byte(self.abbreviations as u8);
+        w.byte(self.single_dash as u8);
+        w.len(self.presets.len());
+        for (name, values) in &self.presets {
+            w.str(name);
+            w.len(values.len());
+            for (arg, value) in values {
+                w.str(arg);
+                w.str(value);
+            }
+        }
+        w.opt_str(self.preset_arg.as_deref());
+        w.0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Schema, ParseErr> {
+        let mut r = Reader::header(bytes, SCHEMA_TAG)?;
+        let mut specs = vec![];
+        for _ in 0..r.len()? {
+            let mut spec = ArgSpec::new(&r.str()?, kind_from_byte(r.byte()?)?);
+            spec.required = match r.byte()? {
+                0 => false,
+                1 => true,
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.positional = match r.byte()? {
+                0 => false,
+                1 => true,
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.max_occurrences = r.len()?.checked_sub(1);
+            spec.accumulation = match r.byte()? {
+                0 => Accumulation::Append,
+                1 => Accumulation::Replace,
+                2 => Accumulation::Error,
+                3 => Accumulation::Keep,
+                4 => Accumulation::Concat,
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.classification = match r.byte()? {
+                0 => Classification::Public,
+                1 => Classification::Internal,
+                2 => Classification::Secret,
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.default = r.opt_str()?;
+            spec.description = r.opt_str()?;
+            spec.implicit = r.opt_str()?;
+            spec.delimiter = match r.opt_str()? {
+                Some(delimiter) => {
+                    let mut chars = delimiter.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Some(c),
+                        _ => return Err(ParseErr::InvalidWireFormat),
+                    }
+                }
+                None => None,
+            };
+            spec.separator = r.opt_str()?;
+            if let Some(marker) = r.opt_str()? {
+                let custom = crate::types::spec(&spec.name, &marker)
+                    .ok_or(ParseErr::UnsupportedArgType(marker))?;
+                spec.custom = custom.custom;
+                spec.validators = custom.validators;
+            }
+            spec.metavar = r.opt_str()?;
+            for _ in 0..r.len()? {
+                spec.choices.push(r.str()?);
+            }
+            spec.fold_choices = match r.byte()? {
+                0 => false,
+                1 => true,
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.hint = match r.byte()? {
+                0 => None,
+                1 => Some(ValueHint::File),
+                2 => Some(ValueHint::Dir),
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.path_check = match r.byte()? {
+                0 => None,
+                1 => Some(PathCheck::Exists),
+                2 => Some(PathCheck::File),
+                3 => Some(PathCheck::Dir),
+                4 => Some(PathCheck::ParentExists),
+                5 => Some(PathCheck::CreateParent),
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.glob = match r.byte()? {
+                0 => None,
+                1 => Some(NoMatches::Fail),
+                2 => Some(NoMatches::Keep),
+                3 => Some(NoMatches::Skip),
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            spec.env = r.opt_str()?;
+            spec.absent = match r.byte()? {
+                0 => AbsentBool::False,
+                1 => AbsentBool::Unset,
+                _ => return Err(ParseErr::InvalidWireFormat),
+            };
+            for _ in 0..r.len()? {
+                spec.defaults_if.push((r.str()?, r.str()?, r.str()?));
+            }
+            specs.push(spec);
+        }
+        let mut schema = Schema::from_specs(specs);
+        for _ in 0..r.len()? {
+            let tag = r.byte()?;
+            if tag == 2 {
+                let exactly = r.byte()? != 0;
+                let args = (0..r.len()?).map(|_| r.str()).collect::<Result<_, _>>()?;
+                schema.constraints.push(Constraint::OneOf(args, exactly));
+                continue;
+            }
+            if tag == 3 {
+                let (arg, other, value) = (r.str()?, r.str()?, r.str()?);
+                schema.constraints.push(Constraint::RequiredIf(arg, other, value));
+                continue;
+            }
+            let (arg, other) = (r.str()?, r.str()?);
+            schema.constraints.push(match tag {
+                0 => Constraint::Requires(arg, other),
+                1 => Constraint::ConflictsWith(arg, other),
+                _ => return Err(ParseErr::InvalidWireFormat),
+            });
+        }
+        schema.inversions = r.pairs()?;
+        for _ in 0..r.len()? {
+            schema.hidden.push(r.str()?);
+        }
+        for _ in 0..r.len()? {
+            schema.deprecations.push((r.str()?, r.opt_str()?));
+        }
+        schema.case_insensitive = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.prefixes = r.str()?;
+        schema.response_files = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.expand_env = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.expand_tilde = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.shell_words = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.capture_rest = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.collect_unknown = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.help_program = r.opt_str()?;
+        schema.version = r.opt_str()?;
+        schema.prompt = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.limits.max_input_len = r.len()?.checked_sub(1);
+        schema.limits.max_tokens = r.len()?.checked_sub(1);
+        schema.limits.max_values = r.len()?.checked_sub(1);
+        schema.limits.max_response_file_depth = r.len()?.checked_sub(1);
+        schema.abbreviations = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        schema.single_dash = match r.byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(ParseErr::InvalidWireFormat),
+        };
+        for _ in 0..r.len()? {
+            let name = r.str()?;
+            let values = (0..r.len()?).map(|_| Ok((r.str()?, r.str()?)));
+            schema.presets.push((name, values.collect::<Result<_, ParseErr>>()?));
+        }
+        schema.preset_arg = r.opt_str()?;
+        r.finish()?;
+        Ok(schema)
+    }
+}
+
+impl ParsedArgs {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer::header(PARSED_ARGS_TAG);
+        let mut names: Vec<&str> = self.args.keys().map(|name| &**name).collect();
+        names.sort();
+        w.len(names.len());
+        for name in names {
+            w.str(name);
+            w.opt_str(self.args[name].get().as_deref());
+            w.byte(source_to_byte(self.source(name)));
+            w.len(self.occurrences_of(name));
+            let given = self.values_of(name).unwrap_or_default();
+            w.len(given.len());
+            for value in given {
+                w.str(value);
+            }
+        }
+        w.len(self.warnings.len());
+        for warning in &self.warnings {
+            match warning {
+                ParseWarning::Deprecated(arg, replacement) => {
+                    w.byte(0);
+                    w.str(arg);
+                    w.opt_str(replacement.as_deref());
+                }
+                ParseWarning::Overridden(arg) => {
+                    w.byte(1);
+                    w.str(arg);
+                }
+                ParseWarning::Ignored(arg) => {
+                    w.byte(2);
+                    w.str(arg);
+                }
+            }
+        }
+        w.len(self.normalizations.len());
+        for normalization in &self.normalizations {
+            let (tag, given, read) = match normalization {
+                Normalization::CaseFolded(given, name) => (0, given, name),
+                Normalization::Unquoted(given, read) => (1, given, read),
+                Normalization::Abbreviated(given, name) => (2, given, name),
+            };
+            w.byte(tag);
+            w.str(given);
+            w.str(read);
+        }
+        w.len(self.trailing.len());
+        for word in &self.trailing {
+            w.str(word);
+        }
+        w.len(self.unknown.len());
+        for word in &self.unknown {
+            w.str(word);
+        }
+        w.str(&self.input);
+        w.len(self.tokens.len());
+        for token in &self.tokens {
+            w.str(&token.name);
+            w.len(token.values.len());
+            for value in &token.values {
+                w.str(value);
+            }
+            w.len(token.span.start);
+            w.len(token.span.end);
+        }
+        w.0
+    }
+
+    /// Decodes arguments encoded by [`ParsedArgs::to_bytes`]. Every value is
+    /// set again through `schema`, so the receiving side re-validates names
+    /// and types instead of trusting the sender.
+    pub fn from_bytes(schema: &Schema, bytes: &[u8]) -> Result<ParsedArgs, ParseErr> {
+        let mut r = Reader::header(bytes, PARSED_ARGS_TAG)?;
+        let mut args = NameMap::default();
+        let mut sources = NameMap::default();
+        let mut occurrences = NameMap::default();
+        let mut given = NameMap::default();
+        for _ in 0..r.len()? {
+            let name = r.str()?;
+            let spec = schema
+                .spec(&name)
+                .ok_or_else(|| ParseErr::unknown_arg(&name))?;
+            let name = schema.symbol(&name);
+            let mut arg = spec.new_arg();
+            if let Some(value) = r.opt_str()? {
+                let values = match spec.kind {
+                    ArgKind::OptStr if spec.implicit.as_deref() == Some(value.as_str()) => vec![],
+                    ArgKind::StrArray | ArgKind::NumberArray if value.is_empty() => vec![],
+                    ArgKind::StrArray | ArgKind::NumberArray => {
+                        value.split(',').map(ToString::to_string).collect()
+                    }
+                    _ => vec![value],
+                };
+                arg.set(&name, values)?;
+            }
+            if let Some(source) = source_from_byte(r.byte()?)? {
+                sources.insert(name.clone(), source);
+            }
+            let count = r.len()?;
+            let mut values = vec![];
+            for _ in 0..r.len()? {
+                values.push(r.str()?);
+            }
+            if count > 0 {
+                occurrences.insert(name.clone(), count);
+                given.insert(name.clone(), values);
+            }
+            args.insert(name, arg);
+        }
+        if args.len() != schema.specs.len() {
+            return Err(ParseErr::InvalidWireFormat);
+        }
+        let mut warnings = vec![];
+        for _ in 0..r.len()? {
+            match r.byte()? {
+                0 => warnings.push(ParseWarning::Deprecated(r.str()?, r.opt_str()?)),
+                1 => warnings.push(ParseWarning::Overridden(r.str()?)),
+                2 => warnings.push(ParseWarning::Ignored(r.str()?)),
+                _ => return Err(ParseErr::InvalidWireFormat),
+            }
+        }
+        let mut normalizations = vec![];
+        for _ in 0..r.len()? {
+            match r.byte()? {
+                0 => normalizations.push(Normalization::CaseFolded(r.str()?, r.str()?)),
+                1 => normalizations.push(Normalization::Unquoted(r.str()?, r.str()?)),
+                2 => normalizations.push(Normalization::Abbreviated(r.str()?, r.str()?)),
+                _ => return Err(ParseErr::InvalidWireFormat),
+            }
+        }
+        let mut trailing = vec![];
+        for _ in 0..r.len()? {
+            trailing.push(r.str()?);
+        }
+        let mut unknown = vec![];
+        for _ in 0..r.len()? {
+            unknown.push(r.str()?);
+        }
+        let input = r.str()?;
+        let mut tokens = vec![];
+        for _ in 0..r.len()? {
+            let name = r.str()?;
+            let values = (0..r.len()?).map(|_| r.str()).collect::<Result<_, _>>()?;
+            let span = r.len()?..r.len()?;
+            let text = input.get(span.clone()).ok_or(ParseErr::InvalidWireFormat)?;
+            tokens.push(RawToken { name, values, text: text.to_string(), span });
+        }
+        r.finish()?;
+        Ok(ParsedArgs {
+            args,
+            order: schema.interned().map(|(name, _)| name.clone()).collect(),
+            positionals: schema.positional_names(),
+            kinds: schema.kinds(),
+            classifications: schema.classifications(),
+            warnings,
+            normalizations,
+            sources,
+            occurrences,
+            given,
+            trailing,
+            unknown,
+            tokens,
+            input,
+            os_words: vec![],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Limits;
+
+    fn schema() -> Schema {
+        Schema::new("l,p#,d*,s[*],n[#]")
+            .unwrap()
+            .requires("d", "p")
+            .conflicts_with("l", "s")
+            .any_of(&["l", "d", "s"])
+            .required_if("s", "p", "443")
+            .inverted("no-l", "l")
+            .hidden("n")
+            .deprecated("d", Some("s"))
+            .accumulate("s", Accumulation::Replace)
+            .accumulate("d", Accumulation::Concat)
+            .separator("d", " ")
+            .delimiter("n", ':')
+            .preset("fast", &[("p", "1")])
+            .unwrap()
+            .glob("s", NoMatches::Keep)
+            .case_insensitive()
+            .abbreviations()
+            .flag_prefixes("/")
+            .limits(Limits { max_tokens: Some(64), ..Limits::default() })
+            .absent_bool("l", AbsentBool::Unset)
+    }
+
+    #[test]
+    fn schema_round_trip() {
+        let schema = schema();
+        let decoded = Schema::from_bytes(&schema.to_bytes()).unwrap();
+        assert_eq!(decoded.to_bytes(), schema.to_bytes());
+        assert_eq!(decoded.usage("app"), schema.usage("app"));
+        assert_eq!(decoded.limits, schema.limits);
+        assert_eq!(decoded.spec("l").unwrap().absent_bool(), AbsentBool::Unset);
+        assert_eq!(decoded.presets().collect::<Vec<_>>(), vec!["fast"]);
+        assert_eq!(
+            decoded.parse("-d /tmp").unwrap_err(),
+            ParseErr::MissingRequiredArg { arg: "d".to_string(), required: "p".to_string() }
+        );
+    }
+
+    #[test]
+    fn custom_types_round_trip() {
+        crate::types::register::<u16>("%", "port").unwrap();
+        let schema = Schema::new("p%").unwrap();
+        let decoded = Schema::from_bytes(&schema.to_bytes()).unwrap();
+        assert_eq!(decoded.usage("app"), "usage: app [-p <port>]");
+        assert!(decoded.parse("-p 70000").is_err());
+    }
+
+    #[test]
+    fn parsed_args_round_trip() {
+        let schema = schema();
+        let args = schema.parse("-P 8080 -d '/var/logs' -n 1 2 3 --no-l -- rest").unwrap();
+        let decoded = ParsedArgs::from_bytes(&schema, &args.to_bytes()).unwrap();
+        assert_eq!(decoded.get("p").unwrap().as_number(), Some(8080));
+        assert_eq!(decoded.get("d").unwrap().get().unwrap(), "/var/logs");
+        assert_eq!(decoded.get("n").unwrap().as_num_array(), vec![1, 2, 3]);
+        assert_eq!(decoded.get("s").unwrap().get().unwrap(), "");
+        assert!(!decoded.get("l").unwrap().as_bool().unwrap());
+        assert_eq!(decoded.source("p"), Some(Source::CommandLine));
+        assert_eq!(decoded.source("s"), None);
+        assert_eq!(decoded.occurrences_of("l"), 1);
+        assert!(!decoded.is_present("s"));
+        assert_eq!(decoded.values_of("n"), args.values_of("n"));
+        assert_eq!(decoded.warnings(), args.warnings());
+        assert_eq!(decoded.normalizations(), args.normalizations());
+        assert_eq!(decoded.tokens(), args.tokens());
+        assert_eq!(decoded.trailing(), &["rest"]);
+        assert_eq!(decoded.to_bytes(), args.to_bytes());
+    }
+
+    #[test]
+    fn decoding_revalidates_against_the_schema() {
+        let sender = Schema::new("p*").unwrap();
+        let bytes = sender.parse("-p http").unwrap().to_bytes();
+        let receiver = Schema::new("p#").unwrap();
+        assert_eq!(
+            ParsedArgs::from_bytes(&receiver, &bytes).unwrap_err(),
+            ParseErr::NumberFormatErr { arg: "p".into(), value: "http".into(), span: None }
+        );
+        let receiver = Schema::new("d*").unwrap();
+        assert_eq!(
+            ParsedArgs::from_bytes(&receiver, &bytes).unwrap_err(),
+            ParseErr::unknown_arg("p")
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        let bytes = schema().to_bytes();
+        assert_eq!(
+            Schema::from_bytes(&bytes[..bytes.len() - 1]).unwrap_err(),
+            ParseErr::InvalidWireFormat
+        );
+        assert_eq!(Schema::from_bytes(b"AP\x01").unwrap_err(), ParseErr::InvalidWireFormat);
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert_eq!(Schema::from_bytes(&trailing).unwrap_err(), ParseErr::InvalidWireFormat);
+    }
+}